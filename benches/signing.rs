@@ -0,0 +1,173 @@
+//! Benchmarks for the signing hot path
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use prost_amino::Message;
+use signatory::{ed25519, encoding::Decode, public_key::PublicKeyed};
+use signatory_dalek::Ed25519Signer;
+use std::{
+    io::{Cursor, Read, Write},
+    os::unix::net::UnixStream,
+    thread,
+};
+use tempfile::TempDir;
+use tendermint::{
+    amino_types::{vote::Vote, BlockId, PartsSetHeader, SignVoteRequest, SignedVoteResponse},
+    consensus, TendermintKey,
+};
+use tmkms::{
+    chain::State,
+    config::chain::StateFormat,
+    connection::secret_connection::{PublicKey, SecretConnection},
+    keyring::{self, KeyRing, SecretKeyEncoding, SigningProvider},
+    rpc::Request,
+};
+
+/// Path to the example validator signing key
+const SIGNING_KEY_PATH: &str = "tests/support/signing.key";
+
+/// Example message to sign (roughly the size of a vote's sign bytes)
+const EXAMPLE_MSG: &[u8] = &[0u8; 128];
+
+/// Load the example signer used by the integration tests
+fn test_signer() -> Ed25519Signer {
+    let seed =
+        ed25519::Seed::decode_from_file(SIGNING_KEY_PATH, &SecretKeyEncoding::default()).unwrap();
+
+    Ed25519Signer::from(&seed)
+}
+
+/// Example `SignVoteRequest`
+fn example_vote_request() -> SignVoteRequest {
+    SignVoteRequest {
+        vote: Some(Vote {
+            vote_type: 0x01,
+            height: 12345,
+            round: 2,
+            timestamp: None,
+            block_id: Some(BlockId {
+                hash: b"some hash00000000000000000000000".to_vec(),
+                parts_header: Some(PartsSetHeader {
+                    total: 1_000_000,
+                    hash: b"parts_hash0000000000000000000000".to_vec(),
+                }),
+            }),
+            validator_address: vec![0xa3; 20],
+            validator_index: 56789,
+            signature: vec![],
+        }),
+    }
+}
+
+fn keyring_sign_ed25519(c: &mut Criterion) {
+    let signer = test_signer();
+    let public_key = TendermintKey::ConsensusKey(signer.public_key().unwrap().into());
+    let mut keyring = KeyRing::new(keyring::Format::Hex);
+
+    keyring
+        .add(keyring::ed25519::Signer::new(
+            SigningProvider::SoftSign,
+            public_key,
+            Box::new(signer),
+        ))
+        .unwrap();
+
+    c.bench_function("KeyRing::sign_ed25519", |b| {
+        b.iter(|| keyring.sign_ed25519(None, EXAMPLE_MSG).unwrap())
+    });
+}
+
+fn privval_codec(c: &mut Criterion) {
+    let request = example_vote_request();
+    let mut request_bytes = vec![];
+    request.encode(&mut request_bytes).unwrap();
+
+    c.bench_function("Request::read (SignVoteRequest)", |b| {
+        b.iter(|| Request::read(&mut Cursor::new(&request_bytes)).unwrap())
+    });
+
+    let response = SignedVoteResponse {
+        vote: request.vote,
+        err: None,
+    };
+
+    c.bench_function("SignedVoteResponse::encode", |b| {
+        b.iter(|| {
+            let mut buf = vec![];
+            response.encode(&mut buf).unwrap();
+            buf
+        })
+    });
+}
+
+fn secret_connection_frames(c: &mut Criterion) {
+    let (local_socket, remote_socket) = UnixStream::pair().unwrap();
+
+    let remote = thread::spawn(move || {
+        let signer = test_signer();
+        let public_key = PublicKey::from(signer.public_key().unwrap());
+        SecretConnection::new(remote_socket, &public_key, &signer).unwrap()
+    });
+
+    let signer = test_signer();
+    let public_key = PublicKey::from(signer.public_key().unwrap());
+    let mut local = SecretConnection::new(local_socket, &public_key, &signer).unwrap();
+    let mut remote = remote.join().unwrap();
+
+    let mut request_bytes = vec![];
+    example_vote_request().encode(&mut request_bytes).unwrap();
+    let mut buf = vec![0u8; request_bytes.len()];
+
+    c.bench_function("SecretConnection frame encrypt/decrypt", |b| {
+        b.iter(|| {
+            local.write_all(&request_bytes).unwrap();
+            remote.read_exact(&mut buf).unwrap();
+        })
+    });
+}
+
+fn state_update(c: &mut Criterion) {
+    for &(format, name) in &[
+        (StateFormat::Json, "State::update_consensus_state"),
+        (
+            StateFormat::Binary,
+            "State::update_consensus_state (binary)",
+        ),
+    ] {
+        state_update_with_format(c, format, name);
+    }
+}
+
+fn state_update_with_format(c: &mut Criterion, format: StateFormat, name: &str) {
+    let dir = TempDir::new().unwrap();
+    let mut state = State::load_state(
+        dir.path().join("priv_validator_state.json"),
+        "bench-chain".parse().unwrap(),
+    )
+    .unwrap();
+    state.set_format(format).unwrap();
+    let mut height = 0u64;
+
+    c.bench_function(name, |b| {
+        b.iter(|| {
+            height += 1;
+
+            state
+                .update_consensus_state(consensus::State {
+                    height: height.into(),
+                    round: 0,
+                    step: 0,
+                    block_id: None,
+                })
+                .unwrap()
+        })
+    });
+}
+
+criterion_group!(
+    benches,
+    keyring_sign_ed25519,
+    privval_codec,
+    secret_connection_frames,
+    state_update
+);
+criterion_main!(benches);
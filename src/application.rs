@@ -1,6 +1,6 @@
 //! Abscissa `Application` for the KMS
 
-use crate::{commands::KmsCommand, config::KmsConfig};
+use crate::{commands::KmsCommand, config::KmsConfig, redact};
 use abscissa_core::{
     application::{self, AppCell},
     trace, Application, FrameworkError, StandardPaths,
@@ -79,7 +79,11 @@ impl Application for KmsApplication {
     /// to do so.
     fn register_components(&mut self, command: &Self::Cmd) -> Result<(), FrameworkError> {
         let components = self.framework_components(command)?;
-        self.state.components.register(components)
+        self.state.components.register(components)?;
+
+        // Replace the framework's panic hook, which may print secrets
+        redact::install_panic_hook();
+        Ok(())
     }
 
     /// Post-configuration lifecycle callback.
@@ -1,8 +1,11 @@
 //! Information about particular Tendermint blockchain networks
 
+pub mod cooldown;
+pub mod events;
 mod guard;
 mod registry;
 pub mod state;
+pub mod summary;
 
 pub use self::{
     guard::Guard,
@@ -10,13 +13,36 @@ pub use self::{
     state::State,
 };
 use crate::{
-    config::{chain::ChainConfig, KmsConfig},
-    error::Error,
+    config::{
+        chain::{
+            BlockTimeBoundsConfig, ChainConfig, FsyncPolicy, PubKeyEncoding, SignBytesOutput,
+            StepConfig, UnknownMessagePolicy,
+        },
+        KmsConfig,
+    },
+    confirm,
+    error::{Error, ErrorKind::*},
     keyring::{self, KeyRing},
     prelude::*,
 };
-use std::{path::PathBuf, sync::Mutex};
+use rand::Rng;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+use subtle_encoding::base64;
 pub use tendermint::chain::Id;
+use tendermint::{amino_types::SignedMsgType, TendermintKey};
+
+/// Maximum configurable `response_jitter_ms`, kept well below block times so
+/// the delay can never cause a validator to miss a block
+pub const MAX_RESPONSE_JITTER_MS: u64 = 100;
 
 /// Information about a particular Tendermint blockchain network
 pub struct Chain {
@@ -26,19 +52,116 @@ pub struct Chain {
     /// Signing keyring for this chain
     pub keyring: KeyRing,
 
+    /// Encoding of responses to public key requests
+    pub pubkey_encoding: PubKeyEncoding,
+
+    /// What to do with messages of unrecognized types
+    pub unknown_messages: UnknownMessagePolicy,
+
+    /// Consensus key used to sign proposals (if not the only one)
+    proposal_key: Option<TendermintKey>,
+
+    /// Consensus key used to sign votes (if not the only one)
+    vote_key: Option<TendermintKey>,
+
     /// State from the last block signed for this chain
     pub state: Mutex<State>,
+
+    /// Height below which to never sign
+    pub initial_height: Option<tendermint::block::Height>,
+
+    /// Height at or above which signing is refused for this chain
+    halt_height: Mutex<Option<tendermint::block::Height>>,
+
+    /// Maximum number of blocks a request may jump ahead of the last signed
+    /// height before signing is halted
+    pub max_height_gap: Option<u64>,
+
+    /// Consensus step of each message type
+    pub steps: StepConfig,
+
+    /// Maximum allowed skew between vote timestamps and the local clock
+    pub max_clock_skew: Option<Duration>,
+
+    /// Bounds on vote timestamps relative to the local clock
+    pub block_time_bounds: Option<BlockTimeBoundsConfig>,
+
+    /// Maximum random delay before sending signing responses
+    response_jitter: Option<Duration>,
+
+    /// Time to wait for an operator to confirm each signature on the
+    /// console (confirmation is disabled if `None`)
+    pub confirm_timeout: Option<Duration>,
+
+    /// Domain prefix required for `SignBytesRequest` (disabled if `None`)
+    pub sign_bytes_domain: Option<Vec<u8>>,
+
+    /// Layout of `SignBytesRequest` signatures
+    pub sign_bytes_output: SignBytesOutput,
+
+    /// Log the SHA-256 of the sign bytes of each consensus message signed?
+    pub log_sign_bytes_hash: bool,
+
+    /// Refuse to sign proposals and votes which aren't canonically encoded?
+    pub require_canonical_encoding: bool,
+
+    /// Refuse to sign `<nil>` votes?
+    pub refuse_nil_votes: bool,
+
+    /// Recent signing events for this chain
+    pub events: events::History,
+
+    /// Is signing for this chain currently paused (via the management API)?
+    paused: AtomicBool,
+
+    /// Is this KMS a standby for this chain (i.e. not signing until promoted)?
+    standby: AtomicBool,
+
+    /// Is this chain enabled (i.e. connecting to its validators)?
+    enabled: AtomicBool,
+
+    /// Halt signing for this chain if the state can't be persisted?
+    pub halt_on_state_error: bool,
+
+    /// Reason signing was halted due to an error (if it was)
+    halted: Mutex<Option<String>>,
+
+    /// Was signing halted because of a jump beyond `max_height_gap`?
+    halted_for_height_gap: AtomicBool,
+
+    /// Has an operator acknowledged a jump beyond `max_height_gap` (by
+    /// resuming signing after it halted)?
+    height_gap_acknowledged: AtomicBool,
+
+    /// Halts signing after repeated double-sign attempts (if enabled)
+    double_sign_cooldown: Option<cooldown::Cooldown>,
+
+    /// Signature count above which to warn that a key is due for rotation
+    pub signature_count_threshold: Option<u64>,
+
+    /// Keys which have been warned about exceeding the signature count
+    /// threshold since startup
+    signature_count_warned: Mutex<BTreeSet<String>>,
 }
 
 impl Chain {
     /// Attempt to create a `Chain` state from the given configuration
     pub fn from_config(config: &ChainConfig) -> Result<Chain, Error> {
-        let state_file = match config.state_file {
-            Some(ref path) => path.to_owned(),
-            None => PathBuf::from(&format!("{}_priv_validator_state.json", config.id)),
-        };
+        let mut state = State::load_state(config.state_file_path(), config.id)?;
+        state.set_fsync_policy(config.state_fsync);
+        state.set_format(config.state_format)?;
 
-        let mut state = State::load_state(state_file)?;
+        if config.count_signatures || config.signature_count_threshold.is_some() {
+            state.enable_signature_counts()?;
+        }
+
+        if let Some(ref mirror) = config.state_mirror {
+            state.enable_mirror(mirror)?;
+        }
+
+        if let Some(initial_height) = config.initial_height {
+            state.seed_initial_height(initial_height)?;
+        }
 
         if let Some(ref hook) = config.state_hook {
             match state::hook::run(hook) {
@@ -54,20 +177,846 @@ impl Chain {
             }
         }
 
+        Self::with_state(config, state)
+    }
+
+    /// Create a `Chain` from the given configuration and (already loaded) state
+    pub fn with_state(config: &ChainConfig, state: State) -> Result<Chain, Error> {
+        if !config.enabled {
+            warn!(
+                "[{}] chain disabled: not connecting to validators until enabled",
+                config.id
+            );
+        }
+
+        if config.standby {
+            warn!(
+                "[{}] starting as standby: not signing until promoted",
+                config.id
+            );
+        }
+
+        config.steps.validate()?;
+
+        if config.steps != StepConfig::default() {
+            warn!(
+                "[{}] using non-standard consensus steps: {:?}",
+                config.id, config.steps
+            );
+        }
+
+        if config.interactive_confirm.is_some() {
+            warn!(
+                "[{}] interactive confirmation enabled: every signature must be approved on the console",
+                config.id
+            );
+        }
+
+        if let Some(ref domain) = config.sign_bytes_domain {
+            validate_sign_bytes_domain(domain)?;
+            warn!(
+                "[{}] raw byte signing enabled with domain prefix {:?}",
+                config.id, domain
+            );
+        }
+
+        if let Some(jitter_ms) = config.response_jitter_ms {
+            if jitter_ms > MAX_RESPONSE_JITTER_MS {
+                fail!(
+                    ConfigError,
+                    "[{}] response_jitter_ms must be at most {} (got {})",
+                    config.id,
+                    MAX_RESPONSE_JITTER_MS,
+                    jitter_ms
+                );
+            }
+
+            info!(
+                "[{}] delaying signing responses by up to {}ms",
+                config.id, jitter_ms
+            );
+        }
+
+        if let FsyncPolicy::Batch { interval_ms: 0 } = config.state_fsync {
+            fail!(
+                ConfigError,
+                "[{}] state_fsync interval_ms must be at least 1",
+                config.id
+            );
+        }
+
+        if let Some(ref cooldown) = config.double_sign_cooldown {
+            if cooldown.threshold == 0 {
+                fail!(
+                    ConfigError,
+                    "[{}] double_sign_cooldown threshold must be at least 1",
+                    config.id
+                );
+            }
+        }
+
+        let (proposal_key, vote_key) = match config.signing_keys {
+            Some(ref signing_keys) => {
+                let parse = |key: &Option<String>| -> Result<_, Error> {
+                    key.as_ref()
+                        .map(|key| config.key_format.parse_consensus_key(key))
+                        .transpose()
+                        .map_err(|e| {
+                            format_err!(ConfigError, "[{}] signing_keys: {}", config.id, e)
+                        })
+                        .map_err(Into::into)
+                };
+
+                (parse(&signing_keys.proposal)?, parse(&signing_keys.vote)?)
+            }
+            None => (None, None),
+        };
+
+        let mut keyring = KeyRing::new(config.key_format.clone());
+        keyring.set_key_type_labels(config.key_type_labels.clone());
+        keyring.set_duplicate_key_policy(config.duplicate_keys);
+
+        if let Some(sign_timeout_ms) = config.sign_timeout_ms {
+            keyring.set_sign_timeout(Duration::from_millis(sign_timeout_ms));
+        }
+
         Ok(Self {
             id: config.id,
-            keyring: KeyRing::new(config.key_format.clone()),
+            keyring,
+            pubkey_encoding: config.pubkey_encoding,
+            unknown_messages: config.unknown_messages,
+            proposal_key,
+            vote_key,
             state: Mutex::new(state),
+            initial_height: config.initial_height,
+            halt_height: Mutex::new(config.halt_height),
+            max_height_gap: config.max_height_gap,
+            steps: config.steps,
+            max_clock_skew: config.max_clock_skew_ms.map(Duration::from_millis),
+            block_time_bounds: config.block_time_bounds,
+            response_jitter: config
+                .response_jitter_ms
+                .filter(|&jitter_ms| jitter_ms > 0)
+                .map(Duration::from_millis),
+            confirm_timeout: config.interactive_confirm.as_ref().map(|confirm| {
+                confirm
+                    .timeout_secs
+                    .map(Duration::from_secs)
+                    .unwrap_or(confirm::DEFAULT_TIMEOUT)
+            }),
+            sign_bytes_domain: config
+                .sign_bytes_domain
+                .as_ref()
+                .map(|domain| domain.as_bytes().to_vec()),
+            sign_bytes_output: config.sign_bytes_output,
+            log_sign_bytes_hash: config.log_sign_bytes_hash,
+            require_canonical_encoding: config.require_canonical_encoding,
+            refuse_nil_votes: config.refuse_nil_votes,
+            events: events::History::new(config.event_history),
+            paused: AtomicBool::new(false),
+            standby: AtomicBool::new(config.standby),
+            enabled: AtomicBool::new(config.enabled),
+            halt_on_state_error: config.halt_on_state_error,
+            halted: Mutex::new(None),
+            halted_for_height_gap: AtomicBool::new(false),
+            height_gap_acknowledged: AtomicBool::new(false),
+            double_sign_cooldown: config.double_sign_cooldown.as_ref().map(|cooldown| {
+                cooldown::Cooldown::new(
+                    cooldown.threshold,
+                    Duration::from_secs(cooldown.window_secs),
+                )
+            }),
+            signature_count_threshold: config.signature_count_threshold,
+            signature_count_warned: Mutex::new(BTreeSet::new()),
+        })
+    }
+
+    /// Is signing for this chain paused?
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+
+    /// Pause signing for this chain. Signing requests will be answered with
+    /// an error until signing is resumed.
+    pub fn pause(&self) {
+        self.paused.store(true, Ordering::SeqCst)
+    }
+
+    /// Resume signing for this chain, including after it was halted due to
+    /// an error
+    pub fn resume(&self) {
+        if let Some(reason) = self.halted.lock().unwrap().take() {
+            warn!("[{}] resuming signing after halt ({})", self.id, reason);
+
+            if self.halted_for_height_gap.swap(false, Ordering::SeqCst) {
+                self.height_gap_acknowledged.store(true, Ordering::SeqCst);
+            }
+        }
+
+        self.paused.store(false, Ordering::SeqCst)
+    }
+
+    /// Reason signing for this chain was halted due to an error (if it was)
+    pub fn halted_reason(&self) -> Option<String> {
+        self.halted.lock().unwrap().clone()
+    }
+
+    /// Halt signing for this chain due to an error until explicitly resumed
+    pub fn halt(&self, reason: String) {
+        error!("[{}] HALTING signing for this chain: {}", self.id, reason);
+        *self.halted.lock().unwrap() = Some(reason);
+    }
+
+    /// Check a request for the given height doesn't jump further ahead of
+    /// the last signed height than `max_height_gap` allows, halting signing
+    /// (and returning the reason) if it does. A jump is allowed once after
+    /// an operator resumes signing following such a halt.
+    pub fn check_height_gap(
+        &self,
+        last_height: tendermint::block::Height,
+        height: tendermint::block::Height,
+    ) -> Option<String> {
+        let max_gap = self.max_height_gap?;
+
+        // Nothing has been signed yet, so there's nothing to jump from
+        if last_height.value() == 0 || height.value() <= last_height.value() + max_gap {
+            return None;
+        }
+
+        if self.height_gap_acknowledged.swap(false, Ordering::SeqCst) {
+            warn!(
+                "[{}] jumping from height {} to {} (acknowledged by operator)",
+                self.id, last_height, height
+            );
+
+            return None;
+        }
+
+        let reason = format!(
+            "height {} is {} blocks ahead of last signed height {} (max_height_gap is {}); \
+             resume signing to acknowledge",
+            height,
+            height.value() - last_height.value(),
+            last_height,
+            max_gap
+        );
+
+        self.halted_for_height_gap.store(true, Ordering::SeqCst);
+        self.halt(reason.clone());
+        Some(reason)
+    }
+
+    /// Height at or above which signing is refused for this chain (if any)
+    pub fn halt_height(&self) -> Option<tendermint::block::Height> {
+        *self.halt_height.lock().unwrap()
+    }
+
+    /// Set (or clear) the height at or above which signing is refused
+    pub fn set_halt_height(&self, halt_height: Option<tendermint::block::Height>) {
+        match halt_height {
+            Some(height) => warn!("[{}] halt height set to {}", self.id, height),
+            None => info!("[{}] halt height cleared", self.id),
+        }
+
+        *self.halt_height.lock().unwrap() = halt_height;
+    }
+
+    /// Get the consensus key used to sign the given type of message, if one
+    /// is configured (otherwise the keyring's only consensus key is used)
+    pub fn signing_key(&self, msg_type: SignedMsgType) -> Option<&TendermintKey> {
+        match msg_type {
+            SignedMsgType::Proposal => self.proposal_key.as_ref(),
+            SignedMsgType::PreVote | SignedMsgType::PreCommit => self.vote_key.as_ref(),
+        }
+    }
+
+    /// Get the public key reported to the validator: the vote key if one is
+    /// configured, otherwise the keyring's only consensus key
+    pub fn public_key(&self) -> Result<TendermintKey, Error> {
+        match self.vote_key {
+            Some(vote_key) => Ok(vote_key),
+            None => self.keyring.default_pubkey(),
+        }
+    }
+
+    /// Ensure each configured signing key is in the keyring
+    pub fn check_signing_keys(&self) -> Result<(), Error> {
+        for (msg_type, key) in &[("proposal", self.proposal_key), ("vote", self.vote_key)] {
+            if let Some(key) = key {
+                if !self.keyring.contains(key) {
+                    fail!(
+                        InvalidKey,
+                        "[{}] {} signing key {} isn't configured for this chain",
+                        self.id,
+                        msg_type,
+                        self.keyring.format().serialize(*key)
+                    );
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Record a rejected double-sign attempt, halting signing for this chain
+    /// if too many have happened recently
+    pub fn record_double_sign_attempt(&self) {
+        let cooldown = match self.double_sign_cooldown {
+            Some(ref cooldown) => cooldown,
+            None => return,
+        };
+
+        if let Some(count) = cooldown.record(Instant::now()) {
+            self.halt(format!(
+                "{} double-sign attempts within {}s (split-brain or stale state?); \
+                 investigate before resuming via the management API",
+                count,
+                cooldown.window().as_secs()
+            ));
+        }
+    }
+
+    /// Count a signature by the given key (or the default key if `None`), if
+    /// signatures are counted for this chain. Counting is informational:
+    /// errors are logged rather than failing the signature.
+    pub fn count_signature(&self, state: &mut State, public_key: Option<&TendermintKey>) {
+        let public_key = match public_key {
+            Some(public_key) => *public_key,
+            None => match self.keyring.default_pubkey() {
+                Ok(public_key) => public_key,
+                Err(e) => {
+                    error!("[{}] couldn't count signature: {}", self.id, e);
+                    return;
+                }
+            },
+        };
+
+        let key = self.keyring.format().serialize(public_key);
+
+        let count = match state.count_signature(&key) {
+            Ok(Some(count)) => count,
+            Ok(None) => return,
+            Err(e) => {
+                error!("[{}] couldn't count signature by {}: {}", self.id, key, e);
+                return;
+            }
+        };
+
+        let threshold = match self.signature_count_threshold {
+            Some(threshold) if count > threshold => threshold,
+            _ => return,
+        };
+
+        if self
+            .signature_count_warned
+            .lock()
+            .unwrap()
+            .insert(key.clone())
+        {
+            warn!(
+                "[{}] key {} has made {} signatures, exceeding signature_count_threshold ({}): it may be due for rotation",
+                self.id, key, count, threshold
+            );
+        }
+    }
+
+    /// Is this KMS a standby for this chain?
+    pub fn is_standby(&self) -> bool {
+        self.standby.load(Ordering::SeqCst)
+    }
+
+    /// Promote this KMS from standby to active, re-reading the (shared) state
+    /// file first so signing resumes from the last state the previously
+    /// active KMS recorded
+    pub fn promote(&self) -> Result<(), Error> {
+        let mut state = self.state.lock().unwrap();
+        state.reload()?;
+        self.standby.store(false, Ordering::SeqCst);
+
+        info!(
+            "[{}] promoted to active at h/r/s {}",
+            self.id,
+            state.consensus_state()
+        );
+
+        Ok(())
+    }
+
+    /// Demote this KMS to standby. Signing requests will be answered with an
+    /// error until it's promoted again.
+    pub fn demote(&self) {
+        // Hold the state lock so no signing operation is in progress
+        let _state = self.state.lock().unwrap();
+        self.standby.store(true, Ordering::SeqCst);
+        warn!("[{}] demoted to standby", self.id);
+    }
+
+    /// Is this chain enabled (i.e. connecting to its validators)?
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::SeqCst)
+    }
+
+    /// Enable this chain, so connections to its validators are opened
+    pub fn enable(&self) {
+        if !self.enabled.swap(true, Ordering::SeqCst) {
+            info!("[{}] chain enabled", self.id);
+        }
+    }
+
+    /// Random delay to wait before sending a signing response, if
+    /// `response_jitter_ms` is configured
+    pub fn response_delay(&self) -> Option<Duration> {
+        self.response_jitter.map(|max| {
+            let max_micros = max.as_micros() as u64;
+            Duration::from_micros(rand::thread_rng().gen_range(0, max_micros + 1))
         })
     }
+
+    /// If signing for this chain is currently disabled, the reason why
+    pub fn signing_disabled_reason(&self) -> Option<String> {
+        if let Some(reason) = self.halted_reason() {
+            Some(format!("signing halted for this chain: {}", reason))
+        } else if !self.is_enabled() {
+            Some("chain is not enabled".to_owned())
+        } else if self.is_standby() {
+            Some("KMS is a standby for this chain".to_owned())
+        } else if self.is_paused() {
+            Some("signing paused for this chain".to_owned())
+        } else {
+            None
+        }
+    }
+}
+
+/// Ensure a `SignBytesRequest` domain prefix can never be the prefix of the
+/// sign bytes of a consensus message.
+///
+/// Consensus sign bytes are an Amino length prefix followed by the `0x08` tag
+/// of the message type field. A prefix of two (or more) printable ASCII
+/// characters can only be followed by a one-byte length and a printable byte,
+/// so it can never match.
+fn validate_sign_bytes_domain(domain: &str) -> Result<(), Error> {
+    if domain.len() < 2 || !domain.bytes().all(|b| b.is_ascii_graphic() || b == b' ') {
+        fail!(
+            ConfigError,
+            "sign_bytes_domain must be at least 2 printable ASCII characters: {:?}",
+            domain
+        );
+    }
+
+    Ok(())
 }
 
 /// Initialize the chain registry from the configuration file
 pub fn load_config(config: &KmsConfig) -> Result<(), Error> {
+    ensure_distinct_state_files(&config.chain)?;
+
     for config in &config.chain {
         REGISTRY.register(Chain::from_config(config)?)?;
     }
 
     let mut registry = REGISTRY.0.write().unwrap();
-    keyring::load_config(&mut registry, &config.providers)
+    keyring::load_config(&mut registry, &config.providers)?;
+
+    for chain in registry.chains() {
+        chain.check_signing_keys()?;
+        log_attestations(chain);
+    }
+
+    for chain_config in config.chain.iter().filter(|c| c.prewarm) {
+        if let Some(chain) = registry.get_chain(&chain_config.id) {
+            prewarm(chain)?;
+        }
+    }
+
+    log_shared_keys(&registry);
+    summary::Summary::new(&registry, config).log();
+
+    for chain_config in &config.chain {
+        if let FsyncPolicy::Batch { interval_ms } = chain_config.state_fsync {
+            spawn_fsync_thread(chain_config.id, Duration::from_millis(interval_ms))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Spawn a thread which `fsync`s the given chain's state file every
+/// interval (if it's been written since it was last `fsync`'d), so updates
+/// made under the `batch` fsync policy become durable even if no further
+/// updates follow them
+fn spawn_fsync_thread(chain_id: Id, interval: Duration) -> Result<(), Error> {
+    thread::Builder::new()
+        .name(format!("state-fsync {}", chain_id))
+        .spawn(move || loop {
+            thread::sleep(interval);
+
+            let registry = REGISTRY.get();
+
+            let chain = match registry.get_chain(&chain_id) {
+                Some(chain) => chain,
+                None => return,
+            };
+
+            let result = chain.state.lock().unwrap().fsync_pending();
+
+            if let Err(e) = result {
+                error!("[{}] {}", chain_id, e);
+            }
+        })
+        .map_err(|e| format_err!(IoError, "error spawning state fsync thread: {}", e))?;
+
+    Ok(())
+}
+
+/// Ensure no two chains share a state file.
+///
+/// The same key may be used for several chains (e.g. a mainnet and its
+/// testnet fork), but double-signing protection is tracked per chain, so
+/// each chain must persist its state separately. Otherwise one chain's state
+/// would overwrite the other's, and after a restart a chain could resume
+/// from the other's (lower) height and sign conflicting messages.
+pub fn ensure_distinct_state_files<'a>(
+    chain_configs: impl IntoIterator<Item = &'a ChainConfig>,
+) -> Result<(), Error> {
+    let mut state_files: BTreeMap<PathBuf, Id> = BTreeMap::new();
+
+    for chain_config in chain_configs {
+        let path = chain_config.state_file_path();
+
+        if let Some(other_id) = state_files.get(&path) {
+            fail!(
+                ConfigError,
+                "chains {} and {} share state file {}: each chain needs its own `state_file`",
+                other_id,
+                chain_config.id,
+                path.display()
+            );
+        }
+
+        state_files.insert(path, chain_config.id);
+    }
+
+    Ok(())
+}
+
+/// Log each key which is registered for more than one chain
+fn log_shared_keys(registry: &Registry) {
+    let mut shared_keys: BTreeMap<TendermintKey, (String, Vec<String>)> = BTreeMap::new();
+
+    for chain in registry.chains() {
+        for signer in chain.keyring.signers() {
+            let public_key = signer.public_key();
+
+            shared_keys
+                .entry(public_key)
+                .or_insert_with(|| (chain.keyring.format().serialize(public_key), vec![]))
+                .1
+                .push(chain.id.to_string());
+        }
+    }
+
+    for (public_key, chain_ids) in shared_keys.values() {
+        if chain_ids.len() > 1 {
+            info!(
+                "key {} is shared by chains {} (double-signing state is tracked separately for each)",
+                public_key,
+                chain_ids.join(", ")
+            );
+        }
+    }
+}
+
+/// Self-test each of the given chain's keys, establishing their providers'
+/// sessions before the first signing request
+fn prewarm(chain: &Chain) -> Result<(), Error> {
+    for signer in chain.keyring.signers() {
+        let public_key = chain.keyring.format().serialize(signer.public_key());
+        let started_at = Instant::now();
+
+        keyring::self_test::self_test(signer).map_err(|e| {
+            format_err!(
+                SigningError,
+                "[{}] {} key {} failed self-test: {}",
+                chain.id,
+                signer.provider(),
+                public_key,
+                e
+            )
+        })?;
+
+        info!(
+            "[{}] {} key {} passed self-test ({} ms)",
+            chain.id,
+            signer.provider(),
+            public_key,
+            started_at.elapsed().as_millis()
+        );
+    }
+
+    Ok(())
+}
+
+/// Fetch and log attestations for the keys of the given chain, where their
+/// providers support it
+fn log_attestations(chain: &Chain) {
+    for signer in chain.keyring.signers() {
+        let public_key = chain.keyring.format().serialize(signer.public_key());
+
+        match signer.attestation() {
+            Some(Ok(attestation)) => {
+                info!(
+                    "[{}] {} key {} attestation: {}",
+                    chain.id,
+                    signer.provider(),
+                    public_key,
+                    attestation
+                );
+
+                debug!(
+                    "[{}] {} key {} attestation document: {}",
+                    chain.id,
+                    signer.provider(),
+                    public_key,
+                    String::from_utf8(base64::encode(&attestation.document)).unwrap()
+                );
+            }
+            Some(Err(e)) => warn!(
+                "[{}] {} key {}: couldn't fetch attestation: {}",
+                chain.id,
+                signer.provider(),
+                public_key,
+                e
+            ),
+            None => (),
+        }
+    }
+}
+
+/// Load the configured chains and keys into a fresh registry whose chain
+/// states are never read from or written to their state files, for commands
+/// which need a chain's keys without signing consensus messages
+pub fn load_unpersisted(config: &KmsConfig) -> Result<Registry, Error> {
+    ensure_distinct_state_files(&config.chain)?;
+    let mut registry = Registry::default();
+
+    for chain_config in &config.chain {
+        registry.register_chain(Chain::with_state(chain_config, State::unpersisted())?)?;
+    }
+
+    keyring::load_config(&mut registry, &config.providers)?;
+    Ok(registry)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ensure_distinct_state_files, validate_sign_bytes_domain};
+    use crate::config::chain::ChainConfig;
+
+    fn chain_config(id: &str, state_file: Option<&str>) -> ChainConfig {
+        let mut toml = format!(
+            "id = \"{}\"\nkey_format = {{ type = \"hex\" }}\nenabled = true\n",
+            id
+        );
+
+        if let Some(state_file) = state_file {
+            toml.push_str(&format!("state_file = \"{}\"\n", state_file));
+        }
+
+        toml::from_str(&toml).unwrap()
+    }
+
+    #[test]
+    fn chains_must_not_share_state_files() {
+        let distinct = [chain_config("mainnet", None), chain_config("testnet", None)];
+        assert!(ensure_distinct_state_files(distinct.iter()).is_ok());
+
+        let shared = [
+            chain_config("mainnet", None),
+            chain_config("testnet", Some("mainnet_priv_validator_state.json")),
+        ];
+        assert!(ensure_distinct_state_files(shared.iter()).is_err());
+    }
+
+    #[test]
+    fn disabled_chains_can_be_enabled() {
+        use super::{Chain, State};
+
+        let mut config = chain_config("mainnet", None);
+        config.enabled = false;
+
+        let chain = Chain::with_state(&config, State::unpersisted()).unwrap();
+        assert!(!chain.is_enabled());
+        assert!(chain.signing_disabled_reason().is_some());
+
+        chain.enable();
+        assert!(chain.is_enabled());
+        assert_eq!(chain.signing_disabled_reason(), None);
+    }
+
+    #[test]
+    fn response_delay_is_bounded() {
+        use super::{Chain, State, MAX_RESPONSE_JITTER_MS};
+        use std::time::Duration;
+
+        let mut config = chain_config("mainnet", None);
+        let chain = Chain::with_state(&config, State::unpersisted()).unwrap();
+        assert_eq!(chain.response_delay(), None);
+
+        config.response_jitter_ms = Some(20);
+        let chain = Chain::with_state(&config, State::unpersisted()).unwrap();
+
+        for _ in 0..100 {
+            assert!(chain.response_delay().unwrap() <= Duration::from_millis(20));
+        }
+
+        config.response_jitter_ms = Some(MAX_RESPONSE_JITTER_MS + 1);
+        assert!(Chain::with_state(&config, State::unpersisted()).is_err());
+    }
+
+    #[test]
+    fn batch_fsync_interval_must_be_nonzero() {
+        use super::{Chain, FsyncPolicy, State};
+
+        let mut config = chain_config("mainnet", None);
+        config.state_fsync = FsyncPolicy::Batch { interval_ms: 100 };
+        assert!(Chain::with_state(&config, State::unpersisted()).is_ok());
+
+        config.state_fsync = FsyncPolicy::Batch { interval_ms: 0 };
+        let err = match Chain::with_state(&config, State::unpersisted()) {
+            Ok(_) => panic!("zero fsync interval accepted"),
+            Err(e) => e,
+        };
+        assert_eq!(*err.kind(), crate::error::ErrorKind::ConfigError);
+    }
+
+    #[test]
+    fn halt_height_can_be_changed_at_runtime() {
+        use super::{Chain, State};
+
+        let mut config = chain_config("mainnet", None);
+        config.halt_height = Some(1000u64.into());
+
+        let chain = Chain::with_state(&config, State::unpersisted()).unwrap();
+        assert_eq!(chain.halt_height(), Some(1000u64.into()));
+
+        chain.set_halt_height(Some(2000u64.into()));
+        assert_eq!(chain.halt_height(), Some(2000u64.into()));
+
+        chain.set_halt_height(None);
+        assert_eq!(chain.halt_height(), None);
+    }
+
+    #[test]
+    fn height_gaps_halt_until_acknowledged() {
+        use super::{Chain, State};
+
+        let mut config = chain_config("mainnet", None);
+        config.max_height_gap = Some(100);
+
+        let chain = Chain::with_state(&config, State::unpersisted()).unwrap();
+        assert!(chain
+            .check_height_gap(0u64.into(), 5000u64.into())
+            .is_none());
+        assert!(chain
+            .check_height_gap(1000u64.into(), 1100u64.into())
+            .is_none());
+
+        assert!(chain
+            .check_height_gap(1000u64.into(), 1101u64.into())
+            .is_some());
+        assert!(chain.signing_disabled_reason().is_some());
+
+        chain.resume();
+        assert!(chain.signing_disabled_reason().is_none());
+        assert!(chain
+            .check_height_gap(1000u64.into(), 1101u64.into())
+            .is_none());
+
+        // The acknowledgement only covers a single jump
+        assert!(chain
+            .check_height_gap(1101u64.into(), 5000u64.into())
+            .is_some());
+    }
+
+    #[test]
+    fn signatures_are_counted_per_key() {
+        use super::{Chain, State, TendermintKey};
+        use signatory::ed25519;
+
+        let chain =
+            Chain::with_state(&chain_config("mainnet", None), State::unpersisted()).unwrap();
+        let public_key = TendermintKey::ConsensusKey(ed25519::PublicKey::new([1; 32]).into());
+        let key = chain.keyring.format().serialize(public_key);
+        let mut state = chain.state.lock().unwrap();
+
+        // Only counted if enabled
+        chain.count_signature(&mut state, Some(&public_key));
+        assert_eq!(state.signature_count(&key), None);
+
+        state.enable_signature_counts().unwrap();
+        chain.count_signature(&mut state, Some(&public_key));
+        chain.count_signature(&mut state, Some(&public_key));
+        assert_eq!(state.signature_count(&key), Some(2));
+    }
+
+    #[cfg(feature = "softsign")]
+    #[test]
+    fn messages_are_routed_to_signing_keys() {
+        use super::{Chain, SignedMsgType, State, TendermintKey};
+        use crate::keyring::{ed25519, SigningProvider};
+        use signatory::public_key::PublicKeyed;
+        use signatory_dalek::Ed25519Signer;
+
+        let signers = [1u8, 2]
+            .iter()
+            .map(|&seed| {
+                let provider = Ed25519Signer::from(&ed25519::Seed::from_bytes([seed; 32]).unwrap());
+                let public_key = TendermintKey::ConsensusKey(provider.public_key().unwrap().into());
+                ed25519::Signer::new(SigningProvider::SoftSign, public_key, Box::new(provider))
+            })
+            .collect::<Vec<_>>();
+
+        let toml = format!(
+            "id = \"mainnet\"\nkey_format = {{ type = \"hex\" }}\n\
+             signing_keys = {{ proposal = \"{}\", vote = \"{}\" }}\n",
+            signers[0].public_key().to_hex(),
+            signers[1].public_key().to_hex()
+        );
+
+        let config: ChainConfig = toml::from_str(&toml).unwrap();
+        let mut chain = Chain::with_state(&config, State::unpersisted()).unwrap();
+        assert!(chain.check_signing_keys().is_err());
+
+        for signer in &signers {
+            chain.keyring.add(signer.clone()).unwrap();
+        }
+
+        chain.check_signing_keys().unwrap();
+        assert_eq!(
+            chain.signing_key(SignedMsgType::Proposal),
+            Some(&signers[0].public_key())
+        );
+        assert_eq!(
+            chain.signing_key(SignedMsgType::PreCommit),
+            Some(&signers[1].public_key())
+        );
+        assert_eq!(chain.public_key().unwrap(), signers[1].public_key());
+
+        // with two consensus keys, signing requires choosing one
+        assert!(chain.keyring.sign_ed25519(None, b"example").is_err());
+        assert!(chain
+            .keyring
+            .sign_ed25519(chain.signing_key(SignedMsgType::Proposal), b"example")
+            .is_ok());
+    }
+
+    #[test]
+    fn sign_bytes_domain_validation() {
+        assert!(validate_sign_bytes_domain("example-fork/raw:").is_ok());
+        assert!(validate_sign_bytes_domain("").is_err());
+        assert!(validate_sign_bytes_domain("x").is_err());
+        assert!(validate_sign_bytes_domain("\x08\x01").is_err());
+        assert!(validate_sign_bytes_domain("caf\u{e9}").is_err());
+    }
 }
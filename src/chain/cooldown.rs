@@ -0,0 +1,86 @@
+//! Counting of rejected double-sign attempts, to halt signing for a chain
+//! when they happen repeatedly
+
+use std::{
+    collections::VecDeque,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// Sliding window of recent double-sign rejections
+#[derive(Debug)]
+pub struct Cooldown {
+    /// Number of rejections within `window` which trips the cooldown
+    threshold: usize,
+
+    /// Window in which rejections are counted
+    window: Duration,
+
+    /// Times of recent rejections, oldest first
+    rejections: Mutex<VecDeque<Instant>>,
+}
+
+impl Cooldown {
+    /// Create a new cooldown which trips after `threshold` rejections within
+    /// `window`
+    pub fn new(threshold: usize, window: Duration) -> Self {
+        Self {
+            threshold,
+            window,
+            rejections: Mutex::new(VecDeque::with_capacity(threshold)),
+        }
+    }
+
+    /// Window in which rejections are counted
+    pub fn window(&self) -> Duration {
+        self.window
+    }
+
+    /// Record a rejection at `now`, returning the number of rejections
+    /// within the window if that reaches the threshold
+    pub fn record(&self, now: Instant) -> Option<usize> {
+        let mut rejections = self.rejections.lock().unwrap();
+
+        while let Some(oldest) = rejections.front() {
+            if now.duration_since(*oldest) < self.window && rejections.len() < self.threshold {
+                break;
+            }
+
+            rejections.pop_front();
+        }
+
+        rejections.push_back(now);
+
+        if rejections.len() >= self.threshold {
+            Some(rejections.len())
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trips_after_threshold_within_window() {
+        let cooldown = Cooldown::new(3, Duration::from_secs(60));
+        let start = Instant::now();
+
+        assert_eq!(cooldown.record(start), None);
+        assert_eq!(cooldown.record(start + Duration::from_secs(10)), None);
+        assert_eq!(cooldown.record(start + Duration::from_secs(20)), Some(3));
+    }
+
+    #[test]
+    fn old_rejections_expire() {
+        let cooldown = Cooldown::new(3, Duration::from_secs(60));
+        let start = Instant::now();
+
+        assert_eq!(cooldown.record(start), None);
+        assert_eq!(cooldown.record(start + Duration::from_secs(30)), None);
+        assert_eq!(cooldown.record(start + Duration::from_secs(70)), None);
+        assert_eq!(cooldown.record(start + Duration::from_secs(80)), Some(3));
+    }
+}
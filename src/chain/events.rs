@@ -0,0 +1,154 @@
+//! In-memory history of recent signing events for a chain.
+//!
+//! This is a bounded ring buffer intended for live troubleshooting (e.g. via
+//! the management API). It is not persisted and is cleared on restart.
+
+use crate::rpc::refusal::RefusalReason;
+use serde::Serialize;
+use std::{collections::VecDeque, sync::Mutex};
+use tendermint::{amino_types::SignedMsgType, block, consensus, Time};
+
+/// Default number of signing events retained per chain
+pub const DEFAULT_HISTORY: usize = 100;
+
+/// A signing request received from a validator and its outcome
+#[derive(Clone, Debug, Serialize)]
+pub struct SignEvent {
+    /// Time the request was handled
+    pub timestamp: Time,
+
+    /// Type of message to be signed
+    pub msg_type: &'static str,
+
+    /// Block height
+    pub height: block::Height,
+
+    /// Consensus round
+    pub round: i64,
+
+    /// Consensus step
+    pub step: i8,
+
+    /// Provider of the key used to sign the message (if any)
+    pub provider: Option<String>,
+
+    /// Was the message signed?
+    pub success: bool,
+
+    /// Reason the message wasn't signed
+    pub error: Option<String>,
+
+    /// Reason code, if signing was refused
+    pub reason: Option<&'static str>,
+}
+
+impl SignEvent {
+    /// Create a new event for handling a request with the given consensus
+    /// state. `error` is `None` if the message was signed.
+    pub fn new(
+        msg_type: SignedMsgType,
+        consensus_state: &consensus::State,
+        provider: Option<String>,
+        error: Option<String>,
+        reason: Option<RefusalReason>,
+    ) -> Self {
+        Self {
+            timestamp: Time::now(),
+            msg_type: msg_type_name(msg_type),
+            height: consensus_state.height,
+            round: consensus_state.round,
+            step: consensus_state.step,
+            provider,
+            success: error.is_none(),
+            error,
+            reason: reason.map(RefusalReason::as_str),
+        }
+    }
+}
+
+/// Bounded history of signing events
+#[derive(Debug)]
+pub struct History {
+    /// Maximum number of events to retain
+    capacity: usize,
+
+    /// Events, oldest first
+    events: Mutex<VecDeque<SignEvent>>,
+}
+
+impl History {
+    /// Create a new history which retains at most `capacity` events
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            events: Mutex::new(VecDeque::with_capacity(capacity)),
+        }
+    }
+
+    /// Record an event, evicting the oldest one if the history is full
+    pub fn record(&self, event: SignEvent) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        let mut events = self.events.lock().unwrap();
+
+        while events.len() >= self.capacity {
+            events.pop_front();
+        }
+
+        events.push_back(event);
+    }
+
+    /// Get a copy of the recorded events, oldest first
+    pub fn events(&self) -> Vec<SignEvent> {
+        self.events.lock().unwrap().iter().cloned().collect()
+    }
+}
+
+/// Name of the given message type as displayed in events
+fn msg_type_name(msg_type: SignedMsgType) -> &'static str {
+    match msg_type {
+        SignedMsgType::Proposal => "proposal",
+        SignedMsgType::PreVote => "prevote",
+        SignedMsgType::PreCommit => "precommit",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(height: u64) -> SignEvent {
+        let consensus_state = consensus::State {
+            height: block::Height::from(height),
+            ..Default::default()
+        };
+
+        SignEvent::new(SignedMsgType::PreVote, &consensus_state, None, None, None)
+    }
+
+    #[test]
+    fn history_is_bounded() {
+        let history = History::new(2);
+
+        for height in 1..=3 {
+            history.record(event(height));
+        }
+
+        let heights = history
+            .events()
+            .iter()
+            .map(|event| event.height.value())
+            .collect::<Vec<_>>();
+
+        assert_eq!(heights, vec![2, 3]);
+    }
+
+    #[test]
+    fn empty_history_records_nothing() {
+        let history = History::new(0);
+        history.record(event(1));
+        assert!(history.events().is_empty());
+    }
+}
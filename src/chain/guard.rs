@@ -16,4 +16,9 @@ impl<'lock> Guard<'lock> {
     pub fn get_chain(&self, chain_id: &Id) -> Option<&Chain> {
         self.0.get_chain(chain_id)
     }
+
+    /// Iterate over all registered chains
+    pub fn chains(&self) -> impl Iterator<Item = &Chain> {
+        self.0.chains()
+    }
 }
@@ -3,62 +3,396 @@
 //!
 //! Double-signing protection is the primary purpose of this code (for now).
 
+mod binary;
+mod counters;
 mod error;
 pub mod hook;
+mod mirror;
+pub mod portable;
 
 pub use self::error::{StateError, StateErrorKind};
+use self::{counters::Counters, mirror::Mirror};
 use crate::{
+    chain,
+    config::chain::{FsyncPolicy, StateFormat, StateMirrorConfig},
     error::{Error, ErrorKind::*},
     prelude::*,
 };
-use atomicwrites::{AtomicFile, OverwriteBehavior};
+use serde::{Deserialize, Serialize};
+use signatory::ed25519;
 use std::{
-    fs,
+    cmp::Ordering,
+    ffi::OsString,
+    fs::{self, File},
     io::{self, prelude::*},
     path::{Path, PathBuf},
+    process,
+    time::{Duration, Instant},
 };
-use tendermint::consensus;
+use tendermint::{amino_types::TimeMsg, block, consensus};
 
 /// State tracking for double signing prevention
 pub struct State {
     consensus_state: consensus::State,
     state_file_path: PathBuf,
+    fsync_policy: FsyncPolicy,
+    last_fsync: Option<Instant>,
+
+    /// Has the state been written to disk without being `fsync`'d?
+    fsync_pending: bool,
+
+    last_signed: Option<LastSigned>,
+
+    /// Format new state is written in
+    format: StateFormat,
+
+    /// Format of the state file currently on disk
+    file_format: StateFormat,
+
+    /// Sequence number of the last binary record written
+    sequence: u64,
+
+    /// Chain this state belongs to, which is recorded in the state file
+    /// (`None` if the state isn't associated with a chain)
+    chain_id: Option<chain::Id>,
+
+    /// Signatures made by each key (if counted)
+    signature_counts: Option<Counters>,
+
+    /// Secondary log state updates are mirrored to (if enabled)
+    mirror: Option<Mirror>,
+}
+
+/// Contents of a state file
+struct StateFile {
+    /// Format of the file
+    format: StateFormat,
+
+    /// Binary record sequence number (or 0 for JSON)
+    sequence: u64,
+
+    /// Consensus state
+    consensus_state: consensus::State,
+
+    /// Chain the state belongs to (if recorded)
+    chain_id: Option<chain::Id>,
+}
+
+/// Contents of a JSON state file: the fields of Tendermint's
+/// `priv_validator_state.json`, plus the chain the state belongs to (absent
+/// from files written by Tendermint or older versions of the KMS)
+#[derive(Deserialize, Serialize)]
+struct JsonStateFile {
+    /// Consensus state
+    #[serde(flatten)]
+    consensus_state: consensus::State,
+
+    /// Chain the state belongs to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    chain_id: Option<chain::Id>,
+}
+
+/// The last message signed, kept in memory so an identical request can be
+/// answered with the same signature
+#[derive(Clone, Debug)]
+pub struct LastSigned {
+    /// Consensus state of the signed message
+    pub consensus_state: consensus::State,
+
+    /// Timestamp of the signed message
+    pub timestamp: Option<TimeMsg>,
+
+    /// Bytes which were signed
+    pub sign_bytes: Vec<u8>,
+
+    /// Signature over `sign_bytes`
+    pub signature: ed25519::Signature,
 }
 
 impl State {
-    /// Load the state from the given path
-    pub fn load_state<P>(path: P) -> Result<Self, Error>
+    /// Load the state of the given chain from the given path, refusing if
+    /// the state file records a different chain
+    pub fn load_state<P>(path: P, chain_id: chain::Id) -> Result<Self, Error>
     where
         P: AsRef<Path>,
     {
-        match fs::read_to_string(path.as_ref()) {
-            Ok(state_json) => {
-                let consensus_state = serde_json::from_str(&state_json).map_err(|e| {
-                    format_err!(
-                        ParseError,
-                        "error parsing {}: {}",
+        // A leftover temporary file means we crashed before renaming it over
+        // the state file, which means the state file is still authoritative.
+        // (Only our own temporary file is removed, since others may belong to
+        // another KMS process currently writing to a shared state file.)
+        let tmp_path = tmp_file_path(path.as_ref());
+
+        if tmp_path.exists() {
+            warn!(
+                "removing incomplete state file write: {}",
+                tmp_path.display()
+            );
+
+            fs::remove_file(&tmp_path)?;
+        }
+
+        match fs::read(path.as_ref()) {
+            Ok(bytes) => {
+                let file = parse_state_file(path.as_ref(), &bytes)?;
+                check_chain_id(path.as_ref(), &file, &chain_id)?;
+
+                if file.chain_id.is_none() {
+                    info!(
+                        "state file {} doesn't record its chain ID: it will be recorded as `{}` on the next update",
                         path.as_ref().display(),
-                        e
-                    )
-                })?;
+                        chain_id
+                    );
+                }
 
                 Ok(Self {
-                    consensus_state,
+                    consensus_state: file.consensus_state,
                     state_file_path: path.as_ref().to_owned(),
+                    fsync_policy: FsyncPolicy::default(),
+                    last_fsync: None,
+                    fsync_pending: false,
+                    last_signed: None,
+                    format: file.format,
+                    file_format: file.format,
+                    sequence: file.sequence,
+                    chain_id: Some(chain_id),
+                    signature_counts: None,
+                    mirror: None,
                 })
             }
             Err(e) if e.kind() == io::ErrorKind::NotFound => {
-                Self::write_initial_state(path.as_ref())
+                Self::write_initial_state(path.as_ref(), chain_id)
             }
             Err(e) => Err(Error::from(e)),
         }
     }
 
+    /// Create a state which is never written to disk, for use when a chain's
+    /// keys are needed without signing consensus messages (e.g. self-tests)
+    pub fn unpersisted() -> Self {
+        Self {
+            consensus_state: consensus::State::default(),
+            state_file_path: PathBuf::new(),
+            fsync_policy: FsyncPolicy::Never,
+            last_fsync: None,
+            fsync_pending: false,
+            last_signed: None,
+            format: StateFormat::default(),
+            file_format: StateFormat::default(),
+            sequence: 0,
+            chain_id: None,
+            signature_counts: None,
+            mirror: None,
+        }
+    }
+
+    /// Re-read the consensus state from the state file, e.g. when another
+    /// KMS instance may have been signing using the same (shared) file
+    pub fn reload(&mut self) -> Result<(), Error> {
+        let bytes = fs::read(&self.state_file_path).map_err(|e| {
+            format_err!(
+                IoError,
+                "error reading {}: {}",
+                self.state_file_path.display(),
+                e
+            )
+        })?;
+
+        let file = parse_state_file(&self.state_file_path, &bytes)?;
+
+        if let Some(ref chain_id) = self.chain_id {
+            check_chain_id(&self.state_file_path, &file, chain_id)?;
+        }
+
+        self.consensus_state = file.consensus_state;
+        self.file_format = file.format;
+        self.sequence = file.sequence;
+        Ok(())
+    }
+
+    /// Read the consensus state of the given chain from an existing state
+    /// file without loading it for signing (or creating it if it doesn't
+    /// exist), refusing if the file records a different chain
+    pub fn read_consensus_state(
+        path: &Path,
+        chain_id: &chain::Id,
+    ) -> Result<consensus::State, Error> {
+        let bytes = fs::read(path)
+            .map_err(|e| format_err!(IoError, "error reading {}: {}", path.display(), e))?;
+
+        let file = parse_state_file(path, &bytes)?;
+        check_chain_id(path, &file, chain_id)?;
+        Ok(file.consensus_state)
+    }
+
+    /// Replace the consensus state with one imported from another KMS,
+    /// refusing if that would regress the state or conflict with it
+    pub fn import(&mut self, new_state: consensus::State) -> Result<(), Error> {
+        if new_state < self.consensus_state
+            || (new_state.cmp(&self.consensus_state) == Ordering::Equal
+                && new_state != self.consensus_state)
+        {
+            fail!(
+                DoubleSign,
+                "refusing to import state {} (block {}): {} is already at {} (block {})",
+                new_state,
+                new_state.block_id_prefix(),
+                self.state_file_path.display(),
+                self.consensus_state,
+                self.consensus_state.block_id_prefix()
+            );
+        }
+
+        self.consensus_state = new_state;
+
+        self.sync_to_disk().map_err(|e| {
+            format_err!(
+                IoError,
+                "error writing state to {}: {}",
+                self.state_file_path.display(),
+                e
+            )
+        })?;
+
+        Ok(())
+    }
+
+    /// Write the current state to disk and `fsync` it immediately, whatever
+    /// the fsync policy, giving operators a known-durable point (e.g. before
+    /// maintenance or taking a snapshot)
+    pub fn persist(&mut self) -> Result<(), Error> {
+        self.write_to_disk(true).map_err(|e| {
+            format_err!(
+                IoError,
+                "error writing state to {}: {}",
+                self.state_file_path.display(),
+                e
+            )
+            .into()
+        })
+    }
+
+    /// `fsync` the state file if it's been written since it was last
+    /// `fsync`'d, returning whether it was. Under the `batch` fsync policy,
+    /// this is called periodically so the last update is made durable within
+    /// the interval, even if no further updates follow it.
+    pub fn fsync_pending(&mut self) -> Result<bool, Error> {
+        if !self.fsync_pending {
+            return Ok(false);
+        }
+
+        sync_file_and_parent(&self.state_file_path).map_err(|e| {
+            format_err!(
+                IoError,
+                "error syncing {}: {}",
+                self.state_file_path.display(),
+                e
+            )
+        })?;
+
+        self.fsync_pending = false;
+        self.last_fsync = Some(Instant::now());
+        Ok(true)
+    }
+
     /// Borrow the current consensus state
     pub fn consensus_state(&self) -> &consensus::State {
         &self.consensus_state
     }
 
+    /// Get the last message signed, if it was signed at the given consensus
+    /// state (i.e. height, round, step, and block ID)
+    pub fn last_signed(&self, consensus_state: &consensus::State) -> Option<&LastSigned> {
+        self.last_signed
+            .as_ref()
+            .filter(|last_signed| last_signed.consensus_state == *consensus_state)
+    }
+
+    /// Remember the last message signed
+    pub fn record_signed(&mut self, last_signed: LastSigned) {
+        self.last_signed = Some(last_signed);
+    }
+
+    /// Count the signatures made by each key, persisting the counts alongside
+    /// the state file
+    pub fn enable_signature_counts(&mut self) -> Result<(), Error> {
+        self.signature_counts = Some(Counters::load(&self.state_file_path)?);
+        Ok(())
+    }
+
+    /// Mirror state updates to the configured secondary log
+    pub fn enable_mirror(&mut self, config: &StateMirrorConfig) -> Result<(), Error> {
+        let chain_id = self
+            .chain_id
+            .ok_or_else(|| format_err!(ConfigError, "state mirroring requires a chain ID"))?;
+
+        self.mirror = Some(Mirror::spawn(config, chain_id)?);
+        Ok(())
+    }
+
+    /// Have recent state updates failed to be mirrored? (`None` if
+    /// mirroring isn't enabled)
+    pub fn is_mirror_failing(&self) -> Option<bool> {
+        self.mirror.as_ref().map(Mirror::is_failing)
+    }
+
+    /// Count a signature by the given key, returning its new count (or
+    /// `None` if signatures aren't counted)
+    pub fn count_signature(&mut self, key: &str) -> Result<Option<u64>, Error> {
+        let fsync = self.fsync_policy == FsyncPolicy::Always;
+
+        self.signature_counts
+            .as_mut()
+            .map(|counts| counts.increment(key, fsync))
+            .transpose()
+    }
+
+    /// Number of signatures made by the given key (if signatures are
+    /// counted)
+    pub fn signature_count(&self, key: &str) -> Option<u64> {
+        self.signature_counts.as_ref().map(|counts| counts.get(key))
+    }
+
+    /// Set the policy for when to `fsync` the state file
+    pub fn set_fsync_policy(&mut self, policy: FsyncPolicy) {
+        if policy != FsyncPolicy::Always {
+            warn!(
+                "state file {} uses fsync policy {:?}: a crash may lose the last signed state!",
+                self.state_file_path.display(),
+                policy
+            );
+        }
+
+        self.fsync_policy = policy;
+    }
+
+    /// Set the format of the state file, migrating the existing file to it
+    /// if it's in a different format
+    pub fn set_format(&mut self, format: StateFormat) -> Result<(), Error> {
+        self.format = format;
+
+        if self.file_format == format || self.state_file_path.as_os_str().is_empty() {
+            return Ok(());
+        }
+
+        self.sync_to_disk().map_err(|e| {
+            format_err!(
+                IoError,
+                "error migrating {} to {:?} format: {}",
+                self.state_file_path.display(),
+                format,
+                e
+            )
+        })?;
+
+        info!(
+            "migrated state file {} to {:?} format",
+            self.state_file_path.display(),
+            format
+        );
+
+        Ok(())
+    }
+
     /// Check and update the chain's height, round, and step
     // TODO(tarcieri): rewrite this logic to follow Tendermint spec and be clippy-friendly
     #[allow(clippy::comparison_chain)]
@@ -95,21 +429,16 @@ impl State {
                     )
                 }
 
-                if new_state.block_id != self.consensus_state.block_id &&
-                    // disallow voting for two different block IDs during different steps
-                    ((new_state.block_id.is_some() && self.consensus_state.block_id.is_some()) ||
-                    // disallow voting `<nil>` and for a block ID on the same step
-                    (new_state.step == self.consensus_state.step))
-                {
+                if block_ids_conflict(&self.consensus_state, &new_state) {
                     fail!(
-                            StateErrorKind::DoubleSign,
-                            "Attempting to sign a second proposal at height:{} round:{} step:{} old block id:{} new block {}",
-                            new_state.height,
-                            new_state.round,
-                            new_state.step,
-                            self.consensus_state.block_id_prefix(),
-                            new_state.block_id_prefix()
-                        );
+                        StateErrorKind::DoubleSign,
+                        "Attempting to sign a second proposal at height:{} round:{} step:{} old block id:{} new block {}",
+                        new_state.height,
+                        new_state.round,
+                        new_state.step,
+                        self.consensus_state.block_id_prefix(),
+                        new_state.block_id_prefix()
+                    );
                 }
             }
         }
@@ -124,6 +453,44 @@ impl State {
                 e
             )
         })?;
+
+        if let Some(ref mirror) = self.mirror {
+            mirror.record(&self.consensus_state);
+        }
+
+        Ok(())
+    }
+
+    /// Ensure the state is seeded to just below the given initial height so
+    /// nothing below it can ever be signed, e.g. for validators which joined
+    /// a chain mid-way. Has no effect if the state is already past it.
+    pub fn seed_initial_height(&mut self, initial_height: block::Height) -> Result<(), Error> {
+        let seed_height = initial_height.value().saturating_sub(1);
+
+        if self.consensus_state.height.value() >= seed_height {
+            return Ok(());
+        }
+
+        self.consensus_state = consensus::State {
+            height: seed_height.into(),
+            ..Default::default()
+        };
+
+        self.sync_to_disk().map_err(|e| {
+            format_err!(
+                IoError,
+                "error writing state to {}: {}",
+                self.state_file_path.display(),
+                e
+            )
+        })?;
+
+        info!(
+            "seeded {} with initial height {}",
+            self.state_file_path.display(),
+            initial_height
+        );
+
         Ok(())
     }
 
@@ -159,17 +526,27 @@ impl State {
         Ok(())
     }
 
-    /// Write the initial state to the given path on disk
-    fn write_initial_state(path: &Path) -> Result<Self, Error> {
+    /// Write the initial state of the given chain to the given path on disk
+    fn write_initial_state(path: &Path, chain_id: chain::Id) -> Result<Self, Error> {
         let mut consensus_state = consensus::State::default();
 
         // TODO(tarcieri): correct upstream `tendermint-rs` default height to 0
         // Set the initial block height to 0 to indicate we've never signed a block
         consensus_state.height = 0.into();
 
-        let initial_state = Self {
+        let mut initial_state = Self {
             consensus_state,
             state_file_path: path.to_owned(),
+            fsync_policy: FsyncPolicy::default(),
+            last_fsync: None,
+            fsync_pending: false,
+            last_signed: None,
+            format: StateFormat::default(),
+            file_format: StateFormat::default(),
+            sequence: 0,
+            chain_id: Some(chain_id),
+            signature_counts: None,
+            mirror: None,
         };
 
         initial_state.sync_to_disk()?;
@@ -177,21 +554,184 @@ impl State {
         Ok(initial_state)
     }
 
-    /// Sync the current state to disk
-    fn sync_to_disk(&self) -> io::Result<()> {
-        let json = serde_json::to_string(&self.consensus_state)?;
+    /// Sync the current state to disk.
+    ///
+    /// Binary state files are updated in place (see the `binary` module).
+    /// Otherwise, the state is written to a temporary file which is then
+    /// atomically renamed over the state file, so a crash at any point leaves
+    /// either the previous or the new state on disk, never a partially
+    /// written file.
+    fn sync_to_disk(&mut self) -> io::Result<()> {
+        let fsync = self.fsync_due();
+        self.write_to_disk(fsync)
+    }
+
+    /// Write the current state to disk (see `sync_to_disk`), `fsync`ing it
+    /// if requested
+    fn write_to_disk(&mut self, fsync: bool) -> io::Result<()> {
+        if self.state_file_path.as_os_str().is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                "state is not persisted",
+            ));
+        }
+
+        if self.format == StateFormat::Binary && self.file_format == StateFormat::Binary {
+            binary::write_record(
+                &self.state_file_path,
+                self.sequence + 1,
+                &self.consensus_state,
+                self.chain_id.as_ref(),
+                fsync,
+            )?;
 
-        AtomicFile::new(&self.state_file_path, OverwriteBehavior::AllowOverwrite)
-            .write(|f| f.write_all(json.as_bytes()))?;
+            self.sequence += 1;
+            self.fsync_pending = !fsync;
+
+            if fsync {
+                self.last_fsync = Some(Instant::now());
+            }
+
+            return Ok(());
+        }
+
+        let tmp_path = self.write_tmp_file(fsync)?;
+        fs::rename(&tmp_path, &self.state_file_path)?;
+        self.file_format = self.format;
+        self.fsync_pending = !fsync;
+
+        if fsync {
+            // Ensure the rename itself is durable
+            File::open(parent_dir(&self.state_file_path))?.sync_all()?;
+            self.last_fsync = Some(Instant::now());
+        }
 
         Ok(())
     }
+
+    /// Write the current state to the temporary file (first half of
+    /// `write_to_disk`), returning its path
+    fn write_tmp_file(&mut self, fsync: bool) -> io::Result<PathBuf> {
+        let contents = match self.format {
+            StateFormat::Json => serde_json::to_vec(&JsonStateFile {
+                consensus_state: self.consensus_state.clone(),
+                chain_id: self.chain_id,
+            })?,
+            StateFormat::Binary => {
+                self.sequence += 1;
+                binary::encode_file(self.sequence, &self.consensus_state, self.chain_id.as_ref())
+            }
+        };
+
+        let tmp_path = tmp_file_path(&self.state_file_path);
+
+        let mut tmp_file = File::create(&tmp_path)?;
+        tmp_file.write_all(&contents)?;
+
+        if fsync {
+            tmp_file.sync_all()?;
+        }
+
+        Ok(tmp_path)
+    }
+
+    /// Should the next write to the state file be `fsync`'d?
+    fn fsync_due(&self) -> bool {
+        match self.fsync_policy {
+            FsyncPolicy::Always => true,
+            FsyncPolicy::Batch { interval_ms } => self.last_fsync.map_or(true, |last_fsync| {
+                last_fsync.elapsed() >= Duration::from_millis(interval_ms)
+            }),
+            FsyncPolicy::Never => false,
+        }
+    }
+}
+
+/// Do the block IDs of two states at the same height and round conflict,
+/// i.e. would signing the new state after the old one be a double sign?
+///
+/// - Two different blocks always conflict, whatever the steps
+/// - `<nil>` (no block) and a block conflict at the same step, but not at
+///   different steps, e.g. a `<nil>` prevote followed by a precommit for a
+///   block which received a polka
+/// - Two `<nil>` block IDs never conflict
+fn block_ids_conflict(old_state: &consensus::State, new_state: &consensus::State) -> bool {
+    match (&old_state.block_id, &new_state.block_id) {
+        (Some(old_block_id), Some(new_block_id)) => old_block_id != new_block_id,
+        (None, None) => false,
+        (Some(_), None) | (None, Some(_)) => old_state.step == new_state.step,
+    }
+}
+
+/// Parse the contents of the state file at the given path (in either
+/// format)
+fn parse_state_file(path: &Path, bytes: &[u8]) -> Result<StateFile, Error> {
+    if binary::is_binary(bytes) {
+        let record = binary::decode_file(bytes)
+            .map_err(|e| format_err!(ParseError, "error parsing {}: {}", path.display(), e))?;
+
+        return Ok(StateFile {
+            format: StateFormat::Binary,
+            sequence: record.sequence,
+            consensus_state: record.consensus_state,
+            chain_id: record.chain_id,
+        });
+    }
+
+    serde_json::from_slice::<JsonStateFile>(bytes)
+        .map(|file| StateFile {
+            format: StateFormat::Json,
+            sequence: 0,
+            consensus_state: file.consensus_state,
+            chain_id: file.chain_id,
+        })
+        .map_err(|e| format_err!(ParseError, "error parsing {}: {}", path.display(), e).into())
+}
+
+/// Ensure the state in the given file belongs to the given chain (if the
+/// file records which chain it belongs to)
+fn check_chain_id(path: &Path, file: &StateFile, chain_id: &chain::Id) -> Result<(), Error> {
+    match file.chain_id {
+        Some(ref file_chain_id) if file_chain_id != chain_id => fail!(
+            ConfigError,
+            "state file {} belongs to chain `{}`, not `{}`: refusing to use it (was it copied from another chain?)",
+            path.display(),
+            file_chain_id,
+            chain_id
+        ),
+        _ => Ok(()),
+    }
+}
+
+/// Directory containing the given state file
+fn parent_dir(state_file_path: &Path) -> &Path {
+    match state_file_path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    }
+}
+
+/// `fsync` the given state file, and the directory containing it (in case
+/// it was renamed into place)
+fn sync_file_and_parent(state_file_path: &Path) -> io::Result<()> {
+    File::open(state_file_path)?.sync_all()?;
+    File::open(parent_dir(state_file_path))?.sync_all()
+}
+
+/// Path of the temporary file used when writing the given state file.
+///
+/// This is unique to the current process, so several KMS processes sharing
+/// a state file (e.g. an active/standby pair) never write to each other's
+/// temporary files.
+fn tmp_file_path(state_file_path: &Path) -> PathBuf {
+    let mut tmp_path = OsString::from(state_file_path);
+    tmp_path.push(format!(".{}.tmp", process::id()));
+    tmp_path.into()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use tendermint::block;
 
     const EXAMPLE_BLOCK_ID: &str =
         "26C0A41F3243C6BCD7AD2DFF8A8D83A71D29D307B5326C227F734A1A512FE47D";
@@ -201,6 +741,8 @@ mod tests {
 
     const EXAMPLE_PATH: &str = "/tmp/tmp_state.json";
 
+    const EXAMPLE_CHAIN_ID: &str = "cosmoshub-3";
+
     /// Macro for compactly expressing a consensus state
     macro_rules! state {
         ($height:expr, $round:expr, $step:expr, $block_id:expr) => {
@@ -228,6 +770,16 @@ mod tests {
                 State {
                     consensus_state: $old_state,
                     state_file_path: EXAMPLE_PATH.into(),
+                    fsync_policy: FsyncPolicy::Always,
+                    last_fsync: None,
+                    fsync_pending: false,
+                    last_signed: None,
+                    format: StateFormat::Json,
+                    file_format: StateFormat::Json,
+                    sequence: 0,
+                    chain_id: None,
+                    signature_counts: None,
+                    mirror: None,
                 }
                 .update_consensus_state($new_state)
                 .unwrap();
@@ -243,6 +795,16 @@ mod tests {
                 let err = State {
                     consensus_state: $old_state,
                     state_file_path: EXAMPLE_PATH.into(),
+                    fsync_policy: FsyncPolicy::Always,
+                    last_fsync: None,
+                    fsync_pending: false,
+                    last_signed: None,
+                    format: StateFormat::Json,
+                    file_format: StateFormat::Json,
+                    sequence: 0,
+                    chain_id: None,
+                    signature_counts: None,
+                    mirror: None,
                 }
                 .update_consensus_state($new_state)
                 .expect_err("expected StateErrorKind::DoubleSign but succeeded");
@@ -293,4 +855,371 @@ mod tests {
         state!(1, 1, 2, None),
         state!(1, 1, 2, block_id!(EXAMPLE_BLOCK_ID))
     );
+
+    successful_update_test!(
+        nil_vote_resigned_success,
+        state!(1, 1, 6, None),
+        state!(1, 1, 6, None)
+    );
+
+    successful_update_test!(
+        nil_prevote_then_nil_precommit_success,
+        state!(1, 1, 6, None),
+        state!(1, 1, 7, None)
+    );
+
+    successful_update_test!(
+        block_prevote_then_nil_precommit_success,
+        state!(1, 1, 6, block_id!(EXAMPLE_BLOCK_ID)),
+        state!(1, 1, 7, None)
+    );
+
+    double_sign_test!(
+        nil_then_block_on_same_step_double_sign,
+        state!(1, 1, 6, None),
+        state!(1, 1, 6, block_id!(EXAMPLE_BLOCK_ID))
+    );
+
+    double_sign_test!(
+        block_then_nil_on_same_step_double_sign,
+        state!(1, 1, 6, block_id!(EXAMPLE_BLOCK_ID)),
+        state!(1, 1, 6, None)
+    );
+
+    double_sign_test!(
+        different_blocks_on_different_steps_double_sign,
+        state!(1, 1, 6, block_id!(EXAMPLE_BLOCK_ID)),
+        state!(1, 1, 7, block_id!(EXAMPLE_DOUBLE_SIGN_BLOCK_ID))
+    );
+
+    #[test]
+    fn fsync_policy_test() {
+        let mut state = State {
+            consensus_state: state!(1, 0, 0, None),
+            state_file_path: EXAMPLE_PATH.into(),
+            fsync_policy: FsyncPolicy::Batch {
+                interval_ms: 60_000,
+            },
+            last_fsync: None,
+            fsync_pending: false,
+            last_signed: None,
+            format: StateFormat::Json,
+            file_format: StateFormat::Json,
+            sequence: 0,
+            chain_id: None,
+            signature_counts: None,
+            mirror: None,
+        };
+
+        assert!(state.fsync_due());
+        state.last_fsync = Some(Instant::now());
+        assert!(!state.fsync_due());
+
+        state.fsync_policy = FsyncPolicy::Never;
+        assert!(!state.fsync_due());
+
+        state.fsync_policy = FsyncPolicy::Always;
+        assert!(state.fsync_due());
+    }
+
+    #[test]
+    fn persist_test() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("priv_validator_state.json");
+
+        let mut state = State::load_state(&path, EXAMPLE_CHAIN_ID.into()).unwrap();
+        state.set_fsync_policy(FsyncPolicy::Batch {
+            interval_ms: 60_000,
+        });
+        state.last_fsync = Some(Instant::now());
+        assert!(!state.fsync_due());
+
+        state
+            .update_consensus_state(state!(5, 1, 2, block_id!(EXAMPLE_BLOCK_ID)))
+            .unwrap();
+
+        let last_fsync = state.last_fsync.unwrap();
+        state.persist().unwrap();
+        assert!(state.last_fsync.unwrap() > last_fsync);
+        assert_eq!(
+            &State::read_consensus_state(&path, &EXAMPLE_CHAIN_ID.into()).unwrap(),
+            state.consensus_state()
+        );
+
+        assert!(State::unpersisted().persist().is_err());
+    }
+
+    #[test]
+    fn fsync_pending_test() {
+        for &format in &[StateFormat::Json, StateFormat::Binary] {
+            let dir = tempfile::tempdir().unwrap();
+            let path = dir.path().join("priv_validator_state.json");
+
+            let mut state = State::load_state(&path, EXAMPLE_CHAIN_ID.into()).unwrap();
+            state.set_format(format).unwrap();
+            state.set_fsync_policy(FsyncPolicy::Batch {
+                interval_ms: 60_000,
+            });
+            state.last_fsync = Some(Instant::now());
+
+            state
+                .update_consensus_state(state!(5, 1, 2, block_id!(EXAMPLE_BLOCK_ID)))
+                .unwrap();
+
+            // The update isn't due to be fsync'd, so it's left pending
+            let last_fsync = state.last_fsync.unwrap();
+            assert!(state.fsync_pending().unwrap());
+            assert!(state.last_fsync.unwrap() > last_fsync);
+            assert!(!state.fsync_pending().unwrap());
+        }
+    }
+
+    #[test]
+    fn chain_id_mismatch_test() {
+        for &format in &[StateFormat::Json, StateFormat::Binary] {
+            let dir = tempfile::tempdir().unwrap();
+            let path = dir.path().join("priv_validator_state.json");
+
+            let mut state = State::load_state(&path, EXAMPLE_CHAIN_ID.into()).unwrap();
+            state.set_format(format).unwrap();
+            state
+                .update_consensus_state(state!(5, 1, 2, block_id!(EXAMPLE_BLOCK_ID)))
+                .unwrap();
+
+            assert!(State::load_state(&path, EXAMPLE_CHAIN_ID.into()).is_ok());
+
+            let err = State::load_state(&path, "columbus-3".into())
+                .err()
+                .expect("expected a chain ID mismatch");
+            assert_eq!(*err.kind(), ConfigError);
+            assert!(
+                err.to_string().contains("belongs to chain `cosmoshub-3`"),
+                "{}",
+                err
+            );
+
+            // Standbys re-reading the file are refused too
+            let mut standby = State::load_state(&path, EXAMPLE_CHAIN_ID.into()).unwrap();
+            standby.chain_id = Some("columbus-3".into());
+            assert!(standby.reload().is_err());
+        }
+    }
+
+    #[test]
+    fn legacy_state_file_test() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("priv_validator_state.json");
+
+        // As written by Tendermint or older versions of the KMS
+        fs::write(
+            &path,
+            r#"{"height":"5","round":"1","step":2,"block_id":null}"#,
+        )
+        .unwrap();
+
+        let mut state = State::load_state(&path, EXAMPLE_CHAIN_ID.into()).unwrap();
+        assert_eq!(state.consensus_state(), &state!(5, 1, 2, None));
+
+        // The chain ID is recorded on the next update
+        state.update_consensus_state(state!(6, 0, 0, None)).unwrap();
+        assert!(State::load_state(&path, "columbus-3".into()).is_err());
+        assert_eq!(
+            State::read_consensus_state(&path, &EXAMPLE_CHAIN_ID.into()).unwrap(),
+            state!(6, 0, 0, None)
+        );
+    }
+
+    #[test]
+    fn reload_test() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("priv_validator_state.json");
+
+        let mut standby = State::load_state(&path, EXAMPLE_CHAIN_ID.into()).unwrap();
+        let mut active = State::load_state(&path, EXAMPLE_CHAIN_ID.into()).unwrap();
+
+        active
+            .update_consensus_state(state!(5, 1, 2, block_id!(EXAMPLE_BLOCK_ID)))
+            .unwrap();
+        assert_eq!(standby.consensus_state(), &state!(0, 0, 0, None));
+
+        standby.reload().unwrap();
+        assert_eq!(standby.consensus_state(), active.consensus_state());
+    }
+
+    #[test]
+    fn binary_format_migration_test() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("priv_validator_state.json");
+
+        let mut state = State::load_state(&path, EXAMPLE_CHAIN_ID.into()).unwrap();
+        state
+            .update_consensus_state(state!(5, 1, 2, block_id!(EXAMPLE_BLOCK_ID)))
+            .unwrap();
+
+        state.set_format(StateFormat::Binary).unwrap();
+        assert!(binary::is_binary(&fs::read(&path).unwrap()));
+
+        // updated in place, and readable by other processes
+        state.update_consensus_state(state!(5, 2, 0, None)).unwrap();
+        state
+            .update_consensus_state(state!(6, 0, 0, block_id!(EXAMPLE_BLOCK_ID)))
+            .unwrap();
+
+        let mut standby = State::load_state(&path, EXAMPLE_CHAIN_ID.into()).unwrap();
+        assert_eq!(standby.consensus_state(), state.consensus_state());
+
+        state
+            .update_consensus_state(state!(6, 0, 1, block_id!(EXAMPLE_BLOCK_ID)))
+            .unwrap();
+        standby.reload().unwrap();
+        assert_eq!(standby.consensus_state(), state.consensus_state());
+
+        // and migrated back to JSON
+        standby.set_format(StateFormat::Json).unwrap();
+        assert_eq!(
+            State::read_consensus_state(&path, &EXAMPLE_CHAIN_ID.into()).unwrap(),
+            state!(6, 0, 1, block_id!(EXAMPLE_BLOCK_ID))
+        );
+        assert!(fs::read_to_string(&path).unwrap().starts_with('{'));
+    }
+
+    #[test]
+    fn resign_after_restart_test() {
+        for &format in &[StateFormat::Json, StateFormat::Binary] {
+            let dir = tempfile::tempdir().unwrap();
+            let path = dir.path().join("priv_validator_state.json");
+            let last_state = state!(5, 1, 2, block_id!(EXAMPLE_BLOCK_ID));
+
+            let mut state = State::load_state(&path, EXAMPLE_CHAIN_ID.into()).unwrap();
+            state.set_format(format).unwrap();
+            state.update_consensus_state(last_state.clone()).unwrap();
+
+            // Each case is checked against a freshly reloaded state, with
+            // nothing about the last signature remembered in memory
+            let restart = || {
+                let state = State::load_state(&path, EXAMPLE_CHAIN_ID.into()).unwrap();
+                assert_eq!(state.consensus_state(), &last_state);
+                assert!(state.last_signed(&last_state).is_none());
+                state
+            };
+
+            // identical h/r/s and block ID: idempotent
+            restart()
+                .update_consensus_state(last_state.clone())
+                .unwrap();
+            restart()
+                .update_consensus_state(last_state.clone())
+                .unwrap();
+
+            // conflicting block ID, or nil, at the same h/r/s
+            for conflicting in &[
+                state!(5, 1, 2, block_id!(EXAMPLE_DOUBLE_SIGN_BLOCK_ID)),
+                state!(5, 1, 2, None),
+            ] {
+                let err = restart()
+                    .update_consensus_state(conflicting.clone())
+                    .unwrap_err();
+                assert_eq!(err.kind(), StateErrorKind::DoubleSign, "{:?}", format);
+            }
+
+            // earlier step, round, or height
+            for (earlier, kind) in &[
+                (
+                    state!(5, 1, 1, block_id!(EXAMPLE_BLOCK_ID)),
+                    StateErrorKind::StepRegression,
+                ),
+                (
+                    state!(5, 0, 2, block_id!(EXAMPLE_BLOCK_ID)),
+                    StateErrorKind::RoundRegression,
+                ),
+                (
+                    state!(4, 1, 2, block_id!(EXAMPLE_BLOCK_ID)),
+                    StateErrorKind::HeightRegression,
+                ),
+            ] {
+                let err = restart()
+                    .update_consensus_state(earlier.clone())
+                    .unwrap_err();
+                assert_eq!(err.kind(), *kind, "{:?}", format);
+            }
+
+            // rejected requests never modify the state file
+            restart();
+        }
+    }
+
+    #[test]
+    fn crash_between_write_and_rename_test() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("priv_validator_state.json");
+
+        let mut state = State::load_state(&path, EXAMPLE_CHAIN_ID.into()).unwrap();
+        state
+            .update_consensus_state(state!(5, 1, 2, block_id!(EXAMPLE_BLOCK_ID)))
+            .unwrap();
+
+        // Simulate a crash after writing the temporary file but before the
+        // rename, leaving a truncated temporary file behind
+        state.consensus_state = state!(6, 0, 0, None);
+        let tmp_path = state.write_tmp_file(true).unwrap();
+        let tmp_contents = fs::read(&tmp_path).unwrap();
+        fs::write(&tmp_path, &tmp_contents[..tmp_contents.len() / 2]).unwrap();
+
+        let recovered = State::load_state(&path, EXAMPLE_CHAIN_ID.into()).unwrap();
+        assert_eq!(
+            recovered.consensus_state(),
+            &state!(5, 1, 2, block_id!(EXAMPLE_BLOCK_ID))
+        );
+        assert!(!tmp_path.exists());
+    }
+
+    #[test]
+    fn seed_initial_height_test() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("priv_validator_state.json");
+
+        let mut state = State::load_state(&path, EXAMPLE_CHAIN_ID.into()).unwrap();
+        state.seed_initial_height(100u64.into()).unwrap();
+        assert_eq!(state.consensus_state(), &state!(99, 0, 0, None));
+
+        // persisted, and never lowers the state
+        let mut state = State::load_state(&path, EXAMPLE_CHAIN_ID.into()).unwrap();
+        state.seed_initial_height(50u64.into()).unwrap();
+        assert_eq!(state.consensus_state(), &state!(99, 0, 0, None));
+
+        // signing at the initial height is still possible
+        state
+            .update_consensus_state(state!(100, 0, 0, block_id!(EXAMPLE_BLOCK_ID)))
+            .unwrap();
+    }
+
+    #[test]
+    fn import_test() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("priv_validator_state.json");
+
+        let mut state = State::load_state(&path, EXAMPLE_CHAIN_ID.into()).unwrap();
+        state
+            .import(state!(5, 1, 2, block_id!(EXAMPLE_BLOCK_ID)))
+            .unwrap();
+        assert_eq!(
+            State::read_consensus_state(&path, &EXAMPLE_CHAIN_ID.into()).unwrap(),
+            state!(5, 1, 2, block_id!(EXAMPLE_BLOCK_ID))
+        );
+
+        // importing the same state again is harmless
+        state
+            .import(state!(5, 1, 2, block_id!(EXAMPLE_BLOCK_ID)))
+            .unwrap();
+
+        // lower or conflicting states are refused
+        assert!(state.import(state!(5, 1, 1, None)).is_err());
+        assert!(state
+            .import(state!(5, 1, 2, block_id!(EXAMPLE_DOUBLE_SIGN_BLOCK_ID)))
+            .is_err());
+        assert_eq!(
+            state.consensus_state(),
+            &state!(5, 1, 2, block_id!(EXAMPLE_BLOCK_ID))
+        );
+    }
 }
@@ -0,0 +1,292 @@
+//! Compact binary state file format.
+//!
+//! The file holds two fixed-size slots, each containing a record of the
+//! consensus state and the ID of the chain it belongs to, along with a
+//! sequence number and checksum. Updates are written in place to
+//! alternating slots, so if a write is torn by a crash the other slot still
+//! holds the previous (valid) record. The record with the highest sequence
+//! number and a valid checksum is the current state.
+
+use crate::{
+    chain,
+    error::{Error, ErrorKind::*},
+    prelude::*,
+};
+use sha2::{Digest, Sha256};
+use std::{convert::TryInto, fs::OpenOptions, io, os::unix::fs::FileExt, path::Path, str};
+use tendermint::{
+    block::{self, parts},
+    chain::id::MAX_LENGTH as CHAIN_ID_MAX_LENGTH,
+    consensus, Hash,
+};
+
+/// Magic bytes at the start of each record
+const MAGIC: &[u8; 4] = b"TMKS";
+
+/// Version of the record format
+const VERSION: u8 = 1;
+
+/// Size of the header and consensus state of a record
+const STATE_SIZE: usize = 103;
+
+/// Size of a record, excluding its checksum: the consensus state, followed
+/// by the length of the chain ID and the (zero-padded) chain ID itself
+const BODY_SIZE: usize = STATE_SIZE + 1 + CHAIN_ID_MAX_LENGTH;
+
+/// Size of the (truncated SHA-256) checksum at the end of each record
+const CHECKSUM_SIZE: usize = 8;
+
+/// Size of a record (and of each slot in the file)
+const RECORD_SIZE: usize = BODY_SIZE + CHECKSUM_SIZE;
+
+/// Flag set if the record has a block ID
+const FLAG_BLOCK_ID: u8 = 0x01;
+
+/// Flag set if the record's block ID has a parts set header
+const FLAG_PARTS: u8 = 0x02;
+
+/// Current record of a binary state file
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Record {
+    /// Sequence number of the record
+    pub sequence: u64,
+
+    /// Consensus state
+    pub consensus_state: consensus::State,
+
+    /// Chain the state belongs to (`None` if the state isn't associated
+    /// with a chain)
+    pub chain_id: Option<chain::Id>,
+}
+
+/// Does the given file contents look like a binary state file? (One of
+/// the slots may have been torn by a crash, but not both.)
+pub fn is_binary(bytes: &[u8]) -> bool {
+    bytes.len() == 2 * RECORD_SIZE
+        && bytes
+            .chunks(RECORD_SIZE)
+            .any(|record| record.starts_with(MAGIC))
+}
+
+/// Encode the contents of a new binary state file, with the given record
+/// in both slots
+pub fn encode_file(
+    sequence: u64,
+    state: &consensus::State,
+    chain_id: Option<&chain::Id>,
+) -> Vec<u8> {
+    encode_record(sequence, state, chain_id).repeat(2)
+}
+
+/// Decode a binary state file, returning its current record
+pub fn decode_file(bytes: &[u8]) -> Result<Record, Error> {
+    if bytes.len() != 2 * RECORD_SIZE {
+        fail!(
+            ParseError,
+            "binary state file is {} bytes (expected {})",
+            bytes.len(),
+            2 * RECORD_SIZE
+        );
+    }
+
+    bytes
+        .chunks(RECORD_SIZE)
+        .filter_map(|record| decode_record(record).ok())
+        .max_by_key(|record| record.sequence)
+        .ok_or_else(|| format_err!(ParseError, "binary state file has no valid records").into())
+}
+
+/// Write the given record in place to its slot in an existing binary state
+/// file, `fsync`ing the file afterwards if requested
+pub fn write_record(
+    path: &Path,
+    sequence: u64,
+    state: &consensus::State,
+    chain_id: Option<&chain::Id>,
+    fsync: bool,
+) -> io::Result<()> {
+    let file = OpenOptions::new().write(true).open(path)?;
+    file.write_all_at(
+        &encode_record(sequence, state, chain_id),
+        slot_offset(sequence) as u64,
+    )?;
+
+    if fsync {
+        file.sync_data()?;
+    }
+
+    Ok(())
+}
+
+/// Offset of the slot the record with the given sequence number goes in
+fn slot_offset(sequence: u64) -> usize {
+    (sequence % 2) as usize * RECORD_SIZE
+}
+
+/// Encode a single record
+fn encode_record(
+    sequence: u64,
+    state: &consensus::State,
+    chain_id: Option<&chain::Id>,
+) -> [u8; RECORD_SIZE] {
+    let mut record = [0u8; RECORD_SIZE];
+    record[..4].copy_from_slice(MAGIC);
+    record[4] = VERSION;
+    record[5..13].copy_from_slice(&sequence.to_le_bytes());
+    record[13..21].copy_from_slice(&state.height.value().to_le_bytes());
+    record[21..29].copy_from_slice(&state.round.to_le_bytes());
+    record[29] = state.step as u8;
+
+    if let Some(ref block_id) = state.block_id {
+        record[30] |= FLAG_BLOCK_ID;
+        record[31..63].copy_from_slice(block_id.hash.as_bytes());
+
+        if let Some(ref parts) = block_id.parts {
+            record[30] |= FLAG_PARTS;
+            record[63..71].copy_from_slice(&parts.total.to_le_bytes());
+            record[71..103].copy_from_slice(parts.hash.as_bytes());
+        }
+    }
+
+    if let Some(chain_id) = chain_id {
+        let chain_id = chain_id.as_str().as_bytes();
+        record[STATE_SIZE] = chain_id.len() as u8;
+        record[STATE_SIZE + 1..STATE_SIZE + 1 + chain_id.len()].copy_from_slice(chain_id);
+    }
+
+    let checksum = Sha256::digest(&record[..BODY_SIZE]);
+    record[BODY_SIZE..].copy_from_slice(&checksum[..CHECKSUM_SIZE]);
+    record
+}
+
+/// Decode a single record
+fn decode_record(record: &[u8]) -> Result<Record, Error> {
+    if !record.starts_with(MAGIC) {
+        fail!(ParseError, "missing magic bytes");
+    }
+
+    if record[4] != VERSION {
+        fail!(ParseError, "unsupported record version: {}", record[4]);
+    }
+
+    let checksum = Sha256::digest(&record[..BODY_SIZE]);
+
+    if record[BODY_SIZE..] != checksum[..CHECKSUM_SIZE] {
+        fail!(ParseError, "checksum mismatch");
+    }
+
+    let u64_at = |offset: usize| u64::from_le_bytes(record[offset..offset + 8].try_into().unwrap());
+    let hash_at = |offset: usize| Hash::Sha256(record[offset..offset + 32].try_into().unwrap());
+    let flags = record[30];
+
+    let block_id = if flags & FLAG_BLOCK_ID != 0 {
+        let parts = if flags & FLAG_PARTS != 0 {
+            Some(parts::Header::new(u64_at(63), hash_at(71)))
+        } else {
+            None
+        };
+
+        Some(block::Id::new(hash_at(31), parts))
+    } else {
+        None
+    };
+
+    let chain_id = match record[STATE_SIZE] as usize {
+        0 => None,
+        len => {
+            let chain_id = record
+                .get(STATE_SIZE + 1..STATE_SIZE + 1 + len)
+                .filter(|_| len <= CHAIN_ID_MAX_LENGTH)
+                .and_then(|bytes| str::from_utf8(bytes).ok())
+                .and_then(|chain_id| chain_id.parse().ok())
+                .ok_or_else(|| format_err!(ParseError, "invalid chain ID"))?;
+
+            Some(chain_id)
+        }
+    };
+
+    Ok(Record {
+        sequence: u64_at(5),
+        consensus_state: consensus::State {
+            height: block::Height::from(u64_at(13)),
+            round: u64_at(21) as i64,
+            step: record[29] as i8,
+            block_id,
+        },
+        chain_id,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example_state() -> consensus::State {
+        consensus::State {
+            height: 1_234_567u64.into(),
+            round: 3,
+            step: 2,
+            block_id: Some(block::Id::new(
+                Hash::Sha256([0xab; 32]),
+                Some(parts::Header::new(1, Hash::Sha256([0xcd; 32]))),
+            )),
+        }
+    }
+
+    fn chain_id() -> chain::Id {
+        "cosmoshub-3".parse().unwrap()
+    }
+
+    fn record(sequence: u64, consensus_state: consensus::State) -> Record {
+        Record {
+            sequence,
+            consensus_state,
+            chain_id: Some(chain_id()),
+        }
+    }
+
+    #[test]
+    fn file_roundtrip() {
+        let file = encode_file(7, &example_state(), Some(&chain_id()));
+        assert!(is_binary(&file));
+        assert_eq!(decode_file(&file).unwrap(), record(7, example_state()));
+
+        let nil_state = consensus::State {
+            block_id: None,
+            ..example_state()
+        };
+
+        let file = encode_file(8, &nil_state, None);
+        assert_eq!(
+            decode_file(&file).unwrap(),
+            Record {
+                chain_id: None,
+                ..record(8, nil_state)
+            }
+        );
+    }
+
+    #[test]
+    fn torn_write_falls_back_to_previous_record() {
+        let mut file = encode_file(1, &example_state(), Some(&chain_id()));
+
+        let newer_state = consensus::State {
+            step: 3,
+            ..example_state()
+        };
+
+        // Record 2 goes in slot 0; corrupt it as if the write was torn
+        file[..RECORD_SIZE].copy_from_slice(&encode_record(2, &newer_state, Some(&chain_id())));
+        assert_eq!(decode_file(&file).unwrap(), record(2, newer_state));
+
+        for byte in &mut file[..50] {
+            *byte = 0;
+        }
+
+        assert!(is_binary(&file));
+        assert_eq!(decode_file(&file).unwrap(), record(1, example_state()));
+
+        file[RECORD_SIZE + 50] ^= 0xff;
+        assert!(decode_file(&file).is_err());
+    }
+}
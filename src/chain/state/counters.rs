@@ -0,0 +1,127 @@
+//! Per-key signature counters, persisted alongside the state file.
+//!
+//! These are informational (e.g. for rotating keys after a number of
+//! signatures), and never affect signing. They're stored as JSON in
+//! `<state_file>.counters`, keyed by public key.
+
+use crate::{
+    error::{Error, ErrorKind::*},
+    prelude::*,
+};
+use std::{
+    collections::BTreeMap,
+    ffi::OsString,
+    fs::{self, File},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process,
+};
+
+/// Signature counts of each key
+#[derive(Debug)]
+pub struct Counters {
+    /// Path of the counters file (empty if the counts aren't persisted)
+    path: PathBuf,
+
+    /// Number of signatures made by each key
+    counts: BTreeMap<String, u64>,
+}
+
+impl Counters {
+    /// Load the counters kept alongside the given state file, starting from
+    /// zero if there are none yet
+    pub fn load(state_file_path: &Path) -> Result<Self, Error> {
+        // Unpersisted state: count in memory only
+        if state_file_path.as_os_str().is_empty() {
+            return Ok(Self {
+                path: PathBuf::new(),
+                counts: BTreeMap::new(),
+            });
+        }
+
+        let path = suffixed(state_file_path, ".counters");
+
+        let counts = match fs::read(&path) {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .map_err(|e| format_err!(ParseError, "error parsing {}: {}", path.display(), e))?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => fail!(IoError, "error reading {}: {}", path.display(), e),
+        };
+
+        Ok(Self { path, counts })
+    }
+
+    /// Number of signatures made by the given key
+    pub fn get(&self, key: &str) -> u64 {
+        self.counts.get(key).cloned().unwrap_or(0)
+    }
+
+    /// Count a signature by the given key and persist the counts, returning
+    /// the key's new count
+    pub fn increment(&mut self, key: &str, fsync: bool) -> Result<u64, Error> {
+        let count = self.counts.entry(key.to_owned()).or_insert(0);
+        *count += 1;
+        let count = *count;
+
+        if !self.path.as_os_str().is_empty() {
+            self.save(fsync).map_err(|e| {
+                format_err!(IoError, "error writing {}: {}", self.path.display(), e)
+            })?;
+        }
+
+        Ok(count)
+    }
+
+    /// Atomically replace the counters file with the current counts
+    fn save(&self, fsync: bool) -> io::Result<()> {
+        let tmp_path = suffixed(&self.path, &format!(".{}.tmp", process::id()));
+
+        let mut tmp_file = File::create(&tmp_path)?;
+        tmp_file.write_all(&serde_json::to_vec(&self.counts)?)?;
+
+        if fsync {
+            tmp_file.sync_all()?;
+        }
+
+        fs::rename(&tmp_path, &self.path)
+    }
+}
+
+/// The given path with a suffix appended to its file name
+fn suffixed(path: &Path, suffix: &str) -> PathBuf {
+    let mut suffixed = OsString::from(path);
+    suffixed.push(suffix);
+    suffixed.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_survive_reload() {
+        let dir = tempfile::tempdir().unwrap();
+        let state_file_path = dir.path().join("priv_validator_state.json");
+
+        let mut counters = Counters::load(&state_file_path).unwrap();
+        assert_eq!(counters.get("key-a"), 0);
+        assert_eq!(counters.increment("key-a", true).unwrap(), 1);
+        assert_eq!(counters.increment("key-a", true).unwrap(), 2);
+        assert_eq!(counters.increment("key-b", false).unwrap(), 1);
+
+        let counters = Counters::load(&state_file_path).unwrap();
+        assert_eq!(counters.get("key-a"), 2);
+        assert_eq!(counters.get("key-b"), 1);
+        assert!(dir
+            .path()
+            .join("priv_validator_state.json.counters")
+            .exists());
+    }
+
+    #[test]
+    fn unpersisted_counts() {
+        let mut counters = Counters::load(Path::new("")).unwrap();
+        assert_eq!(counters.increment("key-a", true).unwrap(), 1);
+        assert_eq!(counters.get("key-a"), 1);
+    }
+}
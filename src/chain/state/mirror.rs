@@ -0,0 +1,271 @@
+//! Best-effort mirroring of state updates to a secondary log.
+//!
+//! Each update is appended to the log as a line of JSON by a background
+//! thread, so a slow or unavailable store never delays signing: if too many
+//! updates are queued, new ones are dropped. Consecutive failures are
+//! counted, and an error is logged once they reach the configured threshold.
+//!
+//! The log is evidence for operators (e.g. to check the highest height
+//! signed before restarting with a lost state file), and is never read by
+//! the KMS itself.
+
+use crate::{
+    chain,
+    config::chain::StateMirrorConfig,
+    error::{Error, ErrorKind::*},
+    prelude::*,
+};
+use serde::Serialize;
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc, Arc,
+    },
+    thread,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tendermint::consensus;
+
+/// Maximum number of updates waiting to be mirrored
+const QUEUE_SIZE: usize = 1024;
+
+/// Entry appended to the mirror log for each state update
+#[derive(Debug, Serialize)]
+struct Entry {
+    /// Chain the state belongs to
+    chain_id: chain::Id,
+
+    /// Updated consensus state
+    #[serde(flatten)]
+    consensus_state: consensus::State,
+
+    /// When the state was updated (seconds since the Unix epoch)
+    timestamp: u64,
+}
+
+/// Handle for mirroring state updates to a log
+pub struct Mirror {
+    /// Updates waiting to be written by the mirror thread
+    sender: mpsc::SyncSender<Entry>,
+
+    /// Failure tracking shared with the mirror thread
+    failures: Arc<Failures>,
+}
+
+impl Mirror {
+    /// Start mirroring the given chain's state updates
+    pub fn spawn(config: &StateMirrorConfig, chain_id: chain::Id) -> Result<Self, Error> {
+        let (sender, receiver) = mpsc::sync_channel(QUEUE_SIZE);
+
+        let failures = Arc::new(Failures {
+            chain_id,
+            path: config.path.clone(),
+            alert_after: config.alert_after.max(1),
+            consecutive: AtomicU64::new(0),
+        });
+
+        let thread_failures = failures.clone();
+
+        thread::Builder::new()
+            .name(format!("state-mirror {}", chain_id))
+            .spawn(move || run(&receiver, &thread_failures))
+            .map_err(|e| format_err!(IoError, "error spawning state mirror thread: {}", e))?;
+
+        info!(
+            "[{}] mirroring state updates to {}",
+            chain_id,
+            config.path.display()
+        );
+
+        Ok(Self { sender, failures })
+    }
+
+    /// Queue a state update to be mirrored, without waiting for it to be
+    /// written
+    pub fn record(&self, consensus_state: &consensus::State) {
+        let entry = Entry {
+            chain_id: self.failures.chain_id,
+            consensus_state: consensus_state.clone(),
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|t| t.as_secs())
+                .unwrap_or(0),
+        };
+
+        if let Err(e) = self.sender.try_send(entry) {
+            let reason = match e {
+                mpsc::TrySendError::Full(_) => "too many updates queued",
+                mpsc::TrySendError::Disconnected(_) => "mirror thread exited",
+            };
+
+            self.failures.failed(reason);
+        }
+    }
+
+    /// Have recent state updates failed to be mirrored?
+    pub fn is_failing(&self) -> bool {
+        self.failures.consecutive.load(Ordering::SeqCst) >= self.failures.alert_after
+    }
+}
+
+/// Consecutive failures to mirror state updates
+struct Failures {
+    /// Chain the state belongs to
+    chain_id: chain::Id,
+
+    /// Path of the mirror log
+    path: PathBuf,
+
+    /// Number of consecutive failures after which to log an error
+    alert_after: u64,
+
+    /// Number of consecutive failures so far
+    consecutive: AtomicU64,
+}
+
+impl Failures {
+    /// Note a failure to mirror an update
+    fn failed(&self, reason: &str) {
+        let count = self.consecutive.fetch_add(1, Ordering::SeqCst) + 1;
+
+        if count == self.alert_after {
+            error!(
+                "[{}] failed to mirror {} consecutive state updates to {}: {}",
+                self.chain_id,
+                count,
+                self.path.display(),
+                reason
+            );
+        } else {
+            debug!(
+                "[{}] failed to mirror state update to {}: {}",
+                self.chain_id,
+                self.path.display(),
+                reason
+            );
+        }
+    }
+
+    /// Note an update was mirrored successfully
+    fn succeeded(&self) {
+        let count = self.consecutive.swap(0, Ordering::SeqCst);
+
+        if count >= self.alert_after {
+            info!(
+                "[{}] state mirror {} recovered after {} failures",
+                self.chain_id,
+                self.path.display(),
+                count
+            );
+        }
+    }
+}
+
+/// Append queued updates to the mirror log until the `Mirror` is dropped
+fn run(receiver: &mpsc::Receiver<Entry>, failures: &Failures) {
+    let mut file: Option<File> = None;
+
+    for entry in receiver {
+        match append(&mut file, &failures.path, &entry) {
+            Ok(()) => failures.succeeded(),
+            Err(e) => {
+                // Reopen the log for the next update
+                file = None;
+                failures.failed(&e.to_string());
+            }
+        }
+    }
+}
+
+/// Append an entry to the mirror log, opening it if necessary
+fn append(file: &mut Option<File>, path: &Path, entry: &Entry) -> io::Result<()> {
+    if file.is_none() {
+        *file = Some(OpenOptions::new().create(true).append(true).open(path)?);
+    }
+
+    let mut line = serde_json::to_vec(entry)?;
+    line.push(b'\n');
+
+    let file = file.as_mut().unwrap();
+    file.write_all(&line)?;
+    file.sync_data()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{fs, thread, time::Duration};
+
+    /// Wait (briefly) for the mirror thread to write the given number of
+    /// lines to the log
+    fn wait_for_lines(path: &Path, count: usize) -> Vec<String> {
+        for _ in 0..100 {
+            if let Ok(contents) = fs::read_to_string(path) {
+                let lines = contents.lines().map(str::to_owned).collect::<Vec<_>>();
+
+                if lines.len() >= count {
+                    return lines;
+                }
+            }
+
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        panic!("state mirror didn't write {} lines", count);
+    }
+
+    #[test]
+    fn appends_updates() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("mirror.log");
+
+        let config = StateMirrorConfig {
+            path: path.clone(),
+            alert_after: 3,
+        };
+
+        let mirror = Mirror::spawn(&config, "mirror-chain".parse().unwrap()).unwrap();
+
+        for height in 1..=2u64 {
+            mirror.record(&consensus::State {
+                height: height.into(),
+                ..Default::default()
+            });
+        }
+
+        let lines = wait_for_lines(&path, 2);
+        let entry: serde_json::Value = serde_json::from_str(&lines[1]).unwrap();
+        assert_eq!(entry["chain_id"], "mirror-chain");
+        assert_eq!(entry["height"], "2");
+        assert!(!mirror.is_failing());
+    }
+
+    #[test]
+    fn failures_are_tracked() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let config = StateMirrorConfig {
+            path: dir.path().join("missing").join("mirror.log"),
+            alert_after: 2,
+        };
+
+        let mirror = Mirror::spawn(&config, "mirror-chain".parse().unwrap()).unwrap();
+
+        for _ in 0..2 {
+            mirror.record(&consensus::State::default());
+        }
+
+        for _ in 0..100 {
+            if mirror.is_failing() {
+                return;
+            }
+
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        panic!("state mirror failures weren't tracked");
+    }
+}
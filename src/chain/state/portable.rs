@@ -0,0 +1,197 @@
+//! Portable, signed export of a chain's double-signing state, for migrating
+//! a validator between machines.
+
+use crate::{
+    chain,
+    error::{Error, ErrorKind::*},
+    keyring::KeyRing,
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
+use signatory::{
+    ed25519,
+    signature::{Signature as _, Verifier},
+};
+use signatory_dalek::Ed25519Verifier;
+use subtle_encoding::hex;
+use tendermint::consensus;
+
+/// Current version of the portable state format
+pub const VERSION: u32 = 1;
+
+/// Prefix of the message signed for an export.
+///
+/// This is printable ASCII, so the signed message can never be mistaken for
+/// an amino-encoded consensus message.
+const SIGNATURE_DOMAIN: &[u8] = b"tmkms state export:";
+
+/// Double-signing state of a chain, signed by its consensus key
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct PortableState {
+    /// Version of the format
+    pub version: u32,
+
+    /// Chain the state belongs to
+    pub chain_id: chain::Id,
+
+    /// Last signed consensus state
+    pub consensus_state: consensus::State,
+
+    /// Consensus public key which signed the export, as displayed for the
+    /// chain's key format
+    pub public_key: String,
+
+    /// Hex-encoded Ed25519 signature of the export
+    pub signature: String,
+}
+
+/// Fields of a `PortableState` covered by its signature
+#[derive(Serialize)]
+struct SignedFields<'a> {
+    version: u32,
+    chain_id: &'a chain::Id,
+    consensus_state: &'a consensus::State,
+}
+
+impl PortableState {
+    /// Export the given state, signing it with the keyring's consensus key
+    pub fn sign(
+        chain_id: chain::Id,
+        consensus_state: consensus::State,
+        keyring: &KeyRing,
+    ) -> Result<Self, Error> {
+        let public_key = keyring.format().serialize(keyring.default_pubkey()?);
+        let message = signed_message(VERSION, &chain_id, &consensus_state)?;
+        let signature = keyring.sign_ed25519(None, &message)?;
+
+        Ok(Self {
+            version: VERSION,
+            chain_id,
+            consensus_state,
+            public_key,
+            signature: String::from_utf8(hex::encode(signature.as_ref())).unwrap(),
+        })
+    }
+
+    /// Verify the export was signed by the keyring's consensus key
+    pub fn verify(&self, keyring: &KeyRing) -> Result<(), Error> {
+        if self.version != VERSION {
+            fail!(
+                ParseError,
+                "unsupported state export version: {} (expected {})",
+                self.version,
+                VERSION
+            );
+        }
+
+        let default_pubkey = keyring.default_pubkey()?;
+        let expected_key = keyring.format().serialize(default_pubkey);
+
+        if self.public_key != expected_key {
+            fail!(
+                VerificationError,
+                "state was exported by key {}, but this KMS has key {} for chain {}",
+                self.public_key,
+                expected_key,
+                self.chain_id
+            );
+        }
+
+        let public_key = default_pubkey
+            .ed25519()
+            .ok_or_else(|| format_err!(InvalidKey, "not an Ed25519 key: {}", expected_key))?;
+
+        let signature = hex::decode(&self.signature)
+            .ok()
+            .and_then(|bytes| ed25519::Signature::from_bytes(&bytes).ok())
+            .ok_or_else(|| format_err!(ParseError, "malformed state export signature"))?;
+
+        let message = signed_message(self.version, &self.chain_id, &self.consensus_state)?;
+
+        Ed25519Verifier::from(&public_key)
+            .verify(&message, &signature)
+            .map_err(|_| {
+                format_err!(
+                    VerificationError,
+                    "invalid signature on state export for chain {}",
+                    self.chain_id
+                )
+            })?;
+
+        Ok(())
+    }
+}
+
+/// Compute the message signed for an export
+fn signed_message(
+    version: u32,
+    chain_id: &chain::Id,
+    consensus_state: &consensus::State,
+) -> Result<Vec<u8>, Error> {
+    let fields = SignedFields {
+        version,
+        chain_id,
+        consensus_state,
+    };
+
+    let mut message = SIGNATURE_DOMAIN.to_vec();
+    serde_json::to_writer(&mut message, &fields)
+        .map_err(|e| format_err!(SerializationError, "error serializing state: {}", e))?;
+
+    Ok(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keyring::{ed25519::Signer, Format, SigningProvider};
+    use signatory::public_key::PublicKeyed;
+    use signatory_dalek::Ed25519Signer;
+    use tendermint::TendermintKey;
+
+    fn example_keyring(seed: u8) -> KeyRing {
+        let provider = Ed25519Signer::from(&ed25519::Seed::from_bytes([seed; 32]).unwrap());
+        let public_key = TendermintKey::ConsensusKey(provider.public_key().unwrap().into());
+        let signer = Signer::new(SigningProvider::SoftSign, public_key, Box::new(provider));
+
+        let mut keyring = KeyRing::new(Format::Hex);
+        keyring.add(signer).unwrap();
+        keyring
+    }
+
+    fn example_export(keyring: &KeyRing) -> PortableState {
+        let consensus_state = consensus::State {
+            height: 12345u64.into(),
+            round: 2,
+            step: 1,
+            block_id: None,
+        };
+
+        PortableState::sign("example-chain".parse().unwrap(), consensus_state, keyring).unwrap()
+    }
+
+    #[test]
+    fn export_roundtrip() {
+        let keyring = example_keyring(1);
+        let export = example_export(&keyring);
+
+        let json = serde_json::to_string(&export).unwrap();
+        let parsed: PortableState = serde_json::from_str(&json).unwrap();
+        parsed.verify(&keyring).unwrap();
+    }
+
+    #[test]
+    fn tampered_export_is_rejected() {
+        let keyring = example_keyring(1);
+        let mut export = example_export(&keyring);
+        export.consensus_state.height = 1u64.into();
+        assert!(export.verify(&keyring).is_err());
+    }
+
+    #[test]
+    fn export_from_other_key_is_rejected() {
+        let export = example_export(&example_keyring(1));
+        assert!(export.verify(&example_keyring(2)).is_err());
+    }
+}
@@ -0,0 +1,213 @@
+//! Summary of the chains and keys loaded at startup, so operators can check
+//! the whole configuration at a glance.
+//!
+//! The summary is logged (one line per chain) once all keys are loaded, and
+//! also logged as a single JSON object at the `debug` level.
+
+use super::Registry;
+use crate::{
+    config::{
+        chain::{PubKeyEncoding, StateFormat},
+        KmsConfig,
+    },
+    keyring::Metadata,
+    prelude::*,
+};
+use serde::Serialize;
+use std::path::PathBuf;
+
+/// Summary of the loaded configuration
+#[derive(Debug, Serialize)]
+pub struct Summary {
+    /// Chains the KMS is signing for
+    pub chains: Vec<ChainSummary>,
+
+    /// Listen address of the management API (if enabled)
+    pub management: Option<String>,
+
+    /// Listen address of the sign server (if enabled)
+    pub sign_server: Option<String>,
+}
+
+/// Summary of a loaded chain
+#[derive(Debug, Serialize)]
+pub struct ChainSummary {
+    /// Chain ID
+    pub id: String,
+
+    /// Is the chain enabled (i.e. connecting to its validators)?
+    pub enabled: bool,
+
+    /// Encoding of public key responses (i.e. validator protocol version)
+    pub pubkey_encoding: PubKeyEncoding,
+
+    /// Path of the state file
+    pub state_file: PathBuf,
+
+    /// Format of the state file
+    pub state_format: StateFormat,
+
+    /// Addresses of the validators the KMS connects to for this chain
+    pub validators: Vec<String>,
+
+    /// Keys loaded for this chain
+    pub keys: Vec<KeySummary>,
+}
+
+/// Summary of a loaded key
+#[derive(Debug, Serialize)]
+pub struct KeySummary {
+    /// Provider holding the key
+    pub provider: String,
+
+    /// Signature algorithm used by the key
+    pub algorithm: String,
+
+    /// Public key, as displayed for the chain's key format
+    pub public_key: String,
+
+    /// Operator-defined metadata for the key
+    pub metadata: Metadata,
+}
+
+impl Summary {
+    /// Summarize the chains registered from the given configuration
+    pub fn new(registry: &Registry, config: &KmsConfig) -> Self {
+        let chains = config
+            .chain
+            .iter()
+            .filter_map(|chain_config| {
+                let chain = registry.get_chain(&chain_config.id)?;
+
+                let keys = chain
+                    .keyring
+                    .signers()
+                    .map(|signer| KeySummary {
+                        provider: signer.provider().to_string(),
+                        algorithm: signer.algorithm().to_string(),
+                        public_key: chain.keyring.format().serialize(signer.public_key()),
+                        metadata: signer.metadata().clone(),
+                    })
+                    .collect();
+
+                let validators = config
+                    .validator
+                    .iter()
+                    .filter(|validator| validator.chain_id == chain.id)
+                    .map(|validator| validator.addr.to_string())
+                    .collect();
+
+                Some(ChainSummary {
+                    id: chain.id.to_string(),
+                    enabled: chain.is_enabled(),
+                    pubkey_encoding: chain_config.pubkey_encoding,
+                    state_file: chain_config.state_file_path(),
+                    state_format: chain_config.state_format,
+                    validators,
+                    keys,
+                })
+            })
+            .collect();
+
+        Self {
+            chains,
+            management: config
+                .management
+                .as_ref()
+                .map(|management| management.laddr.to_string()),
+            sign_server: config
+                .sign_server
+                .as_ref()
+                .map(|sign_server| sign_server.laddr.to_string()),
+        }
+    }
+
+    /// Log the summary
+    pub fn log(&self) {
+        for chain in &self.chains {
+            let keys = chain
+                .keys
+                .iter()
+                .map(|key| format!("{} {} key {}", key.provider, key.algorithm, key.public_key))
+                .collect::<Vec<_>>();
+
+            info!(
+                "[{}] loaded {} key(s): {}; validators: {}{}; pubkey encoding: {}; state: {} ({})",
+                chain.id,
+                keys.len(),
+                none_if_empty(&keys),
+                none_if_empty(&chain.validators),
+                if chain.enabled { "" } else { " (disabled)" },
+                chain.pubkey_encoding,
+                chain.state_file.display(),
+                chain.state_format
+            );
+        }
+
+        if let Some(ref laddr) = self.management {
+            info!("management API: {}", laddr);
+        }
+
+        if let Some(ref laddr) = self.sign_server {
+            info!("sign server: {}", laddr);
+        }
+
+        match serde_json::to_string(self) {
+            Ok(json) => debug!("startup summary: {}", json),
+            Err(e) => debug!("couldn't serialize startup summary: {}", e),
+        }
+    }
+}
+
+/// Join the given items, or `none` if there aren't any
+fn none_if_empty(items: &[String]) -> String {
+    if items.is_empty() {
+        "none".to_owned()
+    } else {
+        items.join(", ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chain::{Chain, State};
+
+    #[test]
+    fn summarizes_chains_and_validators() {
+        let config: KmsConfig = toml::from_str(
+            r#"
+                [[chain]]
+                id = "mainnet"
+                key_format = { type = "hex" }
+                state_format = "binary"
+
+                [[validator]]
+                addr = "unix:///tmp/mainnet.sock"
+                chain_id = "mainnet"
+
+                [providers]
+            "#,
+        )
+        .unwrap();
+
+        let mut registry = Registry::default();
+        registry
+            .register_chain(Chain::with_state(&config.chain[0], State::unpersisted()).unwrap())
+            .unwrap();
+
+        let summary = Summary::new(&registry, &config);
+        assert_eq!(summary.chains.len(), 1);
+        assert_eq!(summary.management, None);
+
+        let chain = &summary.chains[0];
+        assert_eq!(chain.id, "mainnet");
+        assert_eq!(chain.validators, &["unix:///tmp/mainnet.sock"]);
+        assert_eq!(chain.state_format, StateFormat::Binary);
+        assert!(chain.keys.is_empty());
+
+        let json = serde_json::to_value(&summary).unwrap();
+        assert_eq!(json["chains"][0]["state_format"], "binary");
+        assert_eq!(json["chains"][0]["pubkey_encoding"], "amino");
+    }
+}
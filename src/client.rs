@@ -12,7 +12,15 @@ use crate::{
     prelude::*,
     session::Session,
 };
-use std::{panic, process::exit, thread, time::Duration};
+use std::{
+    panic,
+    process::exit,
+    thread,
+    time::{Duration, Instant},
+};
+use throttle::{Throttle, Verdict};
+
+mod throttle;
 
 /// Join handle type used by our clients
 type JoinHandle = thread::JoinHandle<Result<(), Error>>;
@@ -65,13 +73,39 @@ impl Client {
 
 /// Main loop for all clients. Handles reconnecting in the event of an error
 fn main_loop(config: ValidatorConfig) -> Result<(), Error> {
+    wait_until_enabled(&config);
+
+    let mut throttle = Throttle::new(Duration::from_secs(config.error_log_interval_secs));
+
     while let Err(e) = run_client(config.clone()) {
         // `PoisonError` is unrecoverable
         if *e.kind() == ErrorKind::PoisonError {
             error!("[{}@{}] FATAL -- {}", &config.chain_id, &config.addr, e);
             return Err(e);
-        } else {
-            error!("[{}@{}] {}", &config.chain_id, &config.addr, e);
+        }
+
+        let message = e.to_string();
+
+        match throttle.check(&message, Instant::now()) {
+            Verdict::Log { suppressed } => {
+                if suppressed > 0 {
+                    warn!(
+                        "[{}@{}] previous error repeated x{} before changing",
+                        &config.chain_id, &config.addr, suppressed
+                    );
+                }
+
+                error!("[{}@{}] {}", &config.chain_id, &config.addr, message);
+            }
+            Verdict::Suppress => (),
+            Verdict::Summarize { count, period } => warn!(
+                "[{}@{}] {} x{} in last {}s",
+                &config.chain_id,
+                &config.addr,
+                message,
+                count,
+                period.as_secs()
+            ),
         }
 
         if config.reconnect {
@@ -85,6 +119,30 @@ fn main_loop(config: ValidatorConfig) -> Result<(), Error> {
     Ok(())
 }
 
+/// Wait until the validator's chain is enabled before connecting
+fn wait_until_enabled(config: &ValidatorConfig) {
+    let is_enabled = || {
+        chain::REGISTRY
+            .get()
+            .get_chain(&config.chain_id)
+            .map(|chain| chain.is_enabled())
+            .unwrap_or(false)
+    };
+
+    if is_enabled() {
+        return;
+    }
+
+    info!(
+        "[{}@{}] waiting for chain to be enabled before connecting",
+        &config.chain_id, &config.addr
+    );
+
+    while !is_enabled() {
+        thread::sleep(Duration::from_secs(RESPAWN_DELAY));
+    }
+}
+
 /// Ensure chain with given ID is properly registered
 pub fn register_chain(chain_id: &chain::Id) {
     let registry = chain::REGISTRY.get();
@@ -104,3 +162,66 @@ pub fn run_client(config: ValidatorConfig) -> Result<(), Error> {
     panic::catch_unwind(move || Session::open(config)?.request_loop())
         .unwrap_or_else(|e| Err(Error::from_panic(e)))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        chain::{state::State, Chain},
+        config::chain::ChainConfig,
+    };
+    use std::{io, os::unix::net::UnixListener};
+
+    #[test]
+    fn waits_until_chain_is_enabled_before_connecting() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket_path = dir.path().join("validator.sock");
+        let listener = UnixListener::bind(&socket_path).unwrap();
+        listener.set_nonblocking(true).unwrap();
+
+        let chain_id: chain::Id = "client-enable-test".parse().unwrap();
+        let chain_config: ChainConfig = toml::from_str(&format!(
+            "id = \"{}\"\nkey_format = {{ type = \"hex\" }}\n",
+            chain_id
+        ))
+        .unwrap();
+
+        let chain = Chain::with_state(&chain_config, State::unpersisted()).unwrap();
+        chain::REGISTRY.register(chain).unwrap();
+
+        let config: ValidatorConfig = toml::from_str(&format!(
+            "addr = \"unix://{}\"\nchain_id = \"{}\"\nreconnect = false\n",
+            socket_path.display(),
+            chain_id
+        ))
+        .unwrap();
+
+        let client = thread::spawn(move || main_loop(config));
+
+        // Disabled chains don't connect to their validators
+        thread::sleep(Duration::from_secs(2 * RESPAWN_DELAY));
+        assert_eq!(
+            listener.accept().unwrap_err().kind(),
+            io::ErrorKind::WouldBlock
+        );
+
+        chain::REGISTRY.get().get_chain(&chain_id).unwrap().enable();
+
+        let deadline = Instant::now() + Duration::from_secs(5 * RESPAWN_DELAY);
+
+        let connection = loop {
+            match listener.accept() {
+                Ok((connection, _)) => break connection,
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                    assert!(Instant::now() < deadline, "client didn't connect");
+                    thread::sleep(Duration::from_millis(10));
+                }
+                Err(e) => panic!("error accepting connection: {}", e),
+            }
+        };
+
+        // Closing the connection ends the session (without reconnecting)
+        drop(connection);
+        assert!(client.join().unwrap().is_err());
+    }
+}
@@ -0,0 +1,136 @@
+//! Throttling for repeated error messages.
+//!
+//! When a validator is unreachable the reconnect loop fails on every retry,
+//! usually with the same error. Rather than logging each one, the first
+//! occurrence is logged and identical errors are counted and summarized
+//! once per interval.
+
+use std::time::{Duration, Instant};
+
+/// What to do with an error message
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Verdict {
+    /// Log the message. `suppressed` is the number of occurrences of the
+    /// previous (different) message which were suppressed and not yet
+    /// summarized.
+    Log {
+        /// Number of unsummarized occurrences of the previous message
+        suppressed: u64,
+    },
+
+    /// Don't log the message: it's a repeat within the current interval
+    Suppress,
+
+    /// Log a summary: the message occurred `count` times in `period`
+    Summarize {
+        /// Number of occurrences since the message was last logged
+        count: u64,
+
+        /// Time since the message was last logged
+        period: Duration,
+    },
+}
+
+/// Deduplicates identical error messages
+#[derive(Debug)]
+pub struct Throttle {
+    /// Minimum interval between logging identical messages
+    interval: Duration,
+
+    /// Last message seen and when it was last logged
+    last: Option<(String, Instant)>,
+
+    /// Occurrences of the last message since it was last logged
+    suppressed: u64,
+}
+
+impl Throttle {
+    /// Create a new throttle which logs identical messages at most once per
+    /// `interval`. A zero interval disables throttling.
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            last: None,
+            suppressed: 0,
+        }
+    }
+
+    /// Decide what to do about an error message which occurred at `now`
+    pub fn check(&mut self, message: &str, now: Instant) -> Verdict {
+        if self.interval == Duration::from_secs(0) {
+            return Verdict::Log { suppressed: 0 };
+        }
+
+        if let Some((last_message, logged_at)) = &mut self.last {
+            if last_message == message {
+                self.suppressed += 1;
+
+                let period = now.duration_since(*logged_at);
+
+                if period < self.interval {
+                    return Verdict::Suppress;
+                }
+
+                let count = self.suppressed;
+                *logged_at = now;
+                self.suppressed = 0;
+                return Verdict::Summarize { count, period };
+            }
+        }
+
+        let suppressed = self.suppressed;
+        self.last = Some((message.to_owned(), now));
+        self.suppressed = 0;
+        Verdict::Log { suppressed }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repeated_errors_are_summarized() {
+        let mut throttle = Throttle::new(Duration::from_secs(60));
+        let start = Instant::now();
+
+        assert_eq!(
+            throttle.check("connection refused", start),
+            Verdict::Log { suppressed: 0 }
+        );
+
+        for secs in 1..60 {
+            let now = start + Duration::from_secs(secs);
+            assert_eq!(throttle.check("connection refused", now), Verdict::Suppress);
+        }
+
+        assert_eq!(
+            throttle.check("connection refused", start + Duration::from_secs(60)),
+            Verdict::Summarize {
+                count: 60,
+                period: Duration::from_secs(60)
+            }
+        );
+
+        let now = start + Duration::from_secs(61);
+        assert_eq!(throttle.check("connection refused", now), Verdict::Suppress);
+
+        assert_eq!(
+            throttle.check("connection reset", now),
+            Verdict::Log { suppressed: 1 }
+        );
+    }
+
+    #[test]
+    fn zero_interval_disables_throttling() {
+        let mut throttle = Throttle::new(Duration::from_secs(0));
+        let now = Instant::now();
+
+        for _ in 0..3 {
+            assert_eq!(
+                throttle.check("connection refused", now),
+                Verdict::Log { suppressed: 0 }
+            );
+        }
+    }
+}
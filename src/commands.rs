@@ -1,14 +1,23 @@
 //! Subcommands of the `tmkms` command-line application
 
+mod bench;
+mod config;
+#[cfg(feature = "debug-tools")]
+mod debug;
 #[cfg(feature = "ledgertm")]
 mod ledger;
 #[cfg(feature = "softsign")]
 mod softsign;
 mod start;
+mod state;
+mod test;
+mod validate;
 mod version;
 #[cfg(feature = "yubihsm")]
 mod yubihsm;
 
+#[cfg(feature = "debug-tools")]
+pub use self::debug::DebugCommand;
 #[cfg(feature = "ledgertm")]
 pub use self::ledger::LedgerCommand;
 #[cfg(feature = "softsign")]
@@ -16,9 +25,14 @@ pub use self::softsign::SoftsignCommand;
 #[cfg(feature = "yubihsm")]
 pub use self::yubihsm::YubihsmCommand;
 
-pub use self::{start::StartCommand, version::VersionCommand};
-use crate::config::{KmsConfig, CONFIG_ENV_VAR, CONFIG_FILE_NAME};
-use abscissa_core::{Command, Configurable, Help, Options, Runnable};
+pub use self::{
+    bench::BenchCommand, config::ConfigCommand, start::StartCommand, state::StateCommand,
+    test::TestCommand, validate::ValidateCommand, version::VersionCommand,
+};
+use crate::config::{config_dir, KmsConfig, CONFIG_ENV_VAR, CONFIG_FILE_NAME};
+use abscissa_core::{
+    Command, Configurable, FrameworkError, FrameworkErrorKind, Help, Options, Runnable,
+};
 use std::{env, path::PathBuf};
 
 /// Subcommands of the KMS command-line application
@@ -28,10 +42,30 @@ pub enum KmsCommand {
     #[options(help = "show help for a command")]
     Help(Help<Self>),
 
+    /// `bench` subcommand
+    #[options(help = "benchmark the signing throughput of a chain's keys")]
+    Bench(BenchCommand),
+
+    /// `config` subcommand
+    #[options(help = "inspect configuration files")]
+    Config(ConfigCommand),
+
     /// `start` subcommand
     #[options(help = "start the KMS application")]
     Start(StartCommand),
 
+    /// `state` subcommand
+    #[options(help = "export and import double-signing state")]
+    State(StateCommand),
+
+    /// `test` subcommand
+    #[options(help = "sign and verify a test message with each configured key")]
+    Test(TestCommand),
+
+    /// `validate` subcommand
+    #[options(help = "check the configuration file, reporting every problem found")]
+    Validate(ValidateCommand),
+
     /// `version` subcommand
     #[options(help = "display version information")]
     Version(VersionCommand),
@@ -50,6 +84,11 @@ pub enum KmsCommand {
     #[cfg(feature = "softsign")]
     #[options(help = "subcommands for software signer")]
     Softsign(SoftsignCommand),
+
+    /// `debug` subcommand
+    #[cfg(feature = "debug-tools")]
+    #[options(help = "developer tools for debugging")]
+    Debug(DebugCommand),
 }
 
 impl KmsCommand {
@@ -69,7 +108,10 @@ impl Configurable<KmsConfig> for KmsCommand {
     /// or the default
     fn config_path(&self) -> Option<PathBuf> {
         let config = match self {
+            KmsCommand::Bench(bench) => bench.config.as_ref(),
             KmsCommand::Start(start) => start.config.as_ref(),
+            KmsCommand::State(state) => state.config_path(),
+            KmsCommand::Test(test) => test.config.as_ref(),
             #[cfg(feature = "yubihsm")]
             KmsCommand::Yubihsm(yubihsm) => yubihsm.config_path(),
             #[cfg(feature = "ledgertm")]
@@ -84,4 +126,29 @@ impl Configurable<KmsConfig> for KmsCommand {
 
         Some(path)
     }
+
+    /// Merge in the files in the configuration's `config_dir` (if any), and
+    /// warn about any deprecated settings
+    fn process_config(&self, mut config: KmsConfig) -> Result<KmsConfig, FrameworkError> {
+        if let Some(dir) = config.config_dir.clone() {
+            // `config_path` always returns a path for commands which load a config
+            let main_path = self.config_path().unwrap_or_default();
+            let dir = match main_path.parent() {
+                Some(parent) => parent.join(dir),
+                None => dir,
+            };
+
+            config_dir::merge(&mut config, &dir, &main_path)
+                .map_err(|e| FrameworkErrorKind::ConfigError.context(e))?;
+        }
+
+        #[cfg(feature = "yubihsm")]
+        for yubihsm_config in &config.providers.yubihsm {
+            if let Some(warning) = yubihsm_config.auth.deprecation_warning() {
+                abscissa_core::status_warn!("{}", warning);
+            }
+        }
+
+        Ok(config)
+    }
 }
@@ -0,0 +1,132 @@
+//! Benchmark the signing throughput of the configured keys
+
+use crate::{
+    chain,
+    error::Error,
+    keyring::{
+        ed25519::Signer,
+        self_test::{self_test, TEST_MESSAGE},
+    },
+    prelude::*,
+};
+use abscissa_core::{Command, Options, Runnable};
+use std::{
+    path::PathBuf,
+    process,
+    time::{Duration, Instant},
+};
+
+/// The `bench` command: sign the self-test message repeatedly with each of a
+/// chain's keys, discarding the signatures, and report throughput and
+/// latency. Signing state is never touched and no connections are made.
+#[derive(Command, Debug, Default, Options)]
+pub struct BenchCommand {
+    /// Path to configuration file
+    #[options(short = "c", long = "config", help = "path to tmkms.toml")]
+    pub config: Option<PathBuf>,
+
+    /// Chain ID whose keys should be benchmarked
+    #[options(short = "i", long = "chain-id", help = "chain ID to benchmark")]
+    pub chain_id: Option<chain::Id>,
+
+    /// Number of signatures to compute with each key
+    #[options(
+        short = "n",
+        long = "count",
+        default = "1000",
+        help = "signatures per key (default 1000)"
+    )]
+    pub count: usize,
+}
+
+impl Runnable for BenchCommand {
+    /// Benchmark each key of the given chain
+    fn run(&self) {
+        let chain_id = self.chain_id.unwrap_or_else(|| {
+            status_err!("no chain ID given (use --chain-id)");
+            process::exit(1);
+        });
+
+        if self.count == 0 {
+            status_err!("--count must be at least 1");
+            process::exit(1);
+        }
+
+        let registry = chain::load_unpersisted(&app_config()).unwrap_or_else(|e| {
+            status_err!("error loading configuration: {}", e);
+            process::exit(1);
+        });
+
+        let chain = registry.get_chain(&chain_id).unwrap_or_else(|| {
+            status_err!("unknown chain: {}", chain_id);
+            process::exit(1);
+        });
+
+        let mut failed = 0;
+
+        for signer in chain.keyring.signers() {
+            let public_key = chain.keyring.format().serialize(signer.public_key());
+
+            match bench(signer, self.count) {
+                Ok(report) => status_ok!(
+                    "Benchmarked",
+                    "[{}] {} {} key {}: {}",
+                    chain_id,
+                    signer.provider(),
+                    signer.algorithm(),
+                    public_key,
+                    report
+                ),
+                Err(e) => {
+                    status_err!(
+                        "[{}] {} {} key {}: {}",
+                        chain_id,
+                        signer.provider(),
+                        signer.algorithm(),
+                        public_key,
+                        e
+                    );
+                    failed += 1;
+                }
+            }
+        }
+
+        if failed > 0 {
+            process::exit(1);
+        }
+    }
+}
+
+/// Self-test the given signer, then sign the test message `count` times,
+/// returning a summary of the throughput and latencies
+fn bench(signer: &Signer, count: usize) -> Result<String, Error> {
+    self_test(signer)?;
+
+    let mut latencies = Vec::with_capacity(count);
+    let started_at = Instant::now();
+
+    for _ in 0..count {
+        let signed_at = Instant::now();
+        signer.sign(TEST_MESSAGE)?;
+        latencies.push(signed_at.elapsed());
+    }
+
+    let elapsed = started_at.elapsed();
+    latencies.sort();
+
+    Ok(format!(
+        "{} signatures in {:?} ({:.1}/s), latency p50 {:?} p90 {:?} p99 {:?} max {:?}",
+        count,
+        elapsed,
+        count as f64 / elapsed.as_secs_f64(),
+        percentile(&latencies, 50),
+        percentile(&latencies, 90),
+        percentile(&latencies, 99),
+        latencies[count - 1]
+    ))
+}
+
+/// Get the given percentile of a (sorted, non-empty) list of latencies
+fn percentile(sorted: &[Duration], percent: usize) -> Duration {
+    sorted[(sorted.len() * percent / 100).min(sorted.len() - 1)]
+}
@@ -0,0 +1,18 @@
+//! `tmkms config` CLI (sub)commands
+
+mod diff;
+
+use self::diff::DiffCommand;
+use abscissa_core::{Command, Help, Options, Runnable};
+
+/// The `config` subcommand
+#[derive(Command, Debug, Options, Runnable)]
+pub enum ConfigCommand {
+    /// Show help for the `config` subcommand
+    #[options(help = "show help for the 'config' subcommand")]
+    Help(Help<Self>),
+
+    /// Show the effective differences between two configuration files
+    #[options(help = "show the effective differences between two configuration files")]
+    Diff(DiffCommand),
+}
@@ -0,0 +1,56 @@
+//! `tmkms config diff` command
+
+use crate::{
+    config::diff::{diff, parse},
+    prelude::*,
+};
+use abscissa_core::{Command, Options, Runnable};
+use std::{fs, path::PathBuf, process};
+
+/// `diff` command: compare the effective settings of two configuration files,
+/// including defaults neither file spells out
+#[derive(Command, Debug, Default, Options)]
+pub struct DiffCommand {
+    /// Paths to the configuration files to compare
+    #[options(free, help = "paths to the two configuration files")]
+    pub paths: Vec<PathBuf>,
+}
+
+impl Runnable for DiffCommand {
+    /// Print the differences between the two configuration files
+    fn run(&self) {
+        if self.paths.len() != 2 {
+            status_err!("expected exactly two configuration files");
+            eprintln!("\nUsage: tmkms config diff <a.toml> <b.toml>");
+            process::exit(1);
+        }
+
+        let configs = self
+            .paths
+            .iter()
+            .map(|path| {
+                fs::read_to_string(path)
+                    .map_err(|e| e.to_string())
+                    .and_then(|toml_string| parse(&toml_string).map_err(|e| e.to_string()))
+                    .unwrap_or_else(|e| {
+                        status_err!("couldn't load {}: {}", path.display(), e);
+                        process::exit(1);
+                    })
+            })
+            .collect::<Vec<_>>();
+
+        let differences = diff(&configs[0], &configs[1]);
+
+        if differences.is_empty() {
+            status_ok!("Identical", "no effective differences");
+            return;
+        }
+
+        for difference in &differences {
+            println!("{}", difference);
+        }
+
+        // Like `diff(1)`, exit with status 1 if the files differ
+        process::exit(1);
+    }
+}
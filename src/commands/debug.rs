@@ -0,0 +1,19 @@
+//! `tmkms debug` CLI (sub)commands: developer tools, only available in
+//! builds with the `debug-tools` feature
+
+mod decode_session;
+
+use self::decode_session::DecodeSessionCommand;
+use abscissa_core::{Command, Help, Options, Runnable};
+
+/// The `debug` subcommand
+#[derive(Command, Debug, Options, Runnable)]
+pub enum DebugCommand {
+    /// Show help for the `debug` subcommand
+    #[options(help = "show help for the 'debug' subcommand")]
+    Help(Help<Self>),
+
+    /// Decrypt and decode a captured secret connection session
+    #[options(help = "decrypt and print the messages in a captured secret connection")]
+    DecodeSession(DecodeSessionCommand),
+}
@@ -0,0 +1,125 @@
+//! `tmkms debug decode-session` command
+
+use crate::{
+    connection::secret_connection::{capture::DecryptedStream, DEFAULT_CIPHER_SUITE},
+    error::{Error, ErrorKind::*},
+    prelude::*,
+    rpc::{Request, Response},
+};
+use abscissa_core::{Command, Options, Runnable};
+use std::{convert::TryInto, fs, path::PathBuf, process};
+use subtle_encoding::hex;
+
+/// `decode-session` command: decrypt the traffic sent in one direction of a
+/// secret connection and print the messages it carried.
+///
+/// The capture is a hex dump of the raw bytes sent in that direction, from
+/// the start of the connection (e.g. Wireshark's "Follow TCP Stream" for one
+/// side, saved as raw). The key is logged at debug level by KMS builds with
+/// the `debug-tools` feature: use `recv` to decode requests from the
+/// validator, and `send` with `--responses` to decode the KMS's responses.
+#[derive(Command, Debug, Default, Options)]
+pub struct DecodeSessionCommand {
+    /// Session key for the captured direction (hex)
+    #[options(
+        short = "k",
+        long = "key",
+        help = "session key for this direction (hex)"
+    )]
+    pub key: Option<String>,
+
+    /// Decode responses sent by the KMS rather than requests
+    #[options(short = "r", long = "responses", help = "decode KMS responses")]
+    pub responses: bool,
+
+    /// Path to the hex dump of the captured traffic
+    #[options(free, help = "path to a hex dump of the captured traffic")]
+    pub path: Option<PathBuf>,
+}
+
+impl Runnable for DecodeSessionCommand {
+    /// Decrypt and print the captured session
+    fn run(&self) {
+        let (key, path) = match (&self.key, &self.path) {
+            (Some(key), Some(path)) => (key, path),
+            _ => {
+                status_err!("a session key and a capture are required");
+                eprintln!(
+                    "\nUsage: tmkms debug decode-session -k <key> [--responses] <capture.hex>"
+                );
+                process::exit(1);
+            }
+        };
+
+        let stream = decrypt_capture(key, path).unwrap_or_else(|e| {
+            status_err!("{}", e);
+            process::exit(1);
+        });
+
+        if let Some(eph_pubkey) = &stream.eph_pubkey {
+            println!(
+                "ephemeral public key: {}",
+                String::from_utf8(hex::encode(eph_pubkey)).unwrap()
+            );
+        }
+
+        println!(
+            "auth signature: key={} sig={}",
+            String::from_utf8(hex::encode(&stream.auth_sig.key)).unwrap(),
+            String::from_utf8(hex::encode(&stream.auth_sig.sig)).unwrap()
+        );
+
+        for (index, message) in stream.messages.iter().enumerate() {
+            let decoded = if self.responses {
+                Response::read(&mut message.as_slice()).map(|response| format!("{:#?}", response))
+            } else {
+                Request::read(&mut message.as_slice()).map(|request| format!("{:#?}", request))
+            };
+
+            match decoded {
+                Ok(msg) => println!("#{}: {}", index, msg),
+                Err(e) => println!(
+                    "#{}: undecodable ({}): {}",
+                    index,
+                    e,
+                    String::from_utf8(hex::encode(message)).unwrap()
+                ),
+            }
+        }
+
+        if !stream.remainder.is_empty() {
+            println!(
+                "{} trailing bytes (incomplete message): {}",
+                stream.remainder.len(),
+                String::from_utf8(hex::encode(&stream.remainder)).unwrap()
+            );
+        }
+    }
+}
+
+/// Parse the session key and capture and decrypt the capture
+fn decrypt_capture(key: &str, path: &PathBuf) -> Result<DecryptedStream, Error> {
+    let key: [u8; 32] = hex::decode(key.trim())
+        .ok()
+        .and_then(|bytes| bytes.as_slice().try_into().ok())
+        .ok_or_else(|| format_err!(ParseError, "session key must be 32 hex-encoded bytes"))?;
+
+    let dump = fs::read_to_string(path)
+        .map_err(|e| format_err!(IoError, "couldn't read {}: {}", path.display(), e))?;
+
+    let hex_digits = dump
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect::<String>();
+
+    let capture = hex::decode(&hex_digits).map_err(|e| {
+        format_err!(
+            ParseError,
+            "couldn't parse {} as hex: {}",
+            path.display(),
+            e
+        )
+    })?;
+
+    DecryptedStream::decrypt(&DEFAULT_CIPHER_SUITE, &key, &capture)
+}
@@ -1,9 +1,10 @@
 //! `tmkms softsign` CLI (sub)commands
 
+mod convert;
 mod import;
 mod keygen;
 
-use self::{import::ImportCommand, keygen::KeygenCommand};
+use self::{convert::ConvertCommand, import::ImportCommand, keygen::KeygenCommand};
 use abscissa_core::{Command, Help, Options, Runnable};
 
 /// The `softsign` subcommand
@@ -20,4 +21,8 @@ pub enum SoftsignCommand {
     /// Import an existing key into the softsign Base64 format
     #[options(help = "convert existing private key to base64 format")]
     Import(ImportCommand),
+
+    /// Convert a softsign key from one format to another
+    #[options(help = "convert a softsign key between formats (e.g. raw to base64)")]
+    Convert(ConvertCommand),
 }
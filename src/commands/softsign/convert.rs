@@ -0,0 +1,250 @@
+//! `tmkms softsign convert` command
+
+use crate::{
+    config::provider::softsign::KeyFormat,
+    error::{Error, ErrorKind::*},
+    keyring::{ed25519::softsign::load_seed, SecretKeyEncoding},
+    prelude::*,
+};
+use abscissa_core::{Command, Options, Runnable};
+use signatory::{ed25519, encoding::Encode, public_key::PublicKeyed};
+use signatory_dalek::Ed25519Signer;
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    os::unix::fs::OpenOptionsExt,
+    path::{Path, PathBuf},
+    process,
+};
+use subtle_encoding::base64;
+use zeroize::Zeroizing;
+
+/// `convert` command: convert a softsign key from one format to another
+/// (e.g. from `raw` to `base64`)
+#[derive(Command, Debug, Default, Options)]
+pub struct ConvertCommand {
+    #[options(no_short, help = "format of the input key: 'raw', 'base64' or 'json'")]
+    from: Option<String>,
+
+    #[options(no_short, help = "format to write: 'raw' or 'base64'")]
+    to: Option<String>,
+
+    #[options(short = "i", help = "path of the key to convert")]
+    input: Option<PathBuf>,
+
+    #[options(
+        short = "o",
+        help = "path to write the converted key to (must not exist)"
+    )]
+    output: Option<PathBuf>,
+}
+
+impl Runnable for ConvertCommand {
+    /// Convert a softsign key
+    fn run(&self) {
+        let (from, to, input, output) = match (&self.from, &self.to, &self.input, &self.output) {
+            (Some(from), Some(to), Some(input), Some(output)) => (from, to, input, output),
+            _ => {
+                eprintln!(
+                    "Usage: tmkms softsign convert --from FORMAT --to FORMAT --input PATH --output PATH"
+                );
+                process::exit(1);
+            }
+        };
+
+        let parse_format = |format: &str| {
+            format.parse::<KeyFormat>().unwrap_or_else(|e| {
+                status_err!("{}", e);
+                process::exit(1);
+            })
+        };
+
+        let public_key = convert(input, parse_format(from), output, parse_format(to))
+            .unwrap_or_else(|e| {
+                status_err!("{}", e);
+                process::exit(1);
+            });
+
+        status_ok!(
+            "Converted",
+            "{} ({}) to {} ({}), public key: {}",
+            input.display(),
+            from,
+            output.display(),
+            to,
+            String::from_utf8(base64::encode(public_key.as_bytes())).unwrap()
+        );
+    }
+}
+
+/// Convert the key at `input` from one format to another, writing it to
+/// `output` (which must not exist) and verifying the converted key has the
+/// same public key, which is returned
+fn convert(
+    input: &Path,
+    from: KeyFormat,
+    output: &Path,
+    to: KeyFormat,
+) -> Result<ed25519::PublicKey, Error> {
+    let seed = load_seed(input, from)?;
+    let public_key = derive_public_key(&seed)?;
+
+    let encoded = match to {
+        KeyFormat::Raw => Zeroizing::new(seed.as_secret_slice().to_vec()),
+        KeyFormat::Base64 => Zeroizing::new(
+            seed.encode_to_string(&SecretKeyEncoding::default())
+                .map_err(|e| format_err!(InvalidKey, "couldn't encode key: {}", e))?
+                .into_bytes(),
+        ),
+        KeyFormat::Json => fail!(
+            ConfigError,
+            "can't convert keys to 'json' (must be 'raw' or 'base64')"
+        ),
+    };
+
+    write_output(output, &encoded, to, &public_key)?;
+    Ok(public_key)
+}
+
+/// Write the encoded key to `output` (which must not exist), verifying it
+/// has the given public key. The file is removed again on failure.
+fn write_output(
+    output: &Path,
+    encoded: &[u8],
+    to: KeyFormat,
+    public_key: &ed25519::PublicKey,
+) -> Result<(), Error> {
+    let mut output_file = OpenOptions::new()
+        .create_new(true)
+        .write(true)
+        .mode(0o600)
+        .open(output)
+        .map_err(|e| format_err!(IoError, "couldn't create {}: {}", output.display(), e))?;
+
+    let result = output_file
+        .write_all(encoded)
+        .and_then(|()| output_file.sync_all())
+        .map_err(|e| format_err!(IoError, "couldn't write {}: {}", output.display(), e).into())
+        .and_then(|()| load_seed(output, to))
+        .and_then(|seed| derive_public_key(&seed))
+        .and_then(|converted_public_key| {
+            if converted_public_key == *public_key {
+                Ok(())
+            } else {
+                Err(format_err!(
+                    InvalidKey,
+                    "{} doesn't have the expected public key",
+                    output.display()
+                )
+                .into())
+            }
+        });
+
+    if result.is_err() {
+        drop(output_file);
+
+        if let Err(e) = fs::remove_file(output) {
+            warn!("couldn't remove {}: {}", output.display(), e);
+        }
+    }
+
+    result
+}
+
+/// Derive the public key for the given seed
+fn derive_public_key(seed: &ed25519::Seed) -> Result<ed25519::PublicKey, Error> {
+    Ed25519Signer::from(seed)
+        .public_key()
+        .map_err(|_| format_err!(InvalidKey, "couldn't derive public key").into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{fs, os::unix::fs::PermissionsExt};
+
+    #[test]
+    fn converts_raw_to_base64() {
+        let dir = tempfile::tempdir().unwrap();
+        let raw_path = dir.path().join("raw.key");
+        let base64_path = dir.path().join("base64.key");
+
+        let seed = ed25519::Seed::from_bytes([7u8; 32]).unwrap();
+        fs::write(&raw_path, seed.as_secret_slice()).unwrap();
+
+        let public_key =
+            convert(&raw_path, KeyFormat::Raw, &base64_path, KeyFormat::Base64).unwrap();
+        assert_eq!(public_key, derive_public_key(&seed).unwrap());
+
+        let mode = fs::metadata(&base64_path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+
+        // Round trip back to raw
+        let roundtrip_path = dir.path().join("roundtrip.key");
+        convert(
+            &base64_path,
+            KeyFormat::Base64,
+            &roundtrip_path,
+            KeyFormat::Raw,
+        )
+        .unwrap();
+        assert_eq!(fs::read(&roundtrip_path).unwrap(), seed.as_secret_slice());
+    }
+
+    #[test]
+    fn refuses_misdeclared_input() {
+        let dir = tempfile::tempdir().unwrap();
+        let raw_path = dir.path().join("raw.key");
+        let output_path = dir.path().join("output.key");
+
+        fs::write(&raw_path, [7u8; 32]).unwrap();
+
+        assert!(convert(&raw_path, KeyFormat::Base64, &output_path, KeyFormat::Raw).is_err());
+        assert!(!output_path.exists());
+    }
+
+    #[test]
+    fn removes_output_if_verification_fails() {
+        let dir = tempfile::tempdir().unwrap();
+        let output_path = dir.path().join("output.key");
+
+        let seed = ed25519::Seed::from_bytes([7u8; 32]).unwrap();
+        let public_key = derive_public_key(&seed).unwrap();
+        let other_public_key =
+            derive_public_key(&ed25519::Seed::from_bytes([8u8; 32]).unwrap()).unwrap();
+
+        // The written key can't be loaded back in the declared format
+        assert!(write_output(
+            &output_path,
+            seed.as_secret_slice(),
+            KeyFormat::Base64,
+            &public_key,
+        )
+        .is_err());
+        assert!(!output_path.exists());
+
+        // The written key has a different public key
+        let err = write_output(
+            &output_path,
+            seed.as_secret_slice(),
+            KeyFormat::Raw,
+            &other_public_key,
+        )
+        .unwrap_err();
+        assert_eq!(*err.kind(), InvalidKey);
+        assert!(!output_path.exists());
+    }
+
+    #[test]
+    fn refuses_to_overwrite_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let raw_path = dir.path().join("raw.key");
+        let output_path = dir.path().join("output.key");
+
+        fs::write(&raw_path, [7u8; 32]).unwrap();
+        fs::write(&output_path, b"existing").unwrap();
+
+        assert!(convert(&raw_path, KeyFormat::Raw, &output_path, KeyFormat::Base64).is_err());
+        assert_eq!(fs::read(&output_path).unwrap(), b"existing");
+    }
+}
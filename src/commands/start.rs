@@ -1,8 +1,8 @@
 //! Start the KMS
 
-use crate::{chain, client::Client, prelude::*};
+use crate::{chain, client::Client, management, prelude::*, sign_server};
 use abscissa_core::{Command, Options};
-use std::{path::PathBuf, process};
+use std::{path::PathBuf, process, thread};
 
 /// The `start` command
 #[derive(Command, Debug, Options)]
@@ -34,7 +34,9 @@ impl Runnable for StartCommand {
             env!("CARGO_PKG_VERSION")
         );
 
+        self.spawn_management();
         let validator_clients = self.spawn_clients();
+        let sign_server = self.spawn_sign_server();
 
         // Wait for all of the validator client threads to exit
         debug!("Main thread waiting on clients...");
@@ -50,6 +52,11 @@ impl Runnable for StartCommand {
             }
         }
 
+        if let Some(sign_server) = sign_server {
+            debug!("Main thread waiting on sign server...");
+            let _ = sign_server.join();
+        }
+
         if success {
             info!("Shutdown completed successfully");
         } else {
@@ -77,4 +84,34 @@ impl StartCommand {
             .map(Client::spawn)
             .collect()
     }
+
+    /// Start the management API (if configured)
+    pub fn spawn_management(&self) {
+        let management_config = match app_config().management {
+            Some(ref config) => config.clone(),
+            None => return,
+        };
+
+        management::Server::new(&management_config)
+            .and_then(management::Server::spawn)
+            .unwrap_or_else(|e| {
+                status_err!("error starting management API: {}", e);
+                process::exit(1);
+            });
+    }
+
+    /// Start the sign server (if configured). Must be called once chains
+    /// are registered.
+    pub fn spawn_sign_server(&self) -> Option<thread::JoinHandle<()>> {
+        let sign_server_config = app_config().sign_server.clone()?;
+
+        let handle = sign_server::Server::new(&sign_server_config)
+            .and_then(sign_server::Server::spawn)
+            .unwrap_or_else(|e| {
+                status_err!("error starting sign server: {}", e);
+                process::exit(1);
+            });
+
+        Some(handle)
+    }
 }
@@ -0,0 +1,34 @@
+//! `tmkms state` CLI (sub)commands
+
+mod export;
+mod import;
+
+use self::{export::ExportCommand, import::ImportCommand};
+use abscissa_core::{Command, Help, Options, Runnable};
+use std::path::PathBuf;
+
+/// The `state` subcommand
+#[derive(Command, Debug, Options, Runnable)]
+pub enum StateCommand {
+    /// Show help for the `state` subcommand
+    #[options(help = "show help for the 'state' subcommand")]
+    Help(Help<Self>),
+
+    /// Export a chain's double-signing state
+    #[options(help = "export a chain's double-signing state for migration")]
+    Export(ExportCommand),
+
+    /// Import a chain's double-signing state
+    #[options(help = "import double-signing state exported by another KMS")]
+    Import(ImportCommand),
+}
+
+impl StateCommand {
+    pub(super) fn config_path(&self) -> Option<&PathBuf> {
+        match self {
+            StateCommand::Export(export) => export.config.as_ref(),
+            StateCommand::Import(import) => import.config.as_ref(),
+            _ => None,
+        }
+    }
+}
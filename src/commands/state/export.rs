@@ -0,0 +1,82 @@
+//! `tmkms state export` command
+
+use crate::{
+    chain::{
+        self,
+        state::{portable::PortableState, State},
+    },
+    error::{Error, ErrorKind::*},
+    prelude::*,
+};
+use abscissa_core::{Command, Options, Runnable};
+use std::{fs, path::PathBuf, process};
+
+/// `export` command: write a chain's last signed state to a portable blob
+/// signed by the chain's consensus key
+#[derive(Command, Debug, Default, Options)]
+pub struct ExportCommand {
+    /// Path to configuration file
+    #[options(short = "c", long = "config", help = "path to tmkms.toml")]
+    pub config: Option<PathBuf>,
+
+    /// Chain ID whose state should be exported
+    #[options(short = "i", long = "chain-id", help = "chain ID to export")]
+    pub chain_id: Option<chain::Id>,
+
+    /// Path to write the export to (default stdout)
+    #[options(short = "o", long = "output", help = "output path (default stdout)")]
+    pub output: Option<PathBuf>,
+}
+
+impl Runnable for ExportCommand {
+    /// Export the state of the given chain
+    fn run(&self) {
+        let chain_id = self.chain_id.unwrap_or_else(|| {
+            status_err!("no chain ID given (use --chain-id)");
+            process::exit(1);
+        });
+
+        let export = export_state(chain_id).unwrap_or_else(|e| {
+            status_err!("couldn't export state for {}: {}", chain_id, e);
+            process::exit(1);
+        });
+
+        let json = serde_json::to_string_pretty(&export).unwrap();
+
+        match &self.output {
+            Some(path) => {
+                fs::write(path, json + "\n").unwrap_or_else(|e| {
+                    status_err!("couldn't write {}: {}", path.display(), e);
+                    process::exit(1);
+                });
+
+                status_ok!(
+                    "Exported",
+                    "state {} for {} to {}",
+                    export.consensus_state,
+                    chain_id,
+                    path.display()
+                );
+            }
+            None => println!("{}", json),
+        }
+    }
+}
+
+/// Read the chain's state file and sign it with the chain's consensus key
+fn export_state(chain_id: chain::Id) -> Result<PortableState, Error> {
+    let config = app_config();
+
+    let chain_config = config
+        .chain
+        .iter()
+        .find(|chain_config| chain_config.id == chain_id)
+        .ok_or_else(|| format_err!(ConfigError, "unregistered chain: {}", chain_id))?;
+
+    let consensus_state = State::read_consensus_state(&chain_config.state_file_path(), &chain_id)?;
+
+    let registry = chain::load_unpersisted(&config)?;
+    let chain = registry.get_chain(&chain_id).unwrap();
+
+    PortableState::sign(chain_id, consensus_state, &chain.keyring)
+}
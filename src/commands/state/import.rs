@@ -0,0 +1,86 @@
+//! `tmkms state import` command
+
+use crate::{
+    chain::{
+        self,
+        state::{portable::PortableState, State},
+    },
+    error::{Error, ErrorKind::*},
+    prelude::*,
+};
+use abscissa_core::{Command, Options, Runnable};
+use std::{fs, path::PathBuf, process};
+
+/// `import` command: verify a state export and write it to the chain's state
+/// file, refusing if the existing state is already ahead of it.
+///
+/// The KMS must not be running for this chain while the state is imported.
+#[derive(Command, Debug, Default, Options)]
+pub struct ImportCommand {
+    /// Path to configuration file
+    #[options(short = "c", long = "config", help = "path to tmkms.toml")]
+    pub config: Option<PathBuf>,
+
+    /// Path to the export to import
+    #[options(free, help = "path to a state export")]
+    pub path: Option<PathBuf>,
+}
+
+impl Runnable for ImportCommand {
+    /// Import the given state export
+    fn run(&self) {
+        let path = self.path.as_ref().unwrap_or_else(|| {
+            status_err!("no state export given");
+            eprintln!("\nUsage: tmkms state import [-c tmkms.toml] [export.json]");
+            process::exit(1);
+        });
+
+        let export = fs::read_to_string(path)
+            .map_err(|e| format_err!(IoError, "couldn't read {}: {}", path.display(), e).into())
+            .and_then(|json| {
+                serde_json::from_str::<PortableState>(&json).map_err(|e| {
+                    format_err!(ParseError, "couldn't parse {}: {}", path.display(), e).into()
+                })
+            })
+            .unwrap_or_else(|e: Error| {
+                status_err!("{}", e);
+                process::exit(1);
+            });
+
+        let state_file = import_state(&export).unwrap_or_else(|e| {
+            status_err!("couldn't import state for {}: {}", export.chain_id, e);
+            process::exit(1);
+        });
+
+        status_ok!(
+            "Imported",
+            "state {} for {} into {}",
+            export.consensus_state,
+            export.chain_id,
+            state_file.display()
+        );
+    }
+}
+
+/// Verify the export against the chain's consensus key and write it to the
+/// chain's state file, returning the path to the state file
+fn import_state(export: &PortableState) -> Result<PathBuf, Error> {
+    let config = app_config();
+
+    let chain_config = config
+        .chain
+        .iter()
+        .find(|chain_config| chain_config.id == export.chain_id)
+        .ok_or_else(|| format_err!(ConfigError, "unregistered chain: {}", export.chain_id))?;
+
+    let registry = chain::load_unpersisted(&config)?;
+    let chain = registry.get_chain(&export.chain_id).unwrap();
+    export.verify(&chain.keyring)?;
+
+    let state_file = chain_config.state_file_path();
+    let mut state = State::load_state(&state_file, chain_config.id)?;
+    state.set_format(chain_config.state_format)?;
+    state.import(export.consensus_state.clone())?;
+
+    Ok(state_file)
+}
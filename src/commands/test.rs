@@ -0,0 +1,62 @@
+//! Self-test the configured signing keys
+
+use crate::{chain, keyring::self_test::self_test, prelude::*};
+use abscissa_core::{Command, Options, Runnable};
+use std::{path::PathBuf, process};
+
+/// The `test` command
+#[derive(Command, Debug, Default, Options)]
+pub struct TestCommand {
+    /// Path to configuration file
+    #[options(short = "c", long = "config", help = "path to tmkms.toml")]
+    pub config: Option<PathBuf>,
+}
+
+impl Runnable for TestCommand {
+    /// Sign and verify a test message with every configured key
+    fn run(&self) {
+        let registry = chain::load_unpersisted(&app_config()).unwrap_or_else(|e| {
+            status_err!("error loading configuration: {}", e);
+            process::exit(1);
+        });
+
+        let mut failed = 0;
+
+        for chain in registry.chains() {
+            for signer in chain.keyring.signers() {
+                let mut public_key = chain.keyring.format().serialize(signer.public_key());
+
+                if !signer.metadata().is_empty() {
+                    public_key = format!("{} ({})", public_key, signer.metadata());
+                }
+
+                match self_test(signer) {
+                    Ok(()) => status_ok!(
+                        "Passed",
+                        "[{}] {} {} key {} (capabilities: {})",
+                        chain.id,
+                        signer.provider(),
+                        signer.algorithm(),
+                        public_key,
+                        signer.capabilities()
+                    ),
+                    Err(e) => {
+                        status_err!(
+                            "[{}] {} {} key {}: {}",
+                            chain.id,
+                            signer.provider(),
+                            signer.algorithm(),
+                            public_key,
+                            e
+                        );
+                        failed += 1;
+                    }
+                }
+            }
+        }
+
+        if failed > 0 {
+            process::exit(1);
+        }
+    }
+}
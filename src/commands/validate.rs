@@ -0,0 +1,54 @@
+//! Validate the configuration file, reporting every problem found
+
+use crate::{
+    config::{validate::validate, CONFIG_ENV_VAR, CONFIG_FILE_NAME},
+    prelude::*,
+};
+use abscissa_core::{Command, Options, Runnable};
+use std::{env, fs, path::PathBuf, process};
+
+/// The `validate` command
+#[derive(Command, Debug, Default, Options)]
+pub struct ValidateCommand {
+    /// Path to configuration file
+    #[options(short = "c", long = "config", help = "path to tmkms.toml")]
+    pub config: Option<PathBuf>,
+}
+
+impl Runnable for ValidateCommand {
+    /// Check the configuration file and report all problems with it
+    fn run(&self) {
+        // The configuration is read here rather than by the application, which
+        // would stop at the first error it encountered
+        let path = self
+            .config
+            .clone()
+            .or_else(|| env::var(CONFIG_ENV_VAR).ok().map(PathBuf::from))
+            .unwrap_or_else(|| PathBuf::from(CONFIG_FILE_NAME));
+
+        let toml_string = fs::read_to_string(&path).unwrap_or_else(|e| {
+            status_err!("couldn't read {}: {}", path.display(), e);
+            process::exit(1);
+        });
+
+        let problems = validate(&toml_string);
+
+        if problems.is_empty() {
+            status_ok!("Valid", "{}", path.display());
+            return;
+        }
+
+        for problem in &problems {
+            status_err!("{}", problem);
+        }
+
+        status_err!(
+            "{}: {} problem{} found",
+            path.display(),
+            problems.len(),
+            if problems.len() == 1 { "" } else { "s" }
+        );
+
+        process::exit(1);
+    }
+}
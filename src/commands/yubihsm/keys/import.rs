@@ -4,7 +4,11 @@ use super::*;
 use crate::{keyring::SecretKeyEncoding, prelude::*};
 use abscissa_core::{Command, Options, Runnable};
 use signatory::{ed25519, encoding::Decode};
-use std::{fs, path::PathBuf, process};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process,
+};
 use subtle_encoding::base64;
 use tendermint::{config::PrivValidatorKey, PrivateKey, PublicKey};
 use yubihsm::object;
@@ -32,6 +36,17 @@ pub struct ImportCommand {
     #[options(short = "l", long = "label", help = "label for priv_validator keys")]
     pub label: Option<String>,
 
+    /// Path to a softsign key to import as a non-exportable key
+    #[options(long = "from-softsign", help = "path to softsign key to import")]
+    pub from_softsign: Option<PathBuf>,
+
+    /// Format of the softsign key (either `raw`, `base64`, or `json`, default `raw`)
+    #[options(
+        long = "key-format",
+        help = "format of softsign key (raw, base64, or json)"
+    )]
+    pub key_format: Option<String>,
+
     /// Path to the key to import
     #[options(free, help = "path to key to import")]
     pub path: PathBuf,
@@ -39,6 +54,11 @@ pub struct ImportCommand {
 
 impl Runnable for ImportCommand {
     fn run(&self) {
+        if let Some(ref softsign_path) = self.from_softsign {
+            self.import_softsign(softsign_path);
+            return;
+        }
+
         let contents = fs::read_to_string(&self.path).unwrap_or_else(|e| {
             status_err!("couldn't import file {}: {}", self.path.display(), e);
             process::exit(1);
@@ -64,6 +84,90 @@ impl Runnable for ImportCommand {
 }
 
 impl ImportCommand {
+    /// Import a softsign key into the HSM as a non-exportable key
+    #[cfg(feature = "softsign")]
+    fn import_softsign(&self, softsign_path: &Path) {
+        use crate::{config::provider::softsign::KeyFormat, keyring::ed25519::softsign};
+
+        if let Some(id) = self.wrap_key_id {
+            status_warn!(
+                "ignoring wrapkey ID: {} (not applicable to softsign keys)",
+                id
+            );
+        }
+
+        let key_id = self.key_id.unwrap_or_else(|| {
+            status_err!(
+                "no key ID specified (use e.g. tmkms yubihsm keys import -i 1 --from-softsign signing.key)"
+            );
+            process::exit(1);
+        });
+
+        let key_format = match self.key_format {
+            Some(ref format) => format.parse::<KeyFormat>().unwrap_or_else(|e| {
+                status_err!("{}", e);
+                process::exit(1);
+            }),
+            None => KeyFormat::default(),
+        };
+
+        let seed = softsign::load_seed(softsign_path, key_format).unwrap_or_else(|e| {
+            status_err!("{}", e);
+            process::exit(1);
+        });
+
+        let hsm = crate::yubihsm::client();
+
+        if hsm
+            .get_object_info(key_id, object::Type::AsymmetricKey)
+            .is_ok()
+        {
+            status_err!(
+                "key 0x{:04x} already exists in the HSM; refusing to overwrite it",
+                key_id
+            );
+            process::exit(1);
+        }
+
+        let label =
+            yubihsm::object::Label::from(self.label.as_ref().map(|l| l.as_ref()).unwrap_or(""));
+
+        if let Err(e) = hsm.put_asymmetric_key(
+            key_id,
+            label,
+            DEFAULT_DOMAINS,
+            DEFAULT_CAPABILITIES,
+            yubihsm::asymmetric::Algorithm::Ed25519,
+            seed.as_secret_slice(),
+        ) {
+            status_err!("couldn't import key #{}: {}", key_id, e);
+            process::exit(1);
+        }
+
+        let public_key = hsm.get_public_key(key_id).unwrap_or_else(|e| {
+            status_err!("couldn't get public key for key #{}: {}", key_id, e);
+            process::exit(1);
+        });
+
+        status_ok!(
+            "Imported",
+            "key 0x{:04x}: {}",
+            key_id,
+            PublicKey::from_raw_ed25519(public_key.as_ref())
+                .unwrap()
+                .to_hex()
+        );
+    }
+
+    /// Importing softsign keys requires the `softsign` feature
+    #[cfg(not(feature = "softsign"))]
+    fn import_softsign(&self, _softsign_path: &Path) {
+        status_err!(
+            "importing softsign keys requires tmkms to be built with the `softsign` feature"
+        );
+        process::exit(1);
+    }
+
     /// Import a wrapped object into the HSM
     fn import_wrapped(&self, wrapped_key_base64: &str) {
         if let Some(id) = self.key_id {
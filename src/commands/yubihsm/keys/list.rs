@@ -69,12 +69,17 @@ impl Runnable for ListCommand {
                 PublicKey::from_raw_ed25519(&public_key.as_ref()).unwrap(),
             );
 
-            let public_key_serialized = match key_formatters.get(&key.object_id) {
-                Some(key_formatter) => key_formatter.serialize(tendermint_key),
-                None => tendermint_key.to_hex(),
+            let key_formatter = match key_formatters.get(&key.object_id) {
+                Some(key_formatter) => key_formatter.clone(),
+                None => keyring::Format::Hex,
             };
 
-            status_attr_ok!(key_id, public_key_serialized);
+            status_attr_ok!(
+                key_id,
+                "{} (address {})",
+                key_formatter.serialize(tendermint_key),
+                key_formatter.serialize_address(tendermint_key)
+            );
         }
     }
 }
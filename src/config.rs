@@ -1,12 +1,21 @@
 //! Configuration file structures (with serde-derived parser)
 
 pub mod chain;
+pub mod config_dir;
+pub mod diff;
+pub mod management;
 pub mod provider;
+pub mod sign_server;
+pub mod validate;
 pub mod validator;
 
 pub use self::validator::*;
-use self::{chain::ChainConfig, provider::ProviderConfig};
+use self::{
+    chain::ChainConfig, management::ManagementConfig, provider::ProviderConfig,
+    sign_server::SignServerConfig,
+};
 use serde::Deserialize;
+use std::path::PathBuf;
 
 /// Environment variable containing path to config file
 pub const CONFIG_ENV_VAR: &str = "TMKMS_CONFIG_FILE";
@@ -28,4 +37,16 @@ pub struct KmsConfig {
 
     /// Cryptographic signature provider configuration
     pub providers: ProviderConfig,
+
+    /// Management API configuration (disabled unless present)
+    pub management: Option<ManagementConfig>,
+
+    /// Signing server configuration, for serving keys to front-end KMS
+    /// instances (disabled unless present)
+    pub sign_server: Option<SignServerConfig>,
+
+    /// Directory of additional configuration files (`*.toml`) whose
+    /// `[[chain]]`, `[[validator]]` and `[providers]` entries are merged into
+    /// this configuration (relative to the directory of this file)
+    pub config_dir: Option<PathBuf>,
 }
@@ -1,8 +1,27 @@
 //! Chain configuration
 
+mod block_time_bounds;
+mod confirm;
+mod cooldown;
+mod duplicate_keys;
+mod fsync;
 mod hook;
+mod key_labels;
+mod pubkey_encoding;
+mod sign_bytes_output;
+mod signing_keys;
+mod state_format;
+mod state_mirror;
+mod steps;
+mod unknown_messages;
 
-pub use self::hook::HookConfig;
+pub use self::{
+    block_time_bounds::BlockTimeBoundsConfig, confirm::ConfirmConfig, cooldown::CooldownConfig,
+    duplicate_keys::DuplicateKeyPolicy, fsync::FsyncPolicy, hook::HookConfig,
+    key_labels::KeyTypeLabels, pubkey_encoding::PubKeyEncoding, sign_bytes_output::SignBytesOutput,
+    signing_keys::SigningKeysConfig, state_format::StateFormat, state_mirror::StateMirrorConfig,
+    steps::StepConfig, unknown_messages::UnknownMessagePolicy,
+};
 use crate::{chain, keyring};
 use serde::Deserialize;
 use std::path::PathBuf;
@@ -17,11 +36,170 @@ pub struct ChainConfig {
     /// Key serialization format configuration for this chain
     pub key_format: keyring::Format,
 
+    /// Labels displayed for account and consensus keys on this chain
+    #[serde(default)]
+    pub key_type_labels: KeyTypeLabels,
+
+    /// Consensus keys used to sign each type of message (if the chain has
+    /// more than one)
+    pub signing_keys: Option<SigningKeysConfig>,
+
+    /// What to do when several providers supply the same key (default
+    /// `reject`)
+    #[serde(default)]
+    pub duplicate_keys: DuplicateKeyPolicy,
+
+    /// What to do with messages of unrecognized types (default `close`)
+    #[serde(default)]
+    pub unknown_messages: UnknownMessagePolicy,
+
+    /// Encoding of responses to public key requests (default `amino`)
+    #[serde(default)]
+    pub pubkey_encoding: PubKeyEncoding,
+
     /// Path to chain-specific `priv_validator_state.json` file
     pub state_file: Option<PathBuf>,
 
+    /// When to `fsync` the state file (default `always`)
+    #[serde(default)]
+    pub state_fsync: FsyncPolicy,
+
+    /// On-disk format of the state file (default `json`)
+    #[serde(default)]
+    pub state_format: StateFormat,
+
+    /// Halt signing for this chain (until resumed via the management API) if
+    /// the state file can't be written (default true)
+    #[serde(default = "halt_on_state_error_default")]
+    pub halt_on_state_error: bool,
+
+    /// Sign and verify a test message with each of this chain's keys at
+    /// startup, so provider sessions are established before the first
+    /// signing request. Startup fails if any key fails the test.
+    #[serde(default)]
+    pub prewarm: bool,
+
     /// User-specified command to run to obtain the current block height for
     /// this chain. This will be executed at launch time to populate the
     /// initial block height if configured
     pub state_hook: Option<HookConfig>,
+
+    /// Mirror each state update to a secondary log, for an independent
+    /// record of the highest height signed (disabled by default)
+    pub state_mirror: Option<StateMirrorConfig>,
+
+    /// Height below which this KMS will never sign for this chain. The state
+    /// is seeded accordingly on first run.
+    pub initial_height: Option<tendermint::block::Height>,
+
+    /// Height at or above which this KMS will refuse to sign for this chain,
+    /// e.g. the halt height of a planned upgrade (can also be set via the
+    /// management API)
+    pub halt_height: Option<tendermint::block::Height>,
+
+    /// Maximum number of blocks a request may jump ahead of the last signed
+    /// height. Larger jumps halt signing until resumed via the management API
+    /// (unlimited by default)
+    pub max_height_gap: Option<u64>,
+
+    /// Hard deadline (in milliseconds) for a signer to produce a signature,
+    /// after which the request is abandoned (disabled by default)
+    pub sign_timeout_ms: Option<u64>,
+
+    /// Delay each signing response by a random amount of time up to this
+    /// many milliseconds (at most 100), to obscure the timing of signing
+    /// operations. Adds latency to every signature (disabled by default).
+    pub response_jitter_ms: Option<u64>,
+
+    /// Consensus step of each message type, for forks whose consensus orders
+    /// steps differently than Tendermint (default proposal/prevote/precommit)
+    #[serde(default)]
+    pub steps: StepConfig,
+
+    /// Maximum difference (in milliseconds) allowed between the timestamp of
+    /// an incoming vote and the local clock before refusing to sign it
+    pub max_clock_skew_ms: Option<u64>,
+
+    /// Refuse to sign votes whose timestamps are further than these bounds
+    /// behind or ahead of the local clock (disabled by default)
+    pub block_time_bounds: Option<BlockTimeBoundsConfig>,
+
+    /// Connect to this chain's validators (default false). Disabled chains
+    /// are still loaded and validated, but no connections are opened for
+    /// them until they're enabled via the management API.
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Start as a cold standby: connect to validators but refuse to sign
+    /// until explicitly promoted via the management API (default false)
+    #[serde(default)]
+    pub standby: bool,
+
+    /// Enable the `SignBytesRequest` extension for this chain. Bytes to be
+    /// signed must begin with this domain prefix, which must be at least two
+    /// printable ASCII characters so it can't collide with consensus messages
+    pub sign_bytes_domain: Option<String>,
+
+    /// Layout of `SignBytesRequest` signatures: the bare `signature`
+    /// (default), or the public key followed by the signature (`attached`)
+    #[serde(default)]
+    pub sign_bytes_output: SignBytesOutput,
+
+    /// Log the SHA-256 of the bytes signed for each consensus message, for
+    /// correlating on-chain signatures with the KMS logs (default false)
+    #[serde(default)]
+    pub log_sign_bytes_hash: bool,
+
+    /// Refuse to sign `<nil>` votes, i.e. prevotes and precommits for no
+    /// block (default false). Tendermint relies on `<nil>` votes for
+    /// liveness, so this is only for specialized deployments.
+    #[serde(default)]
+    pub refuse_nil_votes: bool,
+
+    /// Refuse to sign proposals and votes which don't re-encode to exactly
+    /// the bytes received, i.e. aren't canonically encoded (default false)
+    #[serde(default)]
+    pub require_canonical_encoding: bool,
+
+    /// Require an operator to approve each signature on the console (for
+    /// ceremonies and air-gapped test networks only)
+    pub interactive_confirm: Option<ConfirmConfig>,
+
+    /// Halt signing for this chain after repeated double-sign attempts
+    /// (disabled by default)
+    pub double_sign_cooldown: Option<CooldownConfig>,
+
+    /// Count the signatures made by each key, persisting the counts
+    /// alongside the state file (default false)
+    #[serde(default)]
+    pub count_signatures: bool,
+
+    /// Warn when a key's signature count exceeds this number, e.g. when it's
+    /// due to be rotated (implies `count_signatures`). Never blocks signing.
+    pub signature_count_threshold: Option<u64>,
+
+    /// Number of recent signing events to keep in memory (default 100)
+    #[serde(default = "event_history_default")]
+    pub event_history: usize,
+}
+
+impl ChainConfig {
+    /// Path to the state file for this chain: either the configured
+    /// `state_file` or `<chain-id>_priv_validator_state.json`
+    pub fn state_file_path(&self) -> PathBuf {
+        match self.state_file {
+            Some(ref path) => path.to_owned(),
+            None => PathBuf::from(&format!("{}_priv_validator_state.json", self.id)),
+        }
+    }
+}
+
+/// Default value for the `ChainConfig` halt_on_state_error field
+fn halt_on_state_error_default() -> bool {
+    true
+}
+
+/// Default value for the `ChainConfig` event_history field
+fn event_history_default() -> usize {
+    chain::events::DEFAULT_HISTORY
 }
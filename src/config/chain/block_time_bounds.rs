@@ -0,0 +1,80 @@
+use serde::Deserialize;
+use std::time::{Duration, SystemTime};
+
+/// Bounds on the timestamps of votes, relative to the local clock, outside
+/// of which signing is refused.
+///
+/// Votes don't carry the time of the block they're for, but a block's time
+/// is derived from the timestamps of the votes which commit the previous
+/// one, so bounding vote timestamps keeps absurd block times from being
+/// signed for.
+#[derive(Copy, Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BlockTimeBoundsConfig {
+    /// Maximum time (in milliseconds) a timestamp may be behind the local
+    /// clock (unbounded if unset)
+    pub max_past_ms: Option<u64>,
+
+    /// Maximum time (in milliseconds) a timestamp may be ahead of the local
+    /// clock (unbounded if unset)
+    pub max_future_ms: Option<u64>,
+}
+
+impl BlockTimeBoundsConfig {
+    /// If the given timestamp is out of bounds relative to `now`, describe
+    /// why
+    pub fn check(&self, timestamp: SystemTime, now: SystemTime) -> Option<String> {
+        match timestamp.duration_since(now) {
+            Ok(ahead) => exceeds(ahead, self.max_future_ms, "ahead of"),
+            Err(e) => exceeds(e.duration(), self.max_past_ms, "behind"),
+        }
+    }
+}
+
+/// Describe the offset if it exceeds the (optional) bound
+fn exceeds(offset: Duration, max_ms: Option<u64>, relation: &str) -> Option<String> {
+    let max_ms = max_ms?;
+
+    if offset > Duration::from_millis(max_ms) {
+        Some(format!(
+            "timestamp is {} ms {} the local clock (max {} ms)",
+            offset.as_millis(),
+            relation,
+            max_ms
+        ))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timestamps_are_bounded() {
+        let bounds = BlockTimeBoundsConfig {
+            max_past_ms: Some(60_000),
+            max_future_ms: Some(5_000),
+        };
+
+        let now = SystemTime::now();
+        let secs = Duration::from_secs;
+
+        assert_eq!(bounds.check(now, now), None);
+        assert_eq!(bounds.check(now - secs(30), now), None);
+        assert_eq!(bounds.check(now + secs(1), now), None);
+
+        let behind = bounds.check(now - secs(120), now).unwrap();
+        assert!(behind.contains("120000 ms behind"), "{}", behind);
+
+        let ahead = bounds.check(now + secs(10), now).unwrap();
+        assert!(ahead.contains("10000 ms ahead of"), "{}", ahead);
+
+        let unbounded_past = BlockTimeBoundsConfig {
+            max_past_ms: None,
+            ..bounds
+        };
+        assert_eq!(unbounded_past.check(now - secs(3600), now), None);
+    }
+}
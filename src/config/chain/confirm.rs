@@ -0,0 +1,14 @@
+use serde::Deserialize;
+
+/// Configuration for interactively confirming each signature on the console
+///
+/// WARNING: every signature blocks until a human approves it. This is only
+/// intended for ceremonies and air-gapped test networks, never for
+/// production validators.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfirmConfig {
+    /// Timeout (in seconds) to wait for confirmation before refusing to
+    /// sign (default 60)
+    pub timeout_secs: Option<u64>,
+}
@@ -0,0 +1,31 @@
+use serde::Deserialize;
+
+/// Configuration for halting signing after repeated double-sign attempts.
+///
+/// A validator repeatedly requesting conflicting signatures indicates
+/// something is badly wrong (e.g. a split-brain between validator nodes, or
+/// stale state), so rather than continuing to reject requests, signing for
+/// the chain is halted until an operator resumes it via the management API.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CooldownConfig {
+    /// Number of double-sign attempts within `window_secs` which halts
+    /// signing (default 3)
+    #[serde(default = "threshold_default")]
+    pub threshold: usize,
+
+    /// Window (in seconds) in which double-sign attempts are counted
+    /// (default 300)
+    #[serde(default = "window_secs_default")]
+    pub window_secs: u64,
+}
+
+/// Default value for the `CooldownConfig` threshold field
+fn threshold_default() -> usize {
+    3
+}
+
+/// Default value for the `CooldownConfig` window_secs field
+fn window_secs_default() -> u64 {
+    300
+}
@@ -0,0 +1,21 @@
+use serde::Deserialize;
+
+/// What to do when more than one provider supplies the same public key for
+/// a chain (e.g. a key mirrored across two HSMs for redundancy)
+#[derive(Copy, Clone, Debug, Deserialize, Eq, PartialEq)]
+pub enum DuplicateKeyPolicy {
+    /// Refuse to start (default)
+    #[serde(rename = "reject")]
+    Reject,
+
+    /// Sign with the first provider configured for the key, and fall back to
+    /// the others (in order) if it returns an error
+    #[serde(rename = "failover")]
+    Failover,
+}
+
+impl Default for DuplicateKeyPolicy {
+    fn default() -> Self {
+        DuplicateKeyPolicy::Reject
+    }
+}
@@ -0,0 +1,34 @@
+use serde::Deserialize;
+
+/// Policy for when to `fsync` the state file after each signature
+///
+/// WARNING: anything other than `always` means a crash or power loss can
+/// roll back the last-signed state on disk, which can cause tmkms to
+/// double sign when it restarts. Only weaken this policy if you understand
+/// (and accept) that risk, e.g. in ephemeral test environments.
+#[derive(Copy, Clone, Debug, Deserialize, Eq, PartialEq)]
+#[serde(deny_unknown_fields, tag = "type")]
+pub enum FsyncPolicy {
+    /// `fsync` the state file after every update (default)
+    #[serde(rename = "always")]
+    Always,
+
+    /// `fsync` the state file every given interval. Updates are written to
+    /// the state file immediately, and any which haven't been `fsync`'d are
+    /// `fsync`'d by a background thread at the end of each interval.
+    #[serde(rename = "batch")]
+    Batch {
+        /// Interval between `fsync` calls in milliseconds (at least 1)
+        interval_ms: u64,
+    },
+
+    /// Never `fsync` the state file (updates are still written to it)
+    #[serde(rename = "never")]
+    Never,
+}
+
+impl Default for FsyncPolicy {
+    fn default() -> Self {
+        FsyncPolicy::Always
+    }
+}
@@ -0,0 +1,23 @@
+use serde::Deserialize;
+
+/// Labels displayed for each type of key (e.g. when keys are added to the
+/// keyring at startup), for chains where the standard "account" and
+/// "consensus" terminology would be misleading
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct KeyTypeLabels {
+    /// Label for account keys (default `account`)
+    pub account: String,
+
+    /// Label for consensus keys (default `consensus`)
+    pub consensus: String,
+}
+
+impl Default for KeyTypeLabels {
+    fn default() -> Self {
+        Self {
+            account: "account".to_owned(),
+            consensus: "consensus".to_owned(),
+        }
+    }
+}
@@ -0,0 +1,33 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Encoding of responses to the validator's public key requests
+///
+/// Validators on either side of Tendermint's amino-to-protobuf migration
+/// reject the handshake if the public key response isn't encoded the way
+/// they expect, even though signing would otherwise work.
+#[derive(Copy, Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum PubKeyEncoding {
+    /// Amino-encoded `PubKeyResponse` (default)
+    #[serde(rename = "amino")]
+    Amino,
+
+    /// Protobuf-encoded `PubKeyResponse` wrapped in a privval `Message`
+    #[serde(rename = "proto")]
+    Proto,
+}
+
+impl Default for PubKeyEncoding {
+    fn default() -> Self {
+        PubKeyEncoding::Amino
+    }
+}
+
+impl fmt::Display for PubKeyEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            PubKeyEncoding::Amino => "amino",
+            PubKeyEncoding::Proto => "proto",
+        })
+    }
+}
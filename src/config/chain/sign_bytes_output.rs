@@ -0,0 +1,63 @@
+use serde::Deserialize;
+use signatory::ed25519;
+
+/// Layout of the signatures returned for `SignBytesRequest`s
+///
+/// Consensus messages (proposals and votes) are unaffected, and are always
+/// answered with the bare signature.
+#[derive(Copy, Clone, Debug, Deserialize, Eq, PartialEq)]
+pub enum SignBytesOutput {
+    /// The 64-byte Ed25519 signature (default)
+    #[serde(rename = "signature")]
+    Signature,
+
+    /// The 32-byte Ed25519 public key of the signing key followed by the
+    /// 64-byte signature ("attached" signature)
+    #[serde(rename = "attached")]
+    Attached,
+}
+
+impl SignBytesOutput {
+    /// Encode a signature by the given public key in this layout
+    pub fn encode(
+        self,
+        public_key: &ed25519::PublicKey,
+        signature: &ed25519::Signature,
+    ) -> Vec<u8> {
+        match self {
+            SignBytesOutput::Signature => signature.as_ref().to_vec(),
+            SignBytesOutput::Attached => {
+                let mut output = public_key.as_bytes().to_vec();
+                output.extend_from_slice(signature.as_ref());
+                output
+            }
+        }
+    }
+}
+
+impl Default for SignBytesOutput {
+    fn default() -> Self {
+        SignBytesOutput::Signature
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn attached_layout() {
+        let public_key = ed25519::PublicKey::new([1; ed25519::PUBLIC_KEY_SIZE]);
+        let signature = ed25519::Signature::new([2; ed25519::SIGNATURE_SIZE]);
+
+        assert_eq!(
+            SignBytesOutput::Signature.encode(&public_key, &signature),
+            signature.as_ref()
+        );
+
+        let attached = SignBytesOutput::Attached.encode(&public_key, &signature);
+        assert_eq!(attached.len(), 96);
+        assert_eq!(&attached[..32], public_key.as_bytes());
+        assert_eq!(&attached[32..], signature.as_ref());
+    }
+}
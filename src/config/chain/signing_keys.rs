@@ -0,0 +1,18 @@
+use serde::Deserialize;
+
+/// Consensus keys used to sign each type of message, for chains with more
+/// than one consensus key (e.g. proposals signed with a hot key, and votes
+/// with a key in an HSM).
+///
+/// Keys are given in the chain's `key_format`. Both keys share the chain's
+/// double-signing state.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SigningKeysConfig {
+    /// Key used to sign proposals
+    pub proposal: Option<String>,
+
+    /// Key used to sign prevotes and precommits. This is also the key
+    /// reported to the validator in response to public key requests.
+    pub vote: Option<String>,
+}
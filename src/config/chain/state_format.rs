@@ -0,0 +1,31 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// On-disk format of the state file
+#[derive(Copy, Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum StateFormat {
+    /// Tendermint's `priv_validator_state.json` format (default). The whole
+    /// file is rewritten (and atomically renamed) after each signature.
+    #[serde(rename = "json")]
+    Json,
+
+    /// Compact fixed-size binary records, updated in place after each
+    /// signature. Existing JSON state files are migrated on startup.
+    #[serde(rename = "binary")]
+    Binary,
+}
+
+impl Default for StateFormat {
+    fn default() -> Self {
+        StateFormat::Json
+    }
+}
+
+impl fmt::Display for StateFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            StateFormat::Json => "json",
+            StateFormat::Binary => "binary",
+        })
+    }
+}
@@ -0,0 +1,25 @@
+use serde::Deserialize;
+use std::path::PathBuf;
+
+/// Configuration for mirroring state updates to a secondary log.
+///
+/// Each state update is appended (as a line of JSON) to a log on an
+/// independent store, e.g. a network filesystem, so there's an external
+/// record of the highest height signed even if the state file is lost.
+/// Mirroring is best-effort and never delays signing.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StateMirrorConfig {
+    /// Path of the log to append state updates to
+    pub path: PathBuf,
+
+    /// Number of consecutive failures to mirror state updates after which
+    /// an error is logged (default 3)
+    #[serde(default = "alert_after_default")]
+    pub alert_after: u64,
+}
+
+/// Default value for the `StateMirrorConfig` alert_after field
+fn alert_after_default() -> u64 {
+    3
+}
@@ -0,0 +1,98 @@
+use crate::{
+    error::{Error, ErrorKind::*},
+    prelude::*,
+};
+use serde::Deserialize;
+use tendermint::amino_types::SignedMsgType;
+
+/// Consensus step assigned to each type of signed message.
+///
+/// Within a given height and round, tmkms refuses to sign a message whose
+/// step is lower than that of the last message it signed. The defaults match
+/// Tendermint's consensus (proposal, then prevote, then precommit), but forks
+/// which order these steps differently can override them per chain.
+#[derive(Copy, Clone, Debug, Deserialize, Eq, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct StepConfig {
+    /// Step of proposals (default 0)
+    pub proposal: i8,
+
+    /// Step of prevotes (default 1)
+    pub prevote: i8,
+
+    /// Step of precommits (default 2)
+    pub precommit: i8,
+}
+
+impl StepConfig {
+    /// Get the step of the given message type
+    pub fn step(&self, msg_type: SignedMsgType) -> i8 {
+        match msg_type {
+            SignedMsgType::Proposal => self.proposal,
+            SignedMsgType::PreVote => self.prevote,
+            SignedMsgType::PreCommit => self.precommit,
+        }
+    }
+
+    /// Ensure every message type is assigned a distinct, non-negative step
+    pub fn validate(&self) -> Result<(), Error> {
+        let steps = [self.proposal, self.prevote, self.precommit];
+
+        if steps.iter().any(|step| *step < 0) {
+            fail!(
+                ConfigError,
+                "consensus steps must be non-negative: {:?}",
+                self
+            );
+        }
+
+        if steps[0] == steps[1] || steps[0] == steps[2] || steps[1] == steps[2] {
+            fail!(ConfigError, "consensus steps must be distinct: {:?}", self);
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for StepConfig {
+    fn default() -> Self {
+        Self {
+            proposal: 0,
+            prevote: 1,
+            precommit: 2,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_steps_are_valid() {
+        assert!(StepConfig::default().validate().is_ok());
+    }
+
+    #[test]
+    fn duplicate_steps_are_rejected() {
+        let steps = StepConfig {
+            precommit: 1,
+            ..StepConfig::default()
+        };
+
+        assert!(steps.validate().is_err());
+    }
+
+    #[test]
+    fn reordered_steps() {
+        let steps = StepConfig {
+            proposal: 2,
+            prevote: 0,
+            precommit: 1,
+        };
+
+        assert!(steps.validate().is_ok());
+        assert_eq!(steps.step(SignedMsgType::Proposal), 2);
+        assert_eq!(steps.step(SignedMsgType::PreVote), 0);
+    }
+}
@@ -0,0 +1,22 @@
+use serde::Deserialize;
+
+/// What to do when a validator sends a message of a type the KMS doesn't
+/// recognize (e.g. a node running a newer version of the privval protocol)
+#[derive(Copy, Clone, Debug, Deserialize, Eq, PartialEq)]
+pub enum UnknownMessagePolicy {
+    /// Close the connection (default)
+    #[serde(rename = "close")]
+    Close,
+
+    /// Log the message and keep handling requests on the connection. The
+    /// privval protocol has no generic error response, so nothing is sent
+    /// in reply.
+    #[serde(rename = "ignore")]
+    Ignore,
+}
+
+impl Default for UnknownMessagePolicy {
+    fn default() -> Self {
+        UnknownMessagePolicy::Close
+    }
+}
@@ -0,0 +1,216 @@
+//! Additional configuration files (e.g. one per chain) read from the
+//! directory named by the `config_dir` setting.
+//!
+//! Each `*.toml` file in the directory may contain `[[chain]]`,
+//! `[[validator]]` and `[providers]` entries, which are appended to those of
+//! the main configuration file. Files are read in lexical order of their
+//! names.
+
+use super::{chain::ChainConfig, provider::ProviderConfig, KmsConfig, ValidatorConfig};
+use crate::{
+    chain,
+    error::{Error, ErrorKind::*},
+    prelude::*,
+};
+use serde::Deserialize;
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Configuration file in the `config_dir` directory
+#[derive(Default, Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct FragmentConfig {
+    /// Chains the KMS is providing key management service for
+    #[serde(default)]
+    pub chain: Vec<ChainConfig>,
+
+    /// Addresses of validator nodes
+    #[serde(default)]
+    pub validator: Vec<ValidatorConfig>,
+
+    /// Cryptographic signature provider configuration
+    #[serde(default)]
+    pub providers: ProviderConfig,
+}
+
+/// Merge every `*.toml` file in the given directory into the configuration.
+///
+/// `main_path` is the path of the main configuration file, used in errors
+/// about entries which conflict with it.
+pub fn merge(config: &mut KmsConfig, dir: &Path, main_path: &Path) -> Result<(), Error> {
+    let mut paths = fs::read_dir(dir)
+        .map_err(|e| format_err!(ConfigError, "couldn't read {}: {}", dir.display(), e))?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format_err!(ConfigError, "couldn't read {}: {}", dir.display(), e))?;
+
+    paths.retain(|path| path.is_file() && path.extension() == Some("toml".as_ref()));
+    paths.sort();
+
+    let mut origins = Origins::default();
+    origins.add(config.chain.iter(), &config.validator, main_path)?;
+
+    for path in paths {
+        let fragment = load(&path)?;
+        origins.add(fragment.chain.iter(), &fragment.validator, &path)?;
+
+        config.chain.extend(fragment.chain);
+        config.validator.extend(fragment.validator);
+        config.providers.merge(fragment.providers);
+    }
+
+    Ok(())
+}
+
+/// Parse a configuration file in the `config_dir` directory
+fn load(path: &Path) -> Result<FragmentConfig, Error> {
+    let toml_string = fs::read_to_string(path)
+        .map_err(|e| format_err!(ConfigError, "couldn't read {}: {}", path.display(), e))?;
+
+    toml::from_str(&toml_string)
+        .map_err(|e| format_err!(ConfigError, "{}: {}", path.display(), e).into())
+}
+
+/// Files which defined each chain and validator seen so far, for reporting
+/// conflicts between files
+#[derive(Default)]
+struct Origins {
+    /// Files which defined each chain
+    chains: BTreeMap<chain::Id, PathBuf>,
+
+    /// Files which defined each validator (by chain and address)
+    validators: BTreeMap<(chain::Id, String), PathBuf>,
+}
+
+impl Origins {
+    /// Record the chains and validators defined in a file, failing if any
+    /// were already defined by another one
+    fn add<'a>(
+        &mut self,
+        chains: impl Iterator<Item = &'a ChainConfig>,
+        validators: &[ValidatorConfig],
+        path: &Path,
+    ) -> Result<(), Error> {
+        for chain_config in chains {
+            if let Some(origin) = self.chains.insert(chain_config.id, path.to_owned()) {
+                fail!(
+                    ConfigError,
+                    "{}: chain `{}` is already defined in {}",
+                    path.display(),
+                    chain_config.id,
+                    origin.display()
+                );
+            }
+        }
+
+        for validator in validators {
+            let key = (validator.chain_id, validator.addr.to_string());
+
+            if let Some(origin) = self.validators.insert(key, path.to_owned()) {
+                fail!(
+                    ConfigError,
+                    "{}: validator {} for chain `{}` is already defined in {}",
+                    path.display(),
+                    validator.addr,
+                    validator.chain_id,
+                    origin.display()
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAIN_CONFIG: &str = r#"
+        [[chain]]
+        id = "mainnet"
+        key_format = { type = "hex" }
+
+        [[validator]]
+        addr = "unix:///tmp/mainnet.sock"
+        chain_id = "mainnet"
+
+        [providers]
+    "#;
+
+    fn write_file(dir: &Path, name: &str, contents: &str) {
+        fs::write(dir.join(name), contents).unwrap();
+    }
+
+    #[test]
+    fn merges_chains_and_validators() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config: KmsConfig = toml::from_str(MAIN_CONFIG).unwrap();
+
+        write_file(
+            dir.path(),
+            "20-testnet.toml",
+            r#"
+                [[chain]]
+                id = "testnet"
+                key_format = { type = "hex" }
+
+                [[validator]]
+                addr = "unix:///tmp/testnet.sock"
+                chain_id = "testnet"
+            "#,
+        );
+        write_file(
+            dir.path(),
+            "10-devnet.toml",
+            "[[chain]]\nid = \"devnet\"\nkey_format = { type = \"hex\" }\n",
+        );
+        write_file(dir.path(), "README", "not a configuration file");
+
+        merge(&mut config, dir.path(), Path::new("tmkms.toml")).unwrap();
+
+        let chain_ids = config
+            .chain
+            .iter()
+            .map(|chain| chain.id.as_str())
+            .collect::<Vec<_>>();
+
+        assert_eq!(chain_ids, &["mainnet", "devnet", "testnet"]);
+        assert_eq!(config.validator.len(), 2);
+        assert_eq!(config.validator[1].chain_id.as_str(), "testnet");
+    }
+
+    #[test]
+    fn rejects_conflicting_chains() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config: KmsConfig = toml::from_str(MAIN_CONFIG).unwrap();
+
+        write_file(
+            dir.path(),
+            "mainnet.toml",
+            "[[chain]]\nid = \"mainnet\"\nkey_format = { type = \"hex\" }\n",
+        );
+
+        let err = merge(&mut config, dir.path(), Path::new("tmkms.toml")).unwrap_err();
+        assert_eq!(*err.kind(), ConfigError);
+        assert!(
+            err.to_string().contains("already defined in tmkms.toml"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_fields() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config: KmsConfig = toml::from_str(MAIN_CONFIG).unwrap();
+
+        write_file(dir.path(), "management.toml", "[management]\n");
+
+        let err = merge(&mut config, dir.path(), Path::new("tmkms.toml")).unwrap_err();
+        assert!(err.to_string().contains("management.toml"), "{}", err);
+    }
+}
@@ -0,0 +1,234 @@
+//! Differences between the effective (i.e. defaults-applied) settings of
+//! two configuration files.
+//!
+//! Both files are parsed into [`KmsConfig`], then flattened into a list of
+//! `path = value` settings using the structures' `Debug` representations,
+//! so defaults the files don't spell out (e.g. `state_fsync`) are compared
+//! too. Entries of arrays like `[[chain]]` are compared by position.
+
+use super::KmsConfig;
+use crate::{
+    error::{Error, ErrorKind::*},
+    prelude::*,
+};
+use std::fmt::{self, Display};
+
+/// A setting which differs between two configurations
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Difference {
+    /// Setting only present in the second configuration
+    Added {
+        /// Path of the setting (e.g. `chain[0].key_format`)
+        path: String,
+
+        /// Value in the second configuration
+        value: String,
+    },
+
+    /// Setting only present in the first configuration
+    Removed {
+        /// Path of the setting
+        path: String,
+
+        /// Value in the first configuration
+        value: String,
+    },
+
+    /// Setting present in both configurations with different values
+    Changed {
+        /// Path of the setting
+        path: String,
+
+        /// Value in the first configuration
+        old: String,
+
+        /// Value in the second configuration
+        new: String,
+    },
+}
+
+impl Display for Difference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Difference::Added { path, value } => write!(f, "+ {} = {}", path, value),
+            Difference::Removed { path, value } => write!(f, "- {} = {}", path, value),
+            Difference::Changed { path, old, new } => {
+                write!(f, "~ {} = {} -> {}", path, old, new)
+            }
+        }
+    }
+}
+
+/// Parse the given configuration file contents
+pub fn parse(toml_string: &str) -> Result<KmsConfig, Error> {
+    toml::from_str(toml_string).map_err(|e| format_err!(ConfigError, "{}", e).into())
+}
+
+/// Compute the effective differences between two configurations
+pub fn diff(a: &KmsConfig, b: &KmsConfig) -> Vec<Difference> {
+    let a = flatten(a);
+    let b = flatten(b);
+    let mut differences = vec![];
+
+    for (path, old) in &a {
+        match b.iter().find(|(p, _)| p == path) {
+            Some((_, new)) if new != old => differences.push(Difference::Changed {
+                path: path.clone(),
+                old: old.clone(),
+                new: new.clone(),
+            }),
+            Some(_) => (),
+            None => differences.push(Difference::Removed {
+                path: path.clone(),
+                value: old.clone(),
+            }),
+        }
+    }
+
+    for (path, value) in &b {
+        if !a.iter().any(|(p, _)| p == path) {
+            differences.push(Difference::Added {
+                path: path.clone(),
+                value: value.clone(),
+            });
+        }
+    }
+
+    differences
+}
+
+/// Nesting level of a value in the `Debug` representation
+struct Frame {
+    /// Path of the value
+    path: String,
+
+    /// Is the value a sequence (whose elements are identified by index)?
+    seq: bool,
+
+    /// Index of the next element of a sequence
+    next_index: usize,
+}
+
+/// Flatten the pretty-printed `Debug` representation of a value into a list
+/// of `(path, value)` settings, in the order they appear
+fn flatten(value: &impl fmt::Debug) -> Vec<(String, String)> {
+    let debug = format!("{:#?}", value);
+    let mut settings = vec![];
+    let mut stack: Vec<Frame> = vec![];
+
+    for line in debug.lines().map(str::trim) {
+        if line.starts_with('}') || line.starts_with(']') || line.starts_with(')') {
+            stack.pop();
+            continue;
+        }
+
+        let line = line.trim_end_matches(',');
+
+        let (path, rest) = match stack.last_mut() {
+            None => (String::new(), line),
+            Some(frame) if frame.seq => {
+                frame.next_index += 1;
+                (format!("{}[{}]", frame.path, frame.next_index - 1), line)
+            }
+            Some(frame) => match line.find(": ") {
+                Some(pos) => (join(&frame.path, &line[..pos]), &line[pos + 2..]),
+                None => (frame.path.clone(), line),
+            },
+        };
+
+        let opener = rest.chars().last();
+        let name = rest.trim_end_matches(&['{', '[', '('][..]).trim();
+
+        match opener {
+            Some('{') | Some('[') | Some('(') => {
+                // Record the name of structs and struct-like enum variants
+                // (e.g. `Bech32`), except the top-level structure. Newtypes
+                // like `Some(...)` are transparent.
+                if opener == Some('{') && !name.is_empty() && !stack.is_empty() {
+                    settings.push((path.clone(), name.to_owned()));
+                }
+
+                stack.push(Frame {
+                    path,
+                    seq: opener == Some('['),
+                    next_index: 0,
+                });
+            }
+            _ => settings.push((path, rest.to_owned())),
+        }
+    }
+
+    settings
+}
+
+/// Join a field name onto a path
+fn join(path: &str, field: &str) -> String {
+    if path.is_empty() {
+        field.to_owned()
+    } else {
+        format!("{}.{}", path, field)
+    }
+}
+
+#[cfg(all(test, feature = "softsign"))]
+mod tests {
+    use super::*;
+
+    const BASE_CONFIG: &str = r#"
+        [[chain]]
+        id = "test_chain_id"
+        key_format = { type = "hex" }
+
+        [[providers.softsign]]
+        chain_ids = ["test_chain_id"]
+        key_format = "base64"
+        path = "tests/support/signing.key"
+    "#;
+
+    #[test]
+    fn identical_configs() {
+        let config = parse(BASE_CONFIG).unwrap();
+        assert_eq!(diff(&config, &parse(BASE_CONFIG).unwrap()), vec![]);
+    }
+
+    #[test]
+    fn explicit_defaults_are_not_differences() {
+        let explicit = BASE_CONFIG.replace(
+            "key_format = { type = \"hex\" }",
+            "key_format = { type = \"hex\" }\nstate_fsync = { type = \"always\" }",
+        );
+
+        assert_eq!(
+            diff(&parse(BASE_CONFIG).unwrap(), &parse(&explicit).unwrap()),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn effective_differences() {
+        let changed = BASE_CONFIG
+            .replace("key_format = \"base64\"", "key_format = \"json\"")
+            .replace(
+                "key_format = { type = \"hex\" }",
+                "key_format = { type = \"hex\" }\nevent_history = 10",
+            );
+
+        let differences = diff(&parse(BASE_CONFIG).unwrap(), &parse(&changed).unwrap());
+
+        assert_eq!(
+            differences,
+            vec![
+                Difference::Changed {
+                    path: "chain[0].event_history".to_owned(),
+                    old: "100".to_owned(),
+                    new: "10".to_owned(),
+                },
+                Difference::Changed {
+                    path: "providers.softsign[0].key_format".to_owned(),
+                    old: "Base64".to_owned(),
+                    new: "Json".to_owned(),
+                },
+            ]
+        );
+    }
+}
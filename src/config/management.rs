@@ -0,0 +1,54 @@
+//! Management API configuration
+
+use crate::{
+    error::{Error, ErrorKind::*},
+    prelude::*,
+};
+use serde::Deserialize;
+use std::{fs, path::PathBuf};
+use tendermint::net;
+use zeroize::Zeroizing;
+
+/// Management API configuration
+#[derive(Clone, Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct ManagementConfig {
+    /// Address to listen for management API requests on (`tcp://` only)
+    pub laddr: net::Address,
+
+    /// Path to a file containing the bearer token clients must present
+    pub token_file: Option<PathBuf>,
+}
+
+impl ManagementConfig {
+    /// Load the configured bearer token from disk
+    pub fn load_token(&self) -> Result<Zeroizing<String>, Error> {
+        let token_file = self.token_file.as_ref().ok_or_else(|| {
+            format_err!(
+                ConfigError,
+                "management API requires a `token_file` (refusing to start without one)"
+            )
+        })?;
+
+        let token = Zeroizing::new(fs::read_to_string(token_file).map_err(|e| {
+            format_err!(
+                ConfigError,
+                "couldn't read management API token from {}: {}",
+                token_file.display(),
+                e
+            )
+        })?);
+
+        let token = Zeroizing::new(token.trim_end().to_owned());
+
+        if token.is_empty() {
+            fail!(
+                ConfigError,
+                "management API token file is empty: {}",
+                token_file.display()
+            );
+        }
+
+        Ok(token)
+    }
+}
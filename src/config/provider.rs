@@ -1,18 +1,27 @@
 //! Cryptographic service providers: signing backends
 
+#[cfg(feature = "exec")]
+pub mod exec;
 #[cfg(feature = "ledgertm")]
 pub mod ledgertm;
+#[cfg(feature = "remote")]
+pub mod remote;
 #[cfg(feature = "softsign")]
 pub mod softsign;
 #[cfg(feature = "yubihsm")]
 pub mod yubihsm;
 
+#[cfg(feature = "exec")]
+use self::exec::ExecConfig;
 #[cfg(feature = "ledgertm")]
 use self::ledgertm::LedgerTendermintConfig;
+#[cfg(feature = "remote")]
+use self::remote::RemoteConfig;
 #[cfg(feature = "softsign")]
 use self::softsign::SoftsignConfig;
 #[cfg(feature = "yubihsm")]
 use self::yubihsm::YubihsmConfig;
+use crate::{chain, keyring::SigningProvider};
 use serde::Deserialize;
 
 /// Provider configuration
@@ -33,4 +42,80 @@ pub struct ProviderConfig {
     #[cfg(feature = "ledgertm")]
     #[serde(default)]
     pub ledgertm: Vec<LedgerTendermintConfig>,
+
+    /// Signers which invoke an external command
+    #[cfg(feature = "exec")]
+    #[serde(default)]
+    pub exec: Vec<ExecConfig>,
+
+    /// Signers which forward requests to a remote KMS's sign server
+    #[cfg(feature = "remote")]
+    #[serde(default)]
+    pub remote: Vec<RemoteConfig>,
+}
+
+impl ProviderConfig {
+    /// Append the providers of another configuration to this one
+    #[allow(unused_variables)] // when no providers are enabled
+    pub fn merge(&mut self, other: ProviderConfig) {
+        #[cfg(feature = "softsign")]
+        self.softsign.extend(other.softsign);
+
+        #[cfg(feature = "yubihsm")]
+        self.yubihsm.extend(other.yubihsm);
+
+        #[cfg(feature = "ledgertm")]
+        self.ledgertm.extend(other.ledgertm);
+
+        #[cfg(feature = "exec")]
+        self.exec.extend(other.exec);
+
+        #[cfg(feature = "remote")]
+        self.remote.extend(other.remote);
+    }
+
+    /// Chain IDs referenced by each provider's keys
+    pub fn chain_ids(&self) -> Vec<(SigningProvider, chain::Id)> {
+        #[allow(unused_mut)] // when no providers are enabled
+        let mut chain_ids = vec![];
+
+        #[cfg(feature = "softsign")]
+        for config in &self.softsign {
+            for chain_id in &config.chain_ids {
+                chain_ids.push((SigningProvider::SoftSign, *chain_id));
+            }
+        }
+
+        #[cfg(feature = "yubihsm")]
+        for config in &self.yubihsm {
+            for key in &config.keys {
+                for chain_id in &key.chain_ids {
+                    chain_ids.push((SigningProvider::Yubihsm, *chain_id));
+                }
+            }
+        }
+
+        #[cfg(feature = "ledgertm")]
+        for config in &self.ledgertm {
+            for chain_id in &config.chain_ids {
+                chain_ids.push((SigningProvider::LedgerTm, *chain_id));
+            }
+        }
+
+        #[cfg(feature = "exec")]
+        for config in &self.exec {
+            for chain_id in &config.chain_ids {
+                chain_ids.push((SigningProvider::Exec, *chain_id));
+            }
+        }
+
+        #[cfg(feature = "remote")]
+        for config in &self.remote {
+            for chain_id in &config.chain_ids {
+                chain_ids.push((SigningProvider::Remote, *chain_id));
+            }
+        }
+
+        chain_ids
+    }
 }
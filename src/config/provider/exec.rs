@@ -0,0 +1,68 @@
+//! Configuration for signing via an external command
+
+use crate::{chain, keyring::Metadata};
+use serde::Deserialize;
+use std::ffi::OsString;
+
+/// External command signer configuration
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct ExecConfig {
+    /// Chains this signing key is authorized to be used from
+    pub chain_ids: Vec<chain::Id>,
+
+    /// Command (with arguments) to invoke for each signature. The message to
+    /// sign is written to its stdin, and it must write the signature to its
+    /// stdout and exit successfully.
+    pub cmd: Vec<OsString>,
+
+    /// Ed25519 public key of the command's signing key (hex). Every
+    /// signature the command returns is verified against it.
+    pub public_key: String,
+
+    /// Encoding of the signature written to stdout (default `raw`)
+    #[serde(default)]
+    pub signature_encoding: SignatureEncoding,
+
+    /// Time (in milliseconds) the command has to produce a signature before
+    /// it's killed (default 1000)
+    #[serde(default = "timeout_ms_default")]
+    pub timeout_ms: u64,
+
+    /// Maximum number of signing operations to run with this command at once,
+    /// shared by all chains using it. Further operations wait for earlier
+    /// ones to complete (unlimited by default)
+    pub max_concurrent_signs: Option<usize>,
+
+    /// Operator-defined metadata for this key (e.g. `{ team = "infra" }`),
+    /// displayed alongside it. Never affects signing.
+    #[serde(default)]
+    pub metadata: Metadata,
+}
+
+/// Encodings of the signature written to stdout
+#[derive(Copy, Clone, Debug, Deserialize, Eq, PartialEq)]
+pub enum SignatureEncoding {
+    /// 64 raw bytes (default)
+    #[serde(rename = "raw")]
+    Raw,
+
+    /// Hexadecimal (either case, surrounding whitespace ignored)
+    #[serde(rename = "hex")]
+    Hex,
+
+    /// Base64 (surrounding whitespace ignored)
+    #[serde(rename = "base64")]
+    Base64,
+}
+
+impl Default for SignatureEncoding {
+    fn default() -> Self {
+        SignatureEncoding::Raw
+    }
+}
+
+/// Default value for the `ExecConfig` timeout_ms field
+fn timeout_ms_default() -> u64 {
+    1000
+}
@@ -1,6 +1,6 @@
 //! Configuration for Ledger Tendermint signer
 
-use crate::chain;
+use crate::{chain, keyring::Metadata};
 use serde::Deserialize;
 
 /// Ledger Tendermint signer configuration
@@ -9,4 +9,9 @@ use serde::Deserialize;
 pub struct LedgerTendermintConfig {
     /// Chains this signing key is authorized to be used from
     pub chain_ids: Vec<chain::Id>,
+
+    /// Operator-defined metadata for this key (e.g. `{ team = "infra" }`),
+    /// displayed alongside it. Never affects signing.
+    #[serde(default)]
+    pub metadata: Metadata,
 }
@@ -0,0 +1,39 @@
+//! Configuration for signing via a remote KMS's sign server
+
+use crate::{chain, keyring::Metadata};
+use serde::Deserialize;
+use std::path::PathBuf;
+use tendermint::net;
+
+/// Remote (sign server) signer configuration
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct RemoteConfig {
+    /// Chains this signing key is authorized to be used from
+    pub chain_ids: Vec<chain::Id>,
+
+    /// Address of the sign server, including its node ID
+    /// (e.g. `tcp://<node_id>@backend.example.com:26670`)
+    pub addr: net::Address,
+
+    /// Path to our Secret Connection identity key for connecting to the sign
+    /// server (generated if it doesn't exist)
+    pub secret_key: PathBuf,
+
+    /// Ed25519 public key of the signing key held by the sign server (hex).
+    /// Every signature the server returns is verified against it.
+    pub public_key: String,
+
+    /// Read/write timeout of the connection, in seconds (default 10)
+    pub timeout: Option<u16>,
+
+    /// Maximum number of signing operations to run on this sign server at once,
+    /// shared by all chains using it. Further operations wait for earlier
+    /// ones to complete (unlimited by default)
+    pub max_concurrent_signs: Option<usize>,
+
+    /// Operator-defined metadata for this key (e.g. `{ team = "infra" }`),
+    /// displayed alongside it. Never affects signing.
+    #[serde(default)]
+    pub metadata: Metadata,
+}
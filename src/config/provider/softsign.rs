@@ -3,6 +3,7 @@
 use crate::{
     chain,
     error::{Error, ErrorKind::ConfigError},
+    keyring::Metadata,
     prelude::*,
 };
 use serde::Deserialize;
@@ -15,7 +16,9 @@ use std::{
 #[derive(Deserialize, Debug)]
 #[serde(deny_unknown_fields)]
 pub struct SoftsignConfig {
-    /// Chains this signing key is authorized to be used from
+    /// Chains this signing key is authorized to be used from (only
+    /// applicable to keys loaded from `path`)
+    #[serde(default)]
     pub chain_ids: Vec<chain::Id>,
 
     /// Private key file format
@@ -23,9 +26,36 @@ pub struct SoftsignConfig {
 
     /// Path to a file containing a cryptographic key
     // TODO: use `abscissa_core::Secret` to wrap this `PathBuf`
-    pub path: SoftPrivateKey,
+    pub path: Option<SoftPrivateKey>,
+
+    /// Directory to load keys from instead of a single `path`. Each file in
+    /// it matching `key_pattern` is loaded as the key for a single chain.
+    pub key_dir: Option<PathBuf>,
+
+    /// Pattern of key filenames in `key_dir` (default `*.key`). This must
+    /// contain a single `*`, which matches the chain ID of each key, e.g.
+    /// `cosmoshub-3.key` is the key for the `cosmoshub-3` chain.
+    pub key_pattern: Option<String>,
+
+    /// Type of key this is (`consensus` or `account`, default `consensus`)
+    #[serde(default)]
+    pub key_type: KeyType,
+
+    /// Sign with Ed25519ph (SHA-512 prehashed) instead of pure Ed25519.
+    /// Prehash keys must be account keys, and are never used to sign
+    /// consensus messages.
+    #[serde(default)]
+    pub prehash: bool,
+
+    /// Operator-defined metadata for this key (e.g. `{ team = "infra" }`),
+    /// displayed alongside it. Never affects signing.
+    #[serde(default)]
+    pub metadata: Metadata,
 }
 
+/// Default pattern of key filenames in a softsign `key_dir`
+pub const DEFAULT_KEY_PATTERN: &str = "*.key";
+
 /// Software-backed private key (stored in a file)
 #[derive(Deserialize, Debug)]
 #[serde(deny_unknown_fields)]
@@ -38,6 +68,24 @@ impl AsRef<Path> for SoftPrivateKey {
     }
 }
 
+/// Role a softsign key is registered as in the keyring
+#[derive(Copy, Clone, Debug, Deserialize, Eq, Hash, PartialEq)]
+pub enum KeyType {
+    /// Account keys
+    #[serde(rename = "account")]
+    Account,
+
+    /// Consensus keys
+    #[serde(rename = "consensus")]
+    Consensus,
+}
+
+impl Default for KeyType {
+    fn default() -> Self {
+        KeyType::Consensus
+    }
+}
+
 /// Private key format
 #[derive(Copy, Clone, Debug, Deserialize, Eq, Hash, PartialEq)]
 pub enum KeyFormat {
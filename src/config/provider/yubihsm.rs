@@ -1,9 +1,9 @@
 //! Configuration for the `YubiHSM` backend
 
-use crate::{chain, prelude::*};
+use crate::{chain, keyring::Metadata, prelude::*};
 use abscissa_core::secret::{CloneableSecret, DebugSecret, ExposeSecret, Secret};
 use serde::Deserialize;
-use std::{fs, path::PathBuf, process};
+use std::{env, fs, path::PathBuf, process};
 use tendermint::net;
 use yubihsm::Credentials;
 use zeroize::{Zeroize, Zeroizing};
@@ -25,6 +25,11 @@ pub struct YubihsmConfig {
     /// Serial number of the YubiHSM to connect to
     pub serial_number: Option<String>,
 
+    /// Maximum number of signing operations to run on this YubiHSM at once,
+    /// shared by all chains using it. Further operations wait for earlier
+    /// ones to complete (unlimited by default)
+    pub max_concurrent_signs: Option<usize>,
+
     /// Configuration for `yubihsm-connector` compatible HTTP server.
     #[cfg(feature = "yubihsm-server")]
     pub connector_server: Option<ConnectorServerConfig>,
@@ -62,7 +67,15 @@ pub enum AuthConfig {
         /// Password file path
         password_file: PathBuf,
     },
-    /// Read password directly from the config file
+    /// Read password from an environment variable
+    Env {
+        /// Authentication key ID to use to authenticate to the YubiHSM
+        key: u16,
+
+        /// Name of the environment variable containing the password
+        password_env: String,
+    },
+    /// Read password directly from the config file (deprecated)
     String {
         /// Authentication key ID to use to authenticate to the YubiHSM
         key: u16,
@@ -87,11 +100,31 @@ impl AuthConfig {
                 let password_trimmed = password.trim_end();
                 Credentials::from_password(*key, password_trimmed.as_bytes())
             }
+            AuthConfig::Env { key, password_env } => {
+                let password = Zeroizing::new(env::var(password_env).unwrap_or_else(|e| {
+                    status_err!("couldn't read password from ${}: {}", password_env, e);
+                    process::exit(1);
+                }));
+
+                Credentials::from_password(*key, password.trim_end().as_bytes())
+            }
             AuthConfig::String { key, password } => {
                 Credentials::from_password(*key, password.expose_secret().0.as_bytes())
             }
         }
     }
+
+    /// Get a warning to show when the configuration is loaded, if it uses a
+    /// deprecated way of providing the password
+    pub fn deprecation_warning(&self) -> Option<&'static str> {
+        match self {
+            AuthConfig::String { .. } => Some(
+                "inline YubiHSM `password` in config is deprecated; \
+                 use `password_file` or `password_env` instead",
+            ),
+            AuthConfig::Path { .. } | AuthConfig::Env { .. } => None,
+        }
+    }
 }
 
 /// Password to the YubiHSM
@@ -117,6 +150,11 @@ pub struct SigningKeyConfig {
 
     /// Signing key ID
     pub key: u16,
+
+    /// Operator-defined metadata for this key (e.g. `{ team = "infra" }`),
+    /// displayed alongside it. Never affects signing.
+    #[serde(default)]
+    pub metadata: Metadata,
 }
 
 /// Default value for `AdapterConfig::Usb { timeout_ms }`
@@ -0,0 +1,24 @@
+//! Signing server configuration
+
+use serde::Deserialize;
+use std::path::PathBuf;
+use tendermint::{net, node};
+
+/// Signing server configuration: serves this KMS's keys to front-end KMS
+/// instances using the `remote` provider
+#[derive(Clone, Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct SignServerConfig {
+    /// Address to listen for front-end KMS connections on (`tcp://` only)
+    pub laddr: net::Address,
+
+    /// Path to the signing server's Secret Connection identity key
+    /// (generated if it doesn't exist)
+    pub secret_key: PathBuf,
+
+    /// Node IDs of the front-end KMS instances allowed to connect
+    pub allowed_peer_ids: Vec<node::Id>,
+
+    /// Read/write timeout of connections, in seconds (default 10)
+    pub timeout: Option<u16>,
+}
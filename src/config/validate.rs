@@ -0,0 +1,297 @@
+//! Validation of configuration files which reports every problem found,
+//! rather than stopping at the first one like loading the configuration does
+
+use super::{
+    chain::ChainConfig, management::ManagementConfig, sign_server::SignServerConfig, KmsConfig,
+    ValidatorConfig,
+};
+use crate::chain::{self, state::State, Chain, Registry};
+use serde::de::DeserializeOwned;
+use std::collections::BTreeSet;
+
+/// Top-level fields of the configuration file
+const TOP_LEVEL_FIELDS: &[&str] = &[
+    "chain",
+    "validator",
+    "providers",
+    "management",
+    "sign_server",
+    "config_dir",
+];
+
+/// Check the given configuration file contents, returning a description of
+/// each problem found (or an empty list if it's valid)
+pub fn validate(toml_string: &str) -> Vec<String> {
+    let mut problems = vec![];
+
+    let config = match toml_string.parse::<toml::Value>() {
+        Ok(toml::Value::Table(config)) => config,
+        Ok(_) => return vec!["configuration must be a table".to_owned()],
+        Err(e) => return vec![format!("invalid TOML: {}", e)],
+    };
+
+    for field in config.keys() {
+        if !TOP_LEVEL_FIELDS.contains(&field.as_str()) {
+            problems.push(format!(
+                "unknown field `{}`, expected one of {}",
+                field,
+                TOP_LEVEL_FIELDS.join(", ")
+            ));
+        }
+    }
+
+    let chains = parse_entries::<ChainConfig>(&config, "chain", &mut problems);
+    let validators = parse_entries::<ValidatorConfig>(&config, "validator", &mut problems);
+
+    let mut registry = Registry::default();
+    let mut chain_ids = BTreeSet::new();
+
+    if let Err(e) = chain::ensure_distinct_state_files(chains.iter().map(|(_, c)| c)) {
+        problems.push(e.to_string());
+    }
+
+    for (index, chain_config) in chains {
+        if !chain_ids.insert(chain_config.id) {
+            problems.push(format!(
+                "chain[{}]: duplicate chain ID `{}`",
+                index, chain_config.id
+            ));
+            continue;
+        }
+
+        match Chain::with_state(&chain_config, State::unpersisted())
+            .and_then(|chain| registry.register_chain(chain))
+        {
+            Ok(()) => (),
+            Err(e) => problems.push(format!("chain[{}] ({}): {}", index, chain_config.id, e)),
+        }
+    }
+
+    for (index, validator_config) in validators {
+        if !chain_ids.contains(&validator_config.chain_id) {
+            problems.push(format!(
+                "validator[{}] ({}): unregistered chain `{}` (add it to a [[chain]] section)",
+                index, validator_config.addr, validator_config.chain_id
+            ));
+        }
+
+        let secret_key_exists = validator_config
+            .secret_key
+            .as_ref()
+            .map(|path| path.exists())
+            .unwrap_or(false);
+
+        // Missing secret keys are generated on startup, so only check existing ones
+        if secret_key_exists {
+            if let Err(e) = validator_config.load_secret_key() {
+                problems.push(format!(
+                    "validator[{}] ({}): {}",
+                    index, validator_config.addr, e
+                ));
+            }
+        }
+    }
+
+    match config.get("providers") {
+        Some(toml::Value::Table(providers)) => {
+            validate_providers(providers, &mut registry, &mut problems)
+        }
+        Some(_) => problems.push("`providers` must be a table".to_owned()),
+        None => problems.push("missing field `providers`".to_owned()),
+    }
+
+    if let Some(management) = config.get("management") {
+        match management.clone().try_into::<ManagementConfig>() {
+            Ok(management_config) => {
+                if let Err(e) = management_config.load_token() {
+                    problems.push(format!("management: {}", e));
+                }
+            }
+            Err(e) => problems.push(format!("management: {}", e)),
+        }
+    }
+
+    if let Some(sign_server) = config.get("sign_server") {
+        match sign_server.clone().try_into::<SignServerConfig>() {
+            Ok(sign_server_config) => {
+                if sign_server_config.allowed_peer_ids.is_empty() {
+                    problems.push("sign_server: `allowed_peer_ids` must not be empty".to_owned());
+                }
+            }
+            Err(e) => problems.push(format!("sign_server: {}", e)),
+        }
+    }
+
+    if problems.is_empty() {
+        for chain in registry.chains() {
+            if chain.keyring.signers().next().is_none() {
+                problems.push(format!("chain `{}`: no keys configured", chain.id));
+            } else if let Err(e) = chain.check_signing_keys() {
+                problems.push(e.to_string());
+            }
+        }
+    }
+
+    // Catch anything the checks above missed
+    if problems.is_empty() {
+        if let Err(e) = toml::Value::Table(config).try_into::<KmsConfig>() {
+            problems.push(e.to_string());
+        }
+    }
+
+    problems
+}
+
+/// Parse each entry of an array of tables (e.g. `[[chain]]`), recording a
+/// problem for each entry which is invalid
+fn parse_entries<T: DeserializeOwned>(
+    config: &toml::value::Table,
+    field: &str,
+    problems: &mut Vec<String>,
+) -> Vec<(usize, T)> {
+    let entries = match config.get(field) {
+        Some(toml::Value::Array(entries)) => entries,
+        Some(_) => {
+            problems.push(format!("`{}` must be an array of tables", field));
+            return vec![];
+        }
+        None => return vec![],
+    };
+
+    let mut parsed = vec![];
+
+    for (index, entry) in entries.iter().enumerate() {
+        match entry.clone().try_into::<T>() {
+            Ok(value) => parsed.push((index, value)),
+            Err(e) => problems.push(format!("{}[{}]: {}", field, index, e)),
+        }
+    }
+
+    parsed
+}
+
+/// Check the `[providers]` section, loading software keys into the keyrings
+/// of the registered chains. Hardware providers are parsed, but their devices
+/// aren't accessed.
+fn validate_providers(
+    providers: &toml::value::Table,
+    registry: &mut Registry,
+    problems: &mut Vec<String>,
+) {
+    for provider in providers.keys() {
+        match provider.as_str() {
+            #[cfg(feature = "softsign")]
+            "softsign" => {
+                let configs = parse_entries::<super::provider::softsign::SoftsignConfig>(
+                    providers, "softsign", problems,
+                );
+
+                for (index, config) in configs {
+                    if let Err(e) = crate::keyring::ed25519::softsign::init(
+                        registry,
+                        std::slice::from_ref(&config),
+                    ) {
+                        problems.push(format!("providers.softsign[{}]: {}", index, e));
+                    }
+                }
+            }
+            #[cfg(feature = "yubihsm")]
+            "yubihsm" => {
+                let configs = parse_entries::<super::provider::yubihsm::YubihsmConfig>(
+                    providers, "yubihsm", problems,
+                );
+
+                for (index, config) in configs {
+                    if let Some(warning) = config.auth.deprecation_warning() {
+                        abscissa_core::status_warn!("providers.yubihsm[{}]: {}", index, warning);
+                    }
+                }
+            }
+            #[cfg(feature = "ledgertm")]
+            "ledgertm" => {
+                parse_entries::<super::provider::ledgertm::LedgerTendermintConfig>(
+                    providers, "ledgertm", problems,
+                );
+            }
+            #[cfg(feature = "exec")]
+            "exec" => {
+                let configs =
+                    parse_entries::<super::provider::exec::ExecConfig>(providers, "exec", problems);
+
+                for (index, config) in configs {
+                    if let Err(e) =
+                        crate::keyring::ed25519::exec::init(registry, std::slice::from_ref(&config))
+                    {
+                        problems.push(format!("providers.exec[{}]: {}", index, e));
+                    }
+                }
+            }
+            #[cfg(feature = "remote")]
+            "remote" => {
+                parse_entries::<super::provider::remote::RemoteConfig>(
+                    providers, "remote", problems,
+                );
+            }
+            other => problems.push(format!(
+                "providers: unknown provider `{}` (or not enabled in this build)",
+                other
+            )),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "softsign"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn valid_config() {
+        let config = r#"
+            [[chain]]
+            id = "test_chain_id"
+            key_format = { type = "hex" }
+
+            [[validator]]
+            addr = "unix:///tmp/example.sock"
+            chain_id = "test_chain_id"
+
+            [[providers.softsign]]
+            chain_ids = ["test_chain_id"]
+            key_format = "base64"
+            path = "tests/support/signing.key"
+        "#;
+
+        assert_eq!(validate(config), Vec::<String>::new());
+    }
+
+    #[test]
+    fn every_problem_is_reported() {
+        let config = r#"
+            unknown_field = true
+
+            [[chain]]
+            id = "test_chain_id"
+            key_format = { type = "hex" }
+            bogus = 1
+
+            [[validator]]
+            addr = "unix:///tmp/example.sock"
+            chain_id = "other_chain_id"
+
+            [[providers.softsign]]
+            chain_ids = ["test_chain_id"]
+            path = "/nonexistent/signing.key"
+
+            [[providers.nonexistent]]
+            path = "example"
+        "#;
+
+        let problems = validate(config);
+        assert_eq!(problems.len(), 5, "{:#?}", problems);
+        assert!(problems[0].contains("unknown_field"));
+        assert!(problems[1].contains("bogus"));
+        assert!(problems[2].contains("other_chain_id"));
+        assert!(problems.iter().any(|p| p.contains("providers.softsign[0]")));
+        assert!(problems.iter().any(|p| p.contains("nonexistent")));
+    }
+}
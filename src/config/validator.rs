@@ -10,7 +10,10 @@ use signatory::{
     ed25519,
     encoding::{Decode, Encode},
 };
-use std::path::PathBuf;
+use std::{
+    net::IpAddr,
+    path::{Path, PathBuf},
+};
 use tendermint::{chain, net};
 
 /// Validator configuration
@@ -30,11 +33,46 @@ pub struct ValidatorConfig {
     /// Optional timeout value in seconds
     pub timeout: Option<u16>,
 
+    /// Time allowed for the whole Secret Connection handshake in seconds
+    /// (default 5)
+    pub handshake_timeout: Option<u16>,
+
+    /// Source of randomness for the Secret Connection's ephemeral keys
+    /// (default `os`)
+    #[serde(default)]
+    pub handshake_rng: HandshakeRng,
+
+    /// Local address to originate TCP connections from (if applicable)
+    pub bind_address: Option<IpAddr>,
+
     /// Path to our Ed25519 identity key (if applicable)
     pub secret_key: Option<PathBuf>,
 
     /// Height at which to stop signing
     pub max_height: Option<tendermint::block::Height>,
+
+    /// Minimum interval between logging identical connection errors, in
+    /// seconds. Repeats are counted and summarized. 0 disables throttling.
+    #[serde(default = "error_log_interval_secs_default")]
+    pub error_log_interval_secs: u64,
+}
+
+/// Source of randomness for Secret Connection ephemeral keys
+#[derive(Copy, Clone, Debug, Deserialize, Eq, PartialEq)]
+pub enum HandshakeRng {
+    /// Operating system CSPRNG
+    #[serde(rename = "os")]
+    Os,
+
+    /// RNG of the provider holding the chain's keys (e.g. a YubiHSM)
+    #[serde(rename = "provider")]
+    Provider,
+}
+
+impl Default for HandshakeRng {
+    fn default() -> Self {
+        HandshakeRng::Os
+    }
 }
 
 impl ValidatorConfig {
@@ -48,30 +86,40 @@ impl ValidatorConfig {
             )
         })?;
 
-        let seed = if secret_key_path.exists() {
-            ed25519::Seed::decode_from_file(secret_key_path, &SecretKeyEncoding::default())
-                .map_err(|e| {
-                    format_err!(
-                        ConfigError,
-                        "error loading Secret Connection key from {}: {}",
-                        secret_key_path.display(),
-                        e
-                    )
-                })?
-        } else {
-            let s = ed25519::Seed::generate();
-            s.encode_to_file(&secret_key_path, &SecretKeyEncoding::default())
-                .map_err(|_| {
-                    format_err!(IoError, "couldn't write: {}", secret_key_path.display())
-                })?;
-            s
-        };
-
-        Ok(seed)
+        load_or_generate_secret_key(secret_key_path)
     }
 }
 
+/// Load a Secret Connection identity key from disk, generating one if it
+/// doesn't exist yet
+pub fn load_or_generate_secret_key(secret_key_path: &Path) -> Result<ed25519::Seed, Error> {
+    let seed = if secret_key_path.exists() {
+        ed25519::Seed::decode_from_file(secret_key_path, &SecretKeyEncoding::default()).map_err(
+            |e| {
+                format_err!(
+                    ConfigError,
+                    "error loading Secret Connection key from {}: {}",
+                    secret_key_path.display(),
+                    e
+                )
+            },
+        )?
+    } else {
+        let s = ed25519::Seed::generate();
+        s.encode_to_file(&secret_key_path, &SecretKeyEncoding::default())
+            .map_err(|_| format_err!(IoError, "couldn't write: {}", secret_key_path.display()))?;
+        s
+    };
+
+    Ok(seed)
+}
+
 /// Default value for the `ValidatorConfig` reconnect field
 fn reconnect_default() -> bool {
     true
 }
+
+/// Default value for the `ValidatorConfig` error_log_interval_secs field
+fn error_log_interval_secs_default() -> u64 {
+    60
+}
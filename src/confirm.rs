@@ -0,0 +1,96 @@
+//! Interactive console confirmation of signatures.
+//!
+//! When enabled for a chain, every signing request is printed to the console
+//! and only signed after an operator types `y`. This is strictly opt-in and
+//! intended for ceremonies and air-gapped test networks.
+
+use crate::{
+    error::{Error, ErrorKind::*},
+    prelude::*,
+};
+use once_cell::sync::Lazy;
+use std::{
+    io::{self, BufRead, Write},
+    sync::{
+        mpsc::{self, Receiver, RecvTimeoutError},
+        Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+/// Default time to wait for confirmation
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Lines read from the console.
+///
+/// A single background thread owns stdin, so a prompt which times out
+/// doesn't leave behind a reader which would swallow the next answer. The
+/// mutex also ensures only one prompt is shown at a time.
+static CONSOLE: Lazy<Mutex<Receiver<String>>> = Lazy::new(|| {
+    let (sender, receiver) = mpsc::channel();
+
+    thread::Builder::new()
+        .name("confirm".to_owned())
+        .spawn(move || {
+            for line in io::stdin().lock().lines() {
+                match line {
+                    Ok(line) => {
+                        if sender.send(line).is_err() {
+                            break;
+                        }
+                    }
+                    Err(_) => break,
+                }
+            }
+        })
+        .expect("error spawning console confirmation thread");
+
+    Mutex::new(receiver)
+});
+
+/// Ask the operator to approve signing the described message, returning an
+/// error if they reject it or don't answer within `timeout`
+pub fn confirm(description: &str, timeout: Duration) -> Result<(), Error> {
+    let console = CONSOLE.lock().unwrap();
+
+    // Discard anything typed before this prompt was shown
+    while console.try_recv().is_ok() {}
+
+    print!(
+        "*** sign {}? [y/N] (refusing in {} s): ",
+        description,
+        timeout.as_secs()
+    );
+    io::stdout().flush()?;
+
+    match console.recv_timeout(timeout) {
+        Ok(answer) => {
+            let answer = answer.trim();
+
+            if answer.eq_ignore_ascii_case("y") || answer.eq_ignore_ascii_case("yes") {
+                Ok(())
+            } else {
+                fail!(
+                    SigningError,
+                    "signing rejected by operator: {}",
+                    description
+                )
+            }
+        }
+        Err(RecvTimeoutError::Timeout) => {
+            println!();
+            fail!(
+                SigningError,
+                "signing not confirmed within {} s: {}",
+                timeout.as_secs(),
+                description
+            )
+        }
+        Err(RecvTimeoutError::Disconnected) => fail!(
+            SigningError,
+            "console closed; can't confirm signing: {}",
+            description
+        ),
+    }
+}
@@ -5,10 +5,30 @@ pub mod tcp;
 pub mod unix;
 
 use self::{secret_connection::SecretConnection, unix::UnixConnection};
-use std::io;
+use crate::prelude::*;
+use std::{io, time::Instant};
 
 /// Connections to a validator
 pub trait Connection: io::Read + io::Write + Sync + Send {}
 
 impl<T> Connection for SecretConnection<T> where T: io::Read + io::Write + Sync + Send {}
 impl<T> Connection for UnixConnection<T> where T: io::Read + io::Write + Sync + Send {}
+
+/// Run one phase of establishing a connection (e.g. DNS resolution, TCP
+/// connect, or the secret connection handshake) in its own tracing span,
+/// recording how long it took
+pub fn timed_phase<T, E>(phase: &'static str, f: impl FnOnce() -> Result<T, E>) -> Result<T, E> {
+    let span = span!(Level::DEBUG, "connection", phase);
+    let _enter = span.enter();
+    let started_at = Instant::now();
+    let result = f();
+    let elapsed_ms = started_at.elapsed().as_millis() as u64;
+
+    if result.is_ok() {
+        debug!(elapsed_ms, "{} completed in {} ms", phase, elapsed_ms);
+    } else {
+        debug!(elapsed_ms, "{} failed after {} ms", phase, elapsed_ms);
+    }
+
+    result
+}
@@ -1,18 +1,29 @@
 //! `SecretConnection`: Transport layer encryption for Tendermint P2P connections.
 
 mod amino_types;
+#[cfg(feature = "debug-tools")]
+pub mod capture;
+mod cipher;
 mod kdf;
 mod nonce;
 mod public_key;
-
-pub use self::{amino_types::AuthSigMessage, kdf::Kdf, nonce::Nonce, public_key::PublicKey};
-use crate::error::{Error, ErrorKind};
-use bytes::BufMut;
-use chacha20poly1305::{
-    aead::{generic_array::GenericArray, Aead, NewAead},
-    ChaCha20Poly1305,
+#[cfg(test)]
+mod test_vectors;
+
+pub use self::{
+    amino_types::AuthSigMessage,
+    cipher::{ChaCha20Poly1305HkdfSha256, CipherSuite, FrameAead, DEFAULT_CIPHER_SUITE},
+    kdf::Kdf,
+    nonce::Nonce,
+    public_key::PublicKey,
 };
+use crate::{
+    error::{Error, ErrorKind},
+    prelude::*,
+};
+use bytes::BufMut;
 use prost_amino::{encoding::encode_varint, Message};
+use rand::{CryptoRng, RngCore};
 use signatory::{
     ed25519,
     signature::{Signature, Signer, Verifier},
@@ -40,8 +51,9 @@ pub struct SecretConnection<IoHandler: Read + Write + Send + Sync> {
     io_handler: IoHandler,
     recv_nonce: Nonce,
     send_nonce: Nonce,
-    recv_cipher: ChaCha20Poly1305,
-    send_cipher: ChaCha20Poly1305,
+    recv_cipher: Box<dyn FrameAead>,
+    send_cipher: Box<dyn FrameAead>,
+    cipher_suite: &'static str,
     remote_pubkey: PublicKey,
     recv_buffer: Vec<u8>,
 }
@@ -52,20 +64,58 @@ impl<IoHandler: Read + Write + Send + Sync> SecretConnection<IoHandler> {
         self.remote_pubkey
     }
 
+    /// Name of the cipher suite protecting this connection
+    pub fn cipher_suite(&self) -> &'static str {
+        self.cipher_suite
+    }
+
     /// Performs handshake and returns a new authenticated SecretConnection.
     pub fn new(
+        handler: IoHandler,
+        local_pubkey: &PublicKey,
+        local_privkey: &dyn Signer<ed25519::Signature>,
+    ) -> Result<SecretConnection<IoHandler>, Error> {
+        Self::new_with_cipher_suite(handler, local_pubkey, local_privkey, &DEFAULT_CIPHER_SUITE)
+    }
+
+    /// Performs handshake using the given cipher suite and returns a new
+    /// authenticated SecretConnection.
+    pub fn new_with_cipher_suite(
+        handler: IoHandler,
+        local_pubkey: &PublicKey,
+        local_privkey: &dyn Signer<ed25519::Signature>,
+        cipher_suite: &dyn CipherSuite,
+    ) -> Result<SecretConnection<IoHandler>, Error> {
+        Self::new_with_rng(
+            handler,
+            local_pubkey,
+            local_privkey,
+            cipher_suite,
+            &mut rand::thread_rng(),
+        )
+    }
+
+    /// Performs handshake using the given cipher suite, generating ephemeral
+    /// keys with the given RNG, and returns a new authenticated
+    /// SecretConnection.
+    pub fn new_with_rng<R: RngCore + CryptoRng>(
         mut handler: IoHandler,
         local_pubkey: &PublicKey,
         local_privkey: &dyn Signer<ed25519::Signature>,
+        cipher_suite: &dyn CipherSuite,
+        rng: &mut R,
     ) -> Result<SecretConnection<IoHandler>, Error> {
         // Generate ephemeral keys for perfect forward secrecy.
-        let (local_eph_pubkey, local_eph_privkey) = gen_eph_keys();
+        let (local_eph_pubkey, local_eph_privkey) = gen_eph_keys(rng);
 
         // Write local ephemeral pubkey and receive one too.
         // NOTE: every 32-byte string is accepted as a Curve25519 public key
         // (see DJB's Curve25519 paper: http://cr.yp.to/ecdh/curve25519-20060209.pdf)
         let remote_eph_pubkey = share_eph_pubkey(&mut handler, &local_eph_pubkey)?;
 
+        debug_handshake_step("local ephemeral public key", local_eph_pubkey.as_bytes());
+        debug_handshake_step("remote ephemeral public key", remote_eph_pubkey.as_bytes());
+
         // Compute common shared secret.
         let shared_secret = EphemeralSecret::diffie_hellman(local_eph_privkey, &remote_eph_pubkey);
 
@@ -89,7 +139,17 @@ impl<IoHandler: Read + Write + Send + Sync> SecretConnection<IoHandler> {
         // was the least, lexicographically sorted.
         let loc_is_least = local_eph_pubkey_bytes == low_eph_pubkey_bytes;
 
-        let kdf = Kdf::derive_secrets_and_challenge(shared_secret.as_bytes(), loc_is_least);
+        let kdf = cipher_suite.derive_secrets_and_challenge(shared_secret.as_bytes(), loc_is_least);
+        debug_handshake_step("challenge", &kdf.challenge);
+
+        // Log the session keys so captured traffic can be decrypted with
+        // `tmkms debug decode-session`. Never enable this in production!
+        #[cfg(feature = "debug-tools")]
+        debug!(
+            "secret connection session keys (debug-tools): recv={} send={}",
+            String::from_utf8(subtle_encoding::hex::encode(&kdf.recv_secret)).unwrap(),
+            String::from_utf8(subtle_encoding::hex::encode(&kdf.send_secret)).unwrap(),
+        );
 
         // Construct SecretConnection.
         let mut sc = SecretConnection {
@@ -97,8 +157,9 @@ impl<IoHandler: Read + Write + Send + Sync> SecretConnection<IoHandler> {
             recv_buffer: vec![],
             recv_nonce: Nonce::default(),
             send_nonce: Nonce::default(),
-            recv_cipher: ChaCha20Poly1305::new(kdf.recv_secret.into()),
-            send_cipher: ChaCha20Poly1305::new(kdf.send_secret.into()),
+            recv_cipher: cipher_suite.new_aead(&kdf.recv_secret),
+            send_cipher: cipher_suite.new_aead(&kdf.send_secret),
+            cipher_suite: cipher_suite.name(),
             remote_pubkey: PublicKey::from(
                 ed25519::PublicKey::from_bytes(remote_eph_pubkey.as_bytes())
                     .ok_or_else(|| ErrorKind::CryptoError)?,
@@ -121,9 +182,17 @@ impl<IoHandler: Read + Write + Send + Sync> SecretConnection<IoHandler> {
         let remote_sig =
             ed25519::Signature::from_bytes(remote_signature).map_err(|_| ErrorKind::CryptoError)?;
 
+        debug_handshake_step("remote identity public key", remote_pubkey.as_bytes());
+        debug_handshake_step("remote challenge signature", remote_signature);
+
         Ed25519Verifier::from(&remote_pubkey)
             .verify(&kdf.challenge, &remote_sig)
-            .map_err(|_| ErrorKind::CryptoError)?;
+            .map_err(|_| {
+                format_err!(
+                    ErrorKind::CryptoError,
+                    "handshake crypto mismatch: invalid peer auth signature"
+                )
+            })?;
 
         // We've authorized.
         sc.remote_pubkey = PublicKey::from(remote_pubkey);
@@ -143,47 +212,72 @@ impl<IoHandler: Read + Write + Send + Sync> SecretConnection<IoHandler> {
 
         let tag = self
             .send_cipher
-            .encrypt_in_place_detached(
-                GenericArray::from_slice(self.send_nonce.to_bytes()),
-                b"",
-                &mut sealed_frame[..TOTAL_FRAME_SIZE],
-            )
-            .map_err(|_| ErrorKind::CryptoError)?;
+            .seal_in_place(&self.send_nonce, &mut sealed_frame[..TOTAL_FRAME_SIZE])?;
 
-        sealed_frame[TOTAL_FRAME_SIZE..].copy_from_slice(tag.as_slice());
+        sealed_frame[TOTAL_FRAME_SIZE..].copy_from_slice(&tag);
 
         Ok(())
     }
 
-    /// Decrypt AEAD authenticated data
-    fn decrypt(&self, ciphertext: &[u8], out: &mut [u8]) -> Result<usize, Error> {
-        // Ensure ciphertext is at least as long as a Poly1305 tag
-        if ciphertext.len() < TAG_SIZE {
-            return Err(ErrorKind::CryptoError.into());
-        }
+    /// Decrypt a received frame, returning the chunk of data it carries
+    fn open_frame(
+        &mut self,
+        sealed_frame: &[u8; TAG_SIZE + TOTAL_FRAME_SIZE],
+    ) -> Result<Vec<u8>, Error> {
+        let chunk = open_sealed_frame(&*self.recv_cipher, &self.recv_nonce, sealed_frame)?;
+        self.recv_nonce.increment();
+        Ok(chunk)
+    }
+}
 
-        // Split ChaCha20 ciphertext from the Poly1305 tag
-        let (ct, tag) = ciphertext.split_at(ciphertext.len() - TAG_SIZE);
+/// Decrypt AEAD authenticated data
+fn decrypt(
+    cipher: &dyn FrameAead,
+    nonce: &Nonce,
+    ciphertext: &[u8],
+    out: &mut [u8],
+) -> Result<usize, Error> {
+    // Ensure ciphertext is at least as long as a Poly1305 tag
+    if ciphertext.len() < TAG_SIZE {
+        return Err(ErrorKind::CryptoError.into());
+    }
 
-        // Return a length error if the output buffer is too small
-        if out.len() < ct.len() {
-            return Err(ErrorKind::CryptoError.into());
-        }
+    // Split ChaCha20 ciphertext from the Poly1305 tag
+    let (ct, tag) = ciphertext.split_at(ciphertext.len() - TAG_SIZE);
 
-        let in_out = &mut out[..ct.len()];
-        in_out.copy_from_slice(ct);
+    // Return a length error if the output buffer is too small
+    if out.len() < ct.len() {
+        return Err(ErrorKind::CryptoError.into());
+    }
+
+    let in_out = &mut out[..ct.len()];
+    in_out.copy_from_slice(ct);
 
-        self.recv_cipher
-            .decrypt_in_place_detached(
-                GenericArray::from_slice(self.recv_nonce.to_bytes()),
-                b"",
-                in_out,
-                tag.into(),
-            )
-            .map_err(|_| ErrorKind::CryptoError)?;
+    cipher.open_in_place(nonce, in_out, tag)?;
+
+    Ok(in_out.len())
+}
 
-        Ok(in_out.len())
+/// Decrypt a sealed frame with the given nonce, returning the chunk of data
+/// it carries
+fn open_sealed_frame(
+    cipher: &dyn FrameAead,
+    nonce: &Nonce,
+    sealed_frame: &[u8; TAG_SIZE + TOTAL_FRAME_SIZE],
+) -> Result<Vec<u8>, Error> {
+    let mut frame = [0u8; TOTAL_FRAME_SIZE];
+    decrypt(cipher, nonce, sealed_frame, &mut frame)?;
+
+    let chunk_length = u32::from_le_bytes(frame[..4].try_into().unwrap()) as usize;
+
+    if chunk_length > DATA_MAX_SIZE {
+        fail!(
+            ErrorKind::ProtocolError,
+            "chunk_length is greater than dataMaxSize"
+        );
     }
+
+    Ok(frame[DATA_LEN_SIZE..(DATA_LEN_SIZE + chunk_length)].to_vec())
 }
 
 impl<IoHandler> Read for SecretConnection<IoHandler>
@@ -205,33 +299,9 @@ where
         let mut sealed_frame = [0u8; TAG_SIZE + TOTAL_FRAME_SIZE];
         self.io_handler.read_exact(&mut sealed_frame)?;
 
-        // decrypt the frame
-        let mut frame = [0u8; TOTAL_FRAME_SIZE];
-        let res = self.decrypt(&sealed_frame, &mut frame);
-
-        if res.is_err() {
-            return Err(io::Error::new(
-                io::ErrorKind::Other,
-                res.err().unwrap().to_string(),
-            ));
-        }
-
-        self.recv_nonce.increment();
-        // end decryption
-
-        let chunk_length = u32::from_le_bytes(frame[..4].try_into().unwrap());
-
-        if chunk_length as usize > DATA_MAX_SIZE {
-            return Err(io::Error::new(
-                io::ErrorKind::Other,
-                "chunk_length is greater than dataMaxSize",
-            ));
-        }
-
-        let mut chunk = vec![0; chunk_length as usize];
-        chunk.clone_from_slice(
-            &frame[DATA_LEN_SIZE..(DATA_LEN_SIZE.checked_add(chunk_length as usize).unwrap())],
-        );
+        let chunk = self
+            .open_frame(&sealed_frame)
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
 
         let n = cmp::min(data.len(), chunk.len());
         data[..n].copy_from_slice(&chunk[..n]);
@@ -283,9 +353,8 @@ where
 }
 
 /// Returns pubkey, private key
-fn gen_eph_keys() -> (EphemeralPublic, EphemeralSecret) {
-    let mut local_csprng = rand::thread_rng();
-    let local_privkey = EphemeralSecret::new(&mut local_csprng);
+fn gen_eph_keys<R: RngCore + CryptoRng>(rng: &mut R) -> (EphemeralPublic, EphemeralSecret) {
+    let local_privkey = EphemeralSecret::new(rng);
     let local_pubkey = EphemeralPublic::from(&local_privkey);
     (local_pubkey, local_privkey)
 }
@@ -392,6 +461,22 @@ fn sort32(first: [u8; 32], second: [u8; 32]) -> ([u8; 32], [u8; 32]) {
     }
 }
 
+/// Log a value computed or exchanged during the handshake, so a failing
+/// handshake can be compared step by step against another implementation's.
+/// Only enabled with the `debug-tools` feature: never enable it in production!
+#[cfg(feature = "debug-tools")]
+fn debug_handshake_step(step: &str, bytes: &[u8]) {
+    debug!(
+        "secret connection handshake (debug-tools): {}={}",
+        step,
+        String::from_utf8(subtle_encoding::hex::encode(bytes)).unwrap()
+    );
+}
+
+/// Handshake debugging is only enabled with the `debug-tools` feature
+#[cfg(not(feature = "debug-tools"))]
+fn debug_handshake_step(_step: &str, _bytes: &[u8]) {}
+
 /// Sign the challenge with the local private key
 fn sign_challenge(
     challenge: &[u8; 32],
@@ -417,14 +502,44 @@ fn share_auth_signature<IoHandler: Read + Write + Send + Sync>(
     amsg.encode_length_delimited(&mut buf)?;
     sc.write_all(&buf)?;
 
-    let mut rbuf = vec![0; 106]; // 100 = 32 + 64 + (amino overhead = 2 fields + 2 lengths + 4 prefix bytes + total length)
-    sc.read_exact(&mut rbuf)?;
+    // The peer's auth signature message (32 + 64 bytes plus amino overhead)
+    // is small enough to always arrive in a single frame. Read it directly,
+    // so failing to decrypt it (i.e. the peers derived different keys) can
+    // be told apart from network errors.
+    let mut sealed_frame = [0u8; TAG_SIZE + TOTAL_FRAME_SIZE];
+    sc.io_handler.read_exact(&mut sealed_frame)?;
+
+    let rbuf = sc.open_frame(&sealed_frame).map_err(auth_frame_error)?;
+
+    AuthSigMessage::decode_length_delimited(rbuf.as_ref()).map_err(|e| {
+        format_err!(
+            ErrorKind::ProtocolError,
+            "handshake protocol mismatch: malformed peer auth signature \
+             (is the validator running a compatible Tendermint version?): {}",
+            e
+        )
+        .into()
+    })
+}
 
-    // TODO: proper error handling:
-    Ok(AuthSigMessage::decode_length_delimited(rbuf.as_ref())?)
+/// Describe an error opening the frame carrying the peer's auth signature.
+/// Failing to decrypt it means the peers derived different keys; other
+/// errors (e.g. an invalid chunk length) are passed through unchanged.
+fn auth_frame_error(e: Error) -> Error {
+    if *e.kind() != ErrorKind::CryptoError {
+        return e;
+    }
+
+    format_err!(
+        ErrorKind::CryptoError,
+        "handshake crypto mismatch: couldn't decrypt peer's auth signature \
+         (is the validator running a compatible Tendermint version?): {}",
+        e
+    )
+    .into()
 }
 
-#[cfg(tests)]
+#[cfg(test)]
 mod tests {
     use super::*;
 
@@ -444,6 +559,33 @@ mod tests {
         assert_eq!(t2, *t4);
     }
 
+    #[test]
+    fn auth_frame_errors_only_report_decryption_failures_as_mismatches() {
+        let cipher = DEFAULT_CIPHER_SUITE.new_aead(&[1u8; 32]);
+        let other_cipher = DEFAULT_CIPHER_SUITE.new_aead(&[2u8; 32]);
+        let nonce = Nonce::default();
+
+        // A frame which decrypts, but has an invalid chunk length
+        let mut sealed_frame = [0u8; TAG_SIZE + TOTAL_FRAME_SIZE];
+        sealed_frame[..DATA_LEN_SIZE].copy_from_slice(&(DATA_MAX_SIZE as u32 + 1).to_le_bytes());
+        let tag = cipher
+            .seal_in_place(&nonce, &mut sealed_frame[..TOTAL_FRAME_SIZE])
+            .unwrap();
+        sealed_frame[TOTAL_FRAME_SIZE..].copy_from_slice(&tag);
+
+        let err = open_sealed_frame(&*cipher, &nonce, &sealed_frame)
+            .map_err(auth_frame_error)
+            .unwrap_err();
+        assert_eq!(*err.kind(), ErrorKind::ProtocolError);
+        assert!(!err.to_string().contains("crypto mismatch"), "{}", err);
+
+        let err = open_sealed_frame(&*other_cipher, &nonce, &sealed_frame)
+            .map_err(auth_frame_error)
+            .unwrap_err();
+        assert_eq!(*err.kind(), ErrorKind::CryptoError);
+        assert!(err.to_string().contains("crypto mismatch"), "{}", err);
+    }
+
     #[test]
     fn test_dh_compatibility() {
         let local_priv = &[
@@ -460,7 +602,7 @@ mod tests {
             92, 56, 205, 118, 191, 208, 49, 3, 226, 150, 30, 205, 230, 157, 163, 7, 36, 28, 223,
             84, 165, 43, 78, 38, 126, 200, 40, 217, 29, 36, 43, 37,
         ];
-        let got_dh = diffie_hellman(local_priv, remote_pub);
+        let got_dh = x25519_dalek::x25519(*local_priv, *remote_pub);
 
         assert_eq!(expected_dh, &got_dh);
     }
@@ -0,0 +1,199 @@
+//! Decryption of captured `SecretConnection` traffic, for debugging.
+//!
+//! Given the key for one direction of a connection (as logged by KMS builds
+//! with the `debug-tools` feature) and the raw bytes sent in that direction,
+//! this recovers the handshake's auth signature message and the sequence of
+//! length-prefixed messages which followed it.
+
+use super::{
+    amino_types::AuthSigMessage, open_sealed_frame, CipherSuite, Nonce, TAG_SIZE, TOTAL_FRAME_SIZE,
+};
+use crate::{
+    error::{Error, ErrorKind::*},
+    prelude::*,
+    rpc::MAX_MSG_LEN,
+};
+use prost_amino::{
+    encoding::{decode_varint, encoded_len_varint},
+    Message,
+};
+use std::convert::TryInto;
+
+/// Size of an encrypted frame on the wire
+pub const SEALED_FRAME_SIZE: usize = TAG_SIZE + TOTAL_FRAME_SIZE;
+
+/// Size of the (unencrypted) ephemeral public key message which begins each
+/// direction of a connection
+const EPH_PUBKEY_MSG_SIZE: usize = 34;
+
+/// Decrypted traffic sent in one direction of a `SecretConnection`
+#[derive(Debug)]
+pub struct DecryptedStream {
+    /// Ephemeral public key sent at the start of the stream (if captured)
+    pub eph_pubkey: Option<[u8; 32]>,
+
+    /// Auth signature message sent during the handshake
+    pub auth_sig: AuthSigMessage,
+
+    /// Length-prefixed messages sent after the handshake, including their
+    /// length prefix
+    pub messages: Vec<Vec<u8>>,
+
+    /// Trailing bytes which don't form a complete message (e.g. because the
+    /// capture was cut short)
+    pub remainder: Vec<u8>,
+}
+
+impl DecryptedStream {
+    /// Decrypt the bytes sent in one direction of a connection using the
+    /// given key. The capture must begin at the start of the connection,
+    /// either with the ephemeral public key or the first encrypted frame.
+    pub fn decrypt(
+        cipher_suite: &dyn CipherSuite,
+        key: &[u8; 32],
+        mut stream: &[u8],
+    ) -> Result<Self, Error> {
+        let mut eph_pubkey = None;
+
+        if stream.len() % SEALED_FRAME_SIZE == EPH_PUBKEY_MSG_SIZE && stream[..2] == [33, 32] {
+            eph_pubkey = Some(stream[2..EPH_PUBKEY_MSG_SIZE].try_into().unwrap());
+            stream = &stream[EPH_PUBKEY_MSG_SIZE..];
+        }
+
+        if stream.len() % SEALED_FRAME_SIZE != 0 {
+            fail!(
+                ParseError,
+                "capture is {} bytes, which isn't a whole number of {}-byte frames",
+                stream.len(),
+                SEALED_FRAME_SIZE
+            );
+        }
+
+        let cipher = cipher_suite.new_aead(key);
+        let mut nonce = Nonce::default();
+        let mut plaintext = vec![];
+
+        for (index, sealed_frame) in stream.chunks(SEALED_FRAME_SIZE).enumerate() {
+            let chunk = open_sealed_frame(&*cipher, &nonce, sealed_frame.try_into().unwrap())
+                .map_err(|e| format_err!(CryptoError, "couldn't decrypt frame {}: {}", index, e))?;
+
+            plaintext.extend_from_slice(&chunk);
+            nonce.increment();
+        }
+
+        let mut messages = split_messages(&plaintext);
+        let remainder = messages.pop().unwrap_or_default();
+        let mut messages = messages.into_iter();
+
+        let auth_sig = messages
+            .next()
+            .ok_or_else(|| format_err!(ParseError, "capture ends before the auth signature"))
+            .and_then(|msg| {
+                AuthSigMessage::decode_length_delimited(msg.as_ref())
+                    .map_err(|e| format_err!(ParseError, "malformed auth signature: {}", e))
+            })?;
+
+        Ok(Self {
+            eph_pubkey,
+            auth_sig,
+            messages: messages.collect(),
+            remainder,
+        })
+    }
+}
+
+/// Split a plaintext stream into length-prefixed messages. The last element
+/// is whatever remains after the last complete message (possibly empty).
+fn split_messages(plaintext: &[u8]) -> Vec<Vec<u8>> {
+    let mut messages = vec![];
+    let mut rest = plaintext;
+
+    while !rest.is_empty() {
+        let mut buf = rest;
+
+        let msg_len = match decode_varint(&mut buf) {
+            Ok(len) if len <= MAX_MSG_LEN as u64 => len as usize,
+            _ => break,
+        };
+
+        let total_len = encoded_len_varint(msg_len as u64) + msg_len;
+
+        if total_len > rest.len() {
+            break;
+        }
+
+        messages.push(rest[..total_len].to_vec());
+        rest = &rest[total_len..];
+    }
+
+    messages.push(rest.to_vec());
+    messages
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connection::secret_connection::{
+        DATA_LEN_SIZE, DATA_MAX_SIZE, DEFAULT_CIPHER_SUITE,
+    };
+
+    /// Encrypt the given chunks as consecutive frames
+    fn seal_frames(key: &[u8; 32], chunks: &[&[u8]]) -> Vec<u8> {
+        let cipher = DEFAULT_CIPHER_SUITE.new_aead(key);
+        let mut nonce = Nonce::default();
+        let mut stream = vec![];
+
+        for chunk in chunks {
+            assert!(chunk.len() <= DATA_MAX_SIZE);
+
+            let mut frame = [0u8; TOTAL_FRAME_SIZE];
+            frame[..DATA_LEN_SIZE].copy_from_slice(&(chunk.len() as u32).to_le_bytes());
+            frame[DATA_LEN_SIZE..DATA_LEN_SIZE + chunk.len()].copy_from_slice(chunk);
+
+            let tag = cipher.seal_in_place(&nonce, &mut frame).unwrap();
+            stream.extend_from_slice(&frame);
+            stream.extend_from_slice(&tag);
+            nonce.increment();
+        }
+
+        stream
+    }
+
+    #[test]
+    fn decrypt_captured_stream() {
+        let key = [7u8; 32];
+
+        let auth_sig = AuthSigMessage {
+            key: vec![1; 32],
+            sig: vec![2; 64],
+        };
+
+        let mut auth_sig_bytes = vec![];
+        auth_sig
+            .encode_length_delimited(&mut auth_sig_bytes)
+            .unwrap();
+
+        let mut capture = vec![33, 32];
+        capture.extend_from_slice(&[9; 32]);
+        capture.extend(seal_frames(
+            &key,
+            &[&auth_sig_bytes, &[3, 0xaa, 0xbb, 0xcc, 2, 0xdd]],
+        ));
+
+        let stream = DecryptedStream::decrypt(&DEFAULT_CIPHER_SUITE, &key, &capture).unwrap();
+        assert_eq!(stream.eph_pubkey, Some([9; 32]));
+        assert_eq!(stream.auth_sig, auth_sig);
+        assert_eq!(stream.messages, vec![vec![3, 0xaa, 0xbb, 0xcc]]);
+        assert_eq!(stream.remainder, vec![2, 0xdd]);
+    }
+
+    #[test]
+    fn wrong_key_is_reported() {
+        let capture = seal_frames(&[7u8; 32], &[&[0]]);
+        let err = DecryptedStream::decrypt(&DEFAULT_CIPHER_SUITE, &[8u8; 32], &capture)
+            .unwrap_err()
+            .to_string();
+
+        assert!(err.contains("frame 0"), "{}", err);
+    }
+}
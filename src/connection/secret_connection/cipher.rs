@@ -0,0 +1,84 @@
+//! Cryptographic primitives used to protect a `SecretConnection`.
+//!
+//! Key derivation and the AEAD used to encrypt frames are abstracted behind
+//! the `CipherSuite` trait so alternatives can be added for future protocol
+//! versions. Tendermint doesn't negotiate these, so both peers must use the
+//! same suite: presently `ChaCha20Poly1305HkdfSha256`, which is the default.
+
+use super::{kdf::Kdf, nonce::Nonce, TAG_SIZE};
+use crate::error::{Error, ErrorKind};
+use chacha20poly1305::{
+    aead::{generic_array::GenericArray, Aead, NewAead},
+    ChaCha20Poly1305,
+};
+
+/// Default cipher suite
+pub static DEFAULT_CIPHER_SUITE: ChaCha20Poly1305HkdfSha256 = ChaCha20Poly1305HkdfSha256;
+
+/// Key derivation function and AEAD used by a `SecretConnection`
+pub trait CipherSuite: Sync {
+    /// Name of this cipher suite (e.g. for logging)
+    fn name(&self) -> &'static str;
+
+    /// Derive the send/receive secrets and the authentication challenge from
+    /// the Diffie-Hellman shared secret
+    fn derive_secrets_and_challenge(&self, shared_secret: &[u8; 32], loc_is_lo: bool) -> Kdf;
+
+    /// Create an AEAD instance for encrypting or decrypting frames with the
+    /// given key
+    fn new_aead(&self, key: &[u8; 32]) -> Box<dyn FrameAead>;
+}
+
+/// AEAD used to encrypt and decrypt individual frames
+pub trait FrameAead: Send + Sync {
+    /// Encrypt the buffer in place, returning the authentication tag
+    fn seal_in_place(&self, nonce: &Nonce, buffer: &mut [u8]) -> Result<[u8; TAG_SIZE], Error>;
+
+    /// Authenticate and decrypt the buffer in place
+    fn open_in_place(&self, nonce: &Nonce, buffer: &mut [u8], tag: &[u8]) -> Result<(), Error>;
+}
+
+/// ChaCha20Poly1305 frames with keys derived using HKDF-SHA256, as used by
+/// Tendermint's secret connection protocol
+#[derive(Copy, Clone, Debug, Default)]
+pub struct ChaCha20Poly1305HkdfSha256;
+
+impl CipherSuite for ChaCha20Poly1305HkdfSha256 {
+    fn name(&self) -> &'static str {
+        "ChaCha20Poly1305/HKDF-SHA256"
+    }
+
+    fn derive_secrets_and_challenge(&self, shared_secret: &[u8; 32], loc_is_lo: bool) -> Kdf {
+        Kdf::derive_secrets_and_challenge(shared_secret, loc_is_lo)
+    }
+
+    fn new_aead(&self, key: &[u8; 32]) -> Box<dyn FrameAead> {
+        Box::new(ChaCha20Poly1305::new(*GenericArray::from_slice(key)))
+    }
+}
+
+impl FrameAead for ChaCha20Poly1305 {
+    fn seal_in_place(&self, nonce: &Nonce, buffer: &mut [u8]) -> Result<[u8; TAG_SIZE], Error> {
+        let tag = self
+            .encrypt_in_place_detached(GenericArray::from_slice(nonce.to_bytes()), b"", buffer)
+            .map_err(|_| ErrorKind::CryptoError)?;
+
+        let mut result = [0u8; TAG_SIZE];
+        result.copy_from_slice(tag.as_slice());
+        Ok(result)
+    }
+
+    fn open_in_place(&self, nonce: &Nonce, buffer: &mut [u8], tag: &[u8]) -> Result<(), Error> {
+        if tag.len() != TAG_SIZE {
+            return Err(ErrorKind::CryptoError.into());
+        }
+
+        self.decrypt_in_place_detached(
+            GenericArray::from_slice(nonce.to_bytes()),
+            b"",
+            buffer,
+            GenericArray::from_slice(tag),
+        )
+        .map_err(|_| ErrorKind::CryptoError.into())
+    }
+}
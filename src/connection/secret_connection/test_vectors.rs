@@ -0,0 +1,233 @@
+//! Fixed-input test vectors for the `SecretConnection` handshake.
+//!
+//! These run the full handshake between two peers with injected ephemeral
+//! keys and deterministic (Ed25519) identity keys, and check the ephemeral
+//! Diffie-Hellman output, the derived session keys and challenge, and the
+//! exact bytes each peer puts on the wire. Any change to them is a change to
+//! the wire protocol, which breaks compatibility with Tendermint's
+//! (pre-Merlin, v0.32-era) secret connection.
+
+use super::*;
+use rand::Error as RandError;
+use sha2::{Digest, Sha256};
+use signatory::public_key::PublicKeyed;
+use signatory_dalek::Ed25519Signer;
+use std::{
+    os::unix::net::UnixStream,
+    sync::{Arc, Mutex},
+    thread,
+};
+use subtle_encoding::hex;
+
+/// Ephemeral secret key of the first peer
+const PEER_A_EPH_SECRET: [u8; 32] = [0x0a; 32];
+
+/// Ephemeral secret key of the second peer
+const PEER_B_EPH_SECRET: [u8; 32] = [0x0b; 32];
+
+/// Expected ephemeral public key of the first peer
+const PEER_A_EPH_PUBKEY: &str = "f77ff4b10788bfdca62ca0bb160d427cf5762d85f2b5cad6807ec9c3febbde09";
+
+/// Expected ephemeral public key of the second peer
+const PEER_B_EPH_PUBKEY: &str = "73b2d8b76aa9b53660032bc8f5d8bee3a3ae4e3b3a7fd49ade81f7347a34aa68";
+
+/// Expected X25519 shared secret
+const SHARED_SECRET: &str = "c09d8a17f54f06a53f844eacbc6273017581b9bc53b5f31f3d338cc3ffd7b86b";
+
+/// Expected receive secret of the peer whose ephemeral public key sorts
+/// lowest (the second peer), i.e. the send secret of the other peer
+const LO_RECV_SECRET: &str = "ab7224a060b29b5f31d1a4b3ab1a9dab408a1bae0086d6bbd146042f518c6f84";
+
+/// Expected send secret of the peer whose ephemeral public key sorts lowest
+const LO_SEND_SECRET: &str = "12c3294aebfe28532f68d248fff934cfd2ec4868d89673f9cc84a7abd0d3012a";
+
+/// Expected challenge both peers sign
+const CHALLENGE: &str = "d86874f7c8ad07ad7e391f80809f21d9940c3d15e9e345f5c0c1542e516ae465";
+
+/// Expected SHA-256 digest of everything the first peer wrote (ephemeral
+/// public key, auth signature frame, and one message frame)
+const PEER_A_WIRE_DIGEST: &str = "da3be2340d25631f5b0667c071e8466a4620004ea66a0e1ff6eecbe816448dde";
+
+/// Expected SHA-256 digest of everything the second peer wrote
+const PEER_B_WIRE_DIGEST: &str = "6842f22ae11c1a40a8f805308fa115b71b68ce16f8cf6a02d235a56c366341e5";
+
+/// RNG which produces the given bytes, used to inject ephemeral keys
+struct FixedRng([u8; 32]);
+
+impl RngCore for FixedRng {
+    fn next_u32(&mut self) -> u32 {
+        unimplemented!()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        unimplemented!()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        dest.copy_from_slice(&self.0[..dest.len()]);
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), RandError> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl CryptoRng for FixedRng {}
+
+/// Socket which records everything written to it
+struct RecordingSocket {
+    socket: UnixStream,
+    written: Arc<Mutex<Vec<u8>>>,
+}
+
+impl Read for RecordingSocket {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.socket.read(buf)
+    }
+}
+
+impl Write for RecordingSocket {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.socket.write(buf)?;
+        self.written.lock().unwrap().extend_from_slice(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.socket.flush()
+    }
+}
+
+/// Result of one peer's side of the handshake
+struct Peer {
+    /// Identity public key
+    public_key: PublicKey,
+
+    /// Authenticated public key of the other peer
+    remote_pubkey: PublicKey,
+
+    /// Message received from the other peer after the handshake
+    received: Vec<u8>,
+
+    /// Everything written to the wire
+    written: Vec<u8>,
+}
+
+/// Run one side of the handshake, then send `message` and receive one
+fn run_peer(socket: UnixStream, identity_seed: u8, eph_secret: [u8; 32], message: &[u8]) -> Peer {
+    let signer = Ed25519Signer::from(&ed25519::Seed::from_bytes([identity_seed; 32]).unwrap());
+    let public_key = PublicKey::from(signer.public_key().unwrap());
+    let written = Arc::new(Mutex::new(vec![]));
+
+    let socket = RecordingSocket {
+        socket,
+        written: Arc::clone(&written),
+    };
+
+    let mut conn = SecretConnection::new_with_rng(
+        socket,
+        &public_key,
+        &signer,
+        &DEFAULT_CIPHER_SUITE,
+        &mut FixedRng(eph_secret),
+    )
+    .unwrap();
+
+    conn.write_all(message).unwrap();
+
+    let mut buf = [0u8; DATA_MAX_SIZE];
+    let n = conn.read(&mut buf).unwrap();
+
+    let written = written.lock().unwrap().clone();
+
+    Peer {
+        public_key,
+        remote_pubkey: conn.remote_pubkey(),
+        received: buf[..n].to_vec(),
+        written,
+    }
+}
+
+fn hex_string(bytes: &[u8]) -> String {
+    String::from_utf8(hex::encode(bytes)).unwrap()
+}
+
+#[test]
+fn ephemeral_keys_and_session_secrets() {
+    let eph_pubkey_a =
+        EphemeralPublic::from(&EphemeralSecret::new(&mut FixedRng(PEER_A_EPH_SECRET)));
+    let eph_pubkey_b =
+        EphemeralPublic::from(&EphemeralSecret::new(&mut FixedRng(PEER_B_EPH_SECRET)));
+
+    let shared_secret = x25519_dalek::x25519(PEER_A_EPH_SECRET, *eph_pubkey_b.as_bytes());
+    assert_eq!(
+        shared_secret,
+        x25519_dalek::x25519(PEER_B_EPH_SECRET, *eph_pubkey_a.as_bytes())
+    );
+
+    let lo = DEFAULT_CIPHER_SUITE.derive_secrets_and_challenge(&shared_secret, true);
+    let hi = DEFAULT_CIPHER_SUITE.derive_secrets_and_challenge(&shared_secret, false);
+
+    assert_eq!(hex_string(eph_pubkey_a.as_bytes()), PEER_A_EPH_PUBKEY);
+    assert_eq!(hex_string(eph_pubkey_b.as_bytes()), PEER_B_EPH_PUBKEY);
+    assert_eq!(hex_string(&shared_secret), SHARED_SECRET);
+    assert_eq!(hex_string(&lo.recv_secret), LO_RECV_SECRET);
+    assert_eq!(hex_string(&lo.send_secret), LO_SEND_SECRET);
+    assert_eq!(hex_string(&lo.challenge), CHALLENGE);
+
+    assert_eq!(lo.recv_secret, hi.send_secret);
+    assert_eq!(lo.send_secret, hi.recv_secret);
+    assert_eq!(lo.challenge, hi.challenge);
+}
+
+#[test]
+fn full_handshake_transcript() {
+    let (socket_a, socket_b) = UnixStream::pair().unwrap();
+
+    let peer_b = thread::spawn(move || run_peer(socket_b, 2, PEER_B_EPH_SECRET, b"pong"));
+    let peer_a = run_peer(socket_a, 1, PEER_A_EPH_SECRET, b"ping");
+    let peer_b = peer_b.join().unwrap();
+
+    assert_eq!(peer_a.remote_pubkey, peer_b.public_key);
+    assert_eq!(peer_b.remote_pubkey, peer_a.public_key);
+    assert_eq!(peer_a.received, b"pong");
+    assert_eq!(peer_b.received, b"ping");
+
+    // Each peer sends its ephemeral public key, then two frames
+    for (peer, eph_pubkey) in &[(&peer_a, PEER_A_EPH_PUBKEY), (&peer_b, PEER_B_EPH_PUBKEY)] {
+        assert_eq!(peer.written.len(), 34 + 2 * (TAG_SIZE + TOTAL_FRAME_SIZE));
+        assert_eq!(peer.written[..2], [33, 32]);
+        assert_eq!(hex_string(&peer.written[2..34]), *eph_pubkey);
+    }
+
+    // The first peer's frames are encrypted with the second peer's receive
+    // secret, and begin with its auth signature over the challenge
+    let mut recv_secret = [0u8; 32];
+    recv_secret.copy_from_slice(&hex::decode(LO_RECV_SECRET).unwrap());
+
+    let cipher = DEFAULT_CIPHER_SUITE.new_aead(&recv_secret);
+    let auth_sig_frame = peer_a.written[34..34 + TAG_SIZE + TOTAL_FRAME_SIZE]
+        .try_into()
+        .unwrap();
+    let auth_sig_msg = open_sealed_frame(&*cipher, &Nonce::default(), auth_sig_frame).unwrap();
+    let auth_sig = AuthSigMessage::decode_length_delimited(auth_sig_msg.as_ref()).unwrap();
+
+    let PublicKey::Ed25519(ref public_key_a) = peer_a.public_key;
+    assert_eq!(auth_sig.key, public_key_a.as_bytes());
+
+    let challenge = hex::decode(CHALLENGE).unwrap();
+    let signature = ed25519::Signature::from_bytes(&auth_sig.sig).unwrap();
+    assert!(Ed25519Verifier::from(public_key_a)
+        .verify(&challenge, &signature)
+        .is_ok());
+
+    assert_eq!(
+        hex_string(&Sha256::digest(&peer_a.written)),
+        PEER_A_WIRE_DIGEST
+    );
+    assert_eq!(
+        hex_string(&Sha256::digest(&peer_b.written)),
+        PEER_B_WIRE_DIGEST
+    );
+}
@@ -1,40 +1,90 @@
 //! TCP socket connection to a validator
 
-use super::secret_connection::{PublicKey, SecretConnection};
+use super::{
+    secret_connection::{PublicKey, SecretConnection, DEFAULT_CIPHER_SUITE},
+    timed_phase,
+};
 use crate::{
     error::{Error, ErrorKind::*},
     prelude::*,
 };
+use net2::TcpBuilder;
+use rand::{rngs::StdRng, SeedableRng};
 use signatory::{ed25519, public_key::PublicKeyed};
 use signatory_dalek::Ed25519Signer;
-use std::{net::TcpStream, time::Duration};
+use std::{
+    io,
+    net::{IpAddr, Shutdown, SocketAddr, TcpStream, ToSocketAddrs},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, RecvTimeoutError},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
 use subtle::ConstantTimeEq;
 use tendermint::node;
+use zeroize::Zeroizing;
 
 /// Default timeout in seconds
 const DEFAULT_TIMEOUT: u16 = 10;
 
-/// Open a TCP socket connection encrypted with SecretConnection
+/// Default time allowed for the whole Secret Connection handshake in seconds
+const DEFAULT_HANDSHAKE_TIMEOUT: u16 = 5;
+
+/// Open a TCP socket connection encrypted with SecretConnection.
+///
+/// If `ephemeral_seed` is given, the handshake's ephemeral keys are derived
+/// from it (e.g. randomness from an HSM) rather than the OS CSPRNG.
+#[allow(clippy::too_many_arguments)]
 pub fn open_secret_connection(
     host: &str,
     port: u16,
     secret_key: &ed25519::Seed,
     peer_id: &Option<node::Id>,
     timeout: Option<u16>,
+    handshake_timeout: Option<u16>,
+    bind_address: Option<IpAddr>,
+    ephemeral_seed: Option<Zeroizing<[u8; 32]>>,
 ) -> Result<SecretConnection<TcpStream>, Error> {
     let signer = Ed25519Signer::from(secret_key);
     let public_key = PublicKey::from(signer.public_key().map_err(|_| Error::from(InvalidKey))?);
 
     info!("KMS node ID: {}", &public_key);
 
-    let socket = TcpStream::connect(format!("{}:{}", host, port))?;
+    let addrs = timed_phase("dns", || (host, port).to_socket_addrs())?.collect::<Vec<_>>();
+    let socket = timed_phase("tcp_connect", || match bind_address {
+        Some(bind_address) => connect_from(bind_address, &addrs),
+        None => TcpStream::connect(&addrs[..]).map_err(Error::from),
+    })?;
 
     let timeout = Duration::from_secs(timeout.unwrap_or(DEFAULT_TIMEOUT).into());
     socket.set_read_timeout(Some(timeout))?;
     socket.set_write_timeout(Some(timeout))?;
 
-    let connection = SecretConnection::new(socket, &public_key, &signer)?;
+    let handshake_timeout = Duration::from_secs(
+        handshake_timeout
+            .unwrap_or(DEFAULT_HANDSHAKE_TIMEOUT)
+            .into(),
+    );
+
+    let connection = timed_phase("handshake", || {
+        handshake(
+            socket,
+            &public_key,
+            &signer,
+            handshake_timeout,
+            ephemeral_seed,
+        )
+    })?;
     let actual_peer_id = connection.remote_pubkey().peer_id();
+    info!(
+        "{}:{}: secret connection established using {}",
+        host,
+        port,
+        connection.cipher_suite()
+    );
 
     // TODO(tarcieri): move this into `SecretConnection::new`
     if let Some(expected_peer_id) = peer_id {
@@ -52,3 +102,118 @@ pub fn open_secret_connection(
 
     Ok(connection)
 }
+
+/// Perform the Secret Connection handshake, dropping the connection if the
+/// whole handshake doesn't complete within `timeout`
+fn handshake(
+    socket: TcpStream,
+    public_key: &PublicKey,
+    signer: &Ed25519Signer,
+    timeout: Duration,
+    ephemeral_seed: Option<Zeroizing<[u8; 32]>>,
+) -> Result<SecretConnection<TcpStream>, Error> {
+    let watchdog_socket = socket.try_clone()?;
+    let timed_out = Arc::new(AtomicBool::new(false));
+    let (done_tx, done_rx) = mpsc::channel::<()>();
+
+    let watchdog = {
+        let timed_out = Arc::clone(&timed_out);
+
+        thread::spawn(move || {
+            if let Err(RecvTimeoutError::Timeout) = done_rx.recv_timeout(timeout) {
+                timed_out.store(true, Ordering::SeqCst);
+                let _ = watchdog_socket.shutdown(Shutdown::Both);
+            }
+        })
+    };
+
+    let result = match ephemeral_seed {
+        Some(seed) => SecretConnection::new_with_rng(
+            socket,
+            public_key,
+            signer,
+            &DEFAULT_CIPHER_SUITE,
+            &mut StdRng::from_seed(*seed),
+        ),
+        None => SecretConnection::new(socket, public_key, signer),
+    };
+
+    // The watchdog may still fire between the handshake completing and being
+    // told so, in which case the socket is already shut down
+    let _ = done_tx.send(());
+    watchdog.join().unwrap();
+
+    if timed_out.load(Ordering::SeqCst) {
+        fail!(
+            IoError,
+            "handshake timeout: not completed within {} ms",
+            timeout.as_millis()
+        );
+    }
+
+    result
+}
+
+/// Connect to the first reachable address in `addrs` from a socket bound to
+/// the given local address
+fn connect_from(bind_address: IpAddr, addrs: &[SocketAddr]) -> Result<TcpStream, Error> {
+    let mut last_error = None;
+
+    for addr in addrs
+        .iter()
+        .filter(|addr| addr.is_ipv4() == bind_address.is_ipv4())
+    {
+        let builder = if bind_address.is_ipv4() {
+            TcpBuilder::new_v4()?
+        } else {
+            TcpBuilder::new_v6()?
+        };
+
+        builder
+            .bind(SocketAddr::new(bind_address, 0))
+            .map_err(|e| format_err!(IoError, "couldn't bind to {}: {}", bind_address, e))?;
+
+        match builder.connect(addr) {
+            Ok(socket) => return Ok(socket),
+            Err(e) => last_error = Some(e),
+        }
+    }
+
+    Err(last_error
+        .unwrap_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("no addresses of the same family as {}", bind_address),
+            )
+        })
+        .into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    #[test]
+    fn stalled_handshake_times_out() {
+        // Accepts connections but never answers the handshake
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let socket = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let _peer = listener.accept().unwrap();
+
+        let signer = Ed25519Signer::from(&ed25519::Seed::from_bytes([1u8; 32]).unwrap());
+        let public_key = PublicKey::from(signer.public_key().unwrap());
+
+        let err = handshake(
+            socket,
+            &public_key,
+            &signer,
+            Duration::from_millis(100),
+            None,
+        )
+        .err()
+        .expect("handshake should time out");
+
+        assert!(err.to_string().contains("handshake timeout"));
+    }
+}
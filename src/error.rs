@@ -1,6 +1,6 @@
 //! Error types
 
-use crate::{chain, prelude::*};
+use crate::{chain, prelude::*, redact};
 use abscissa_core::error::{BoxError, Context};
 use std::{
     any::Any,
@@ -50,6 +50,11 @@ pub enum ErrorKind {
     #[error("I/O error")]
     IoError,
 
+    /// Signer is too busy to handle the request (e.g. because earlier
+    /// operations stalled)
+    #[error("signer overloaded")]
+    Overloaded,
+
     /// KMS internal panic
     #[error("internal crash")]
     PanicError,
@@ -102,13 +107,7 @@ pub struct Error(Box<Context<ErrorKind>>);
 impl Error {
     /// Create an error from a panic
     pub fn from_panic(panic_msg: Box<dyn Any>) -> Self {
-        let err_msg = if let Some(msg) = panic_msg.downcast_ref::<String>() {
-            msg.as_ref()
-        } else if let Some(msg) = panic_msg.downcast_ref::<&str>() {
-            msg
-        } else {
-            "unknown cause"
-        };
+        let err_msg = redact::panic_message(&*panic_msg);
 
         let kind = if err_msg.contains("PoisonError") {
             ErrorKind::PoisonError
@@ -1,18 +1,35 @@
 //! Signing keyring. Presently specialized for Ed25519.
 
+pub mod algorithm;
+pub mod attestation;
+pub mod capabilities;
+pub mod concurrency;
 pub mod ed25519;
 pub mod format;
+pub mod metadata;
 pub mod providers;
+pub mod self_test;
 
 use self::ed25519::Signer;
-pub use self::{format::Format, providers::SigningProvider};
+pub use self::{
+    algorithm::SigningAlgorithm, attestation::Attestation, capabilities::Capabilities,
+    concurrency::ConcurrencyLimit, format::Format, metadata::Metadata, providers::SigningProvider,
+};
 use crate::{
     chain,
-    config::provider::ProviderConfig,
+    config::{
+        chain::{DuplicateKeyPolicy, KeyTypeLabels},
+        provider::ProviderConfig,
+    },
     error::{Error, ErrorKind::*},
     prelude::*,
 };
-use std::collections::BTreeMap;
+use std::{
+    collections::BTreeMap,
+    sync::{mpsc, Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
 use tendermint::TendermintKey;
 
 /// File encoding for software-backed secret keys
@@ -20,11 +37,21 @@ pub type SecretKeyEncoding = subtle_encoding::Base64;
 
 /// Signing keyring
 pub struct KeyRing {
-    /// Keys in the keyring
-    keys: BTreeMap<TendermintKey, Signer>,
+    /// Keys in the keyring, each with its primary signer first followed by
+    /// any failover signers
+    keys: BTreeMap<TendermintKey, Vec<Signer>>,
+
+    /// What to do when a key is added more than once
+    duplicate_keys: DuplicateKeyPolicy,
 
     /// Formatting configuration when displaying keys (e.g. bech32)
     format: Format,
+
+    /// Labels displayed for each type of key
+    key_type_labels: KeyTypeLabels,
+
+    /// Hard deadline for signers to produce a signature (if any)
+    sign_timeout: Option<Duration>,
 }
 
 impl KeyRing {
@@ -33,78 +60,393 @@ impl KeyRing {
         Self {
             keys: BTreeMap::new(),
             format,
+            duplicate_keys: DuplicateKeyPolicy::default(),
+            key_type_labels: KeyTypeLabels::default(),
+            sign_timeout: None,
         }
     }
 
-    /// Add a key to the keyring, returning an error if we already have a
-    /// signer registered for the given public key
+    /// Display keys using the given key type labels
+    pub fn set_key_type_labels(&mut self, key_type_labels: KeyTypeLabels) {
+        self.key_type_labels = key_type_labels;
+    }
+
+    /// Set what to do when a key is added more than once
+    pub fn set_duplicate_key_policy(&mut self, policy: DuplicateKeyPolicy) {
+        self.duplicate_keys = policy;
+    }
+
+    /// Abandon signing operations which don't complete within `timeout`
+    pub fn set_sign_timeout(&mut self, timeout: Duration) {
+        self.sign_timeout = Some(timeout);
+    }
+
+    /// Add a key to the keyring. If a signer is already registered for the
+    /// given public key, this returns an error unless the duplicate key
+    /// policy is `failover`, in which case it's added as a failover signer.
     pub fn add(&mut self, signer: Signer) -> Result<(), Error> {
         let provider = signer.provider();
         let public_key = signer.public_key();
         let public_key_serialized = self.format.serialize(public_key);
         let key_type = match public_key {
-            TendermintKey::AccountKey(_) => "account",
-            TendermintKey::ConsensusKey(_) => "consensus",
+            TendermintKey::AccountKey(_) => &self.key_type_labels.account,
+            TendermintKey::ConsensusKey(_) => &self.key_type_labels.consensus,
         };
 
-        info!(
-            "[keyring:{}] added {} key {}",
-            provider, key_type, public_key_serialized
-        );
+        let address = self.format.serialize_address(public_key);
 
-        if let Some(other) = self.keys.insert(public_key, signer) {
-            fail!(
-                InvalidKey,
-                "[keyring:{}] duplicate key {} already registered as {}",
+        if signer.metadata().is_empty() {
+            info!(
+                "[keyring:{}] added {} {} key {} (address {})",
                 provider,
+                key_type,
+                signer.algorithm(),
                 public_key_serialized,
-                other.provider(),
-            )
+                address
+            );
         } else {
-            Ok(())
+            info!(
+                "[keyring:{}] added {} {} key {} (address {}; {})",
+                provider,
+                key_type,
+                signer.algorithm(),
+                public_key_serialized,
+                address,
+                signer.metadata()
+            );
+        }
+
+        let signers = self.keys.entry(public_key).or_default();
+
+        if let Some(primary) = signers.first() {
+            if self.duplicate_keys == DuplicateKeyPolicy::Reject {
+                fail!(
+                    InvalidKey,
+                    "[keyring:{}] duplicate key {} already registered as {}",
+                    provider,
+                    public_key_serialized,
+                    primary.provider(),
+                );
+            }
+
+            if primary.algorithm() != signer.algorithm() {
+                fail!(
+                    InvalidKey,
+                    "[keyring:{}] duplicate key {} is registered as {} by {}, not {}",
+                    provider,
+                    public_key_serialized,
+                    primary.algorithm(),
+                    primary.provider(),
+                    signer.algorithm()
+                );
+            }
+
+            info!(
+                "[keyring:{}] key {} is failover #{} for {}",
+                provider,
+                public_key_serialized,
+                signers.len(),
+                primary.provider()
+            );
         }
+
+        signers.push(signer);
+        Ok(())
+    }
+
+    /// Is there a signer for the given public key in this keyring?
+    pub fn contains(&self, public_key: &TendermintKey) -> bool {
+        self.keys.contains_key(public_key)
+    }
+
+    /// Get the provider of the signer for the given public key (if present)
+    pub fn get_provider(&self, public_key: &TendermintKey) -> Option<SigningProvider> {
+        self.get_primary(public_key).map(Signer::provider)
+    }
+
+    /// Iterate over all of the signers in this keyring, including failover
+    /// signers for the same key
+    pub fn signers(&self) -> impl Iterator<Item = &Signer> {
+        self.keys.values().flatten()
+    }
+
+    /// Fill the given buffer with random bytes from the first provider in
+    /// this keyring which supports generating randomness, returning that
+    /// provider
+    pub fn random_bytes(&self, buffer: &mut [u8]) -> Result<SigningProvider, Error> {
+        for signer in self.signers() {
+            if let Some(result) = signer.random_bytes(buffer) {
+                result?;
+                return Ok(signer.provider());
+            }
+        }
+
+        fail!(
+            InvalidKey,
+            "no provider in keyring supports generating random bytes"
+        );
+    }
+
+    /// Get the format used when displaying keys in this keyring
+    pub fn format(&self) -> &Format {
+        &self.format
     }
 
     /// Get the default public key for this keyring
     pub fn default_pubkey(&self) -> Result<TendermintKey, Error> {
-        let mut keys = self.keys.keys();
+        let mut keys = self.consensus_signers().map(|signer| signer.public_key());
 
-        if keys.len() == 1 {
-            Ok(*keys.next().unwrap())
-        } else {
-            fail!(InvalidKey, "expected only one key in keyring");
+        match (keys.next(), keys.next()) {
+            (Some(key), None) => Ok(key),
+            _ => fail!(InvalidKey, "expected only one key in keyring"),
+        }
+    }
+
+    /// Get the provider of the default key for this keyring
+    pub fn default_provider(&self) -> Option<SigningProvider> {
+        let mut signers = self.consensus_signers();
+
+        match (signers.next(), signers.next()) {
+            (Some(signer), None) => Some(signer.provider()),
+            _ => None,
         }
     }
 
     /// Sign a message using the secret key associated with the given public key
     /// (if it is in our keyring)
+    ///
+    /// Only pure Ed25519 keys are used: Ed25519ph keys can never produce
+    /// signatures through this method (see `sign_ed25519ph`).
     pub fn sign_ed25519(
         &self,
         public_key: Option<&TendermintKey>,
         msg: &[u8],
     ) -> Result<ed25519::Signature, Error> {
         let signer = match public_key {
-            Some(public_key) => self.keys.get(public_key).ok_or_else(|| {
-                format_err!(InvalidKey, "not in keyring: {}", public_key.to_bech32(""))
-            })?,
+            Some(public_key) => self.get_signer(public_key)?,
             None => {
-                let mut vals = self.keys.values();
+                let mut signers = self.consensus_signers();
 
-                if vals.len() > 1 {
-                    fail!(SigningError, "expected only one key in keyring");
-                } else {
-                    vals.next()
-                        .ok_or_else(|| format_err!(InvalidKey, "keyring is empty"))?
+                match (signers.next(), signers.next()) {
+                    (Some(signer), None) => signer,
+                    (Some(_), Some(_)) => fail!(SigningError, "expected only one key in keyring"),
+                    (None, _) => fail!(InvalidKey, "keyring is empty"),
+                }
+            }
+        };
+
+        if signer.algorithm() != SigningAlgorithm::Ed25519 {
+            fail!(
+                SigningError,
+                "refusing to sign with {} key {}: not an ed25519 key",
+                signer.algorithm(),
+                self.format.serialize(signer.public_key())
+            );
+        }
+
+        self.sign_with_failover(signer, msg)
+    }
+
+    /// Sign a message with the Ed25519ph key associated with the given public
+    /// key. This is intended for integrations which require prehashed
+    /// signatures, and will never use a consensus key.
+    pub fn sign_ed25519ph(
+        &self,
+        public_key: &TendermintKey,
+        msg: &[u8],
+    ) -> Result<ed25519::Signature, Error> {
+        let signer = self.get_signer(public_key)?;
+
+        if signer.algorithm() != SigningAlgorithm::Ed25519ph {
+            fail!(
+                SigningError,
+                "refusing to sign with {} key {}: not an ed25519ph key",
+                signer.algorithm(),
+                self.format.serialize(signer.public_key())
+            );
+        }
+
+        self.sign_with_failover(signer, msg)
+    }
+
+    /// Sign a message with the given primary signer, falling back to the
+    /// failover signers for its key (in order) if it returns an error
+    fn sign_with_failover(
+        &self,
+        primary: &Signer,
+        msg: &[u8],
+    ) -> Result<ed25519::Signature, Error> {
+        let signers = &self.keys[&primary.public_key()];
+        let mut last_error = None;
+
+        for signer in signers {
+            if let Some(ref e) = last_error {
+                warn!(
+                    "[keyring:{}] failing over to {} after signing error: {}",
+                    primary.provider(),
+                    signer.provider(),
+                    e
+                );
+            }
+
+            match self.sign_with_watchdog(signer, msg) {
+                Ok(signature) => return Ok(signature),
+                Err(e) => last_error = Some(e),
+            }
+        }
+
+        Err(last_error.unwrap())
+    }
+
+    /// Sign a message, giving up if the signer doesn't return before the
+    /// configured deadline (e.g. because an HSM is wedged).
+    ///
+    /// Time spent waiting for the signer's concurrency limit (if any) counts
+    /// against the deadline. Abandoned signing operations keep running in
+    /// the background, and neither the signer nor the other signers sharing
+    /// its concurrency limit are used until they complete (other signers,
+    /// e.g. failover signers for the same key from another provider, are
+    /// unaffected).
+    fn sign_with_watchdog(&self, signer: &Signer, msg: &[u8]) -> Result<ed25519::Signature, Error> {
+        let timeout = match self.sign_timeout {
+            Some(timeout) => timeout,
+            None => return signer.sign(msg),
+        };
+
+        let deadline = Instant::now() + timeout;
+        let stalled = signer.stalled();
+
+        if stalled > 0 {
+            fail!(
+                Overloaded,
+                "[keyring:{}] refusing to sign: {} earlier signing operation(s) still stalled",
+                signer.provider(),
+                stalled
+            );
+        }
+
+        let permit = match signer.concurrency_limit() {
+            Some(limit) => {
+                let abandoned = limit.abandoned();
+
+                if abandoned > 0 {
+                    fail!(
+                        Overloaded,
+                        "[keyring:{}] refusing to sign: {} signing operation(s) sharing its \
+                         concurrency limit still stalled",
+                        signer.provider(),
+                        abandoned
+                    );
                 }
+
+                let permit = limit.acquire_timeout(timeout).ok_or_else(|| {
+                    format_err!(
+                        Overloaded,
+                        "[keyring:{}] timed out after {} ms waiting for one of {} concurrent \
+                         signing operations to complete",
+                        signer.provider(),
+                        timeout.as_millis(),
+                        limit.max()
+                    )
+                })?;
+
+                Some(permit)
             }
+            None => None,
         };
 
-        signer.sign(msg)
+        let (sender, receiver) = mpsc::channel();
+        let abandoned = Arc::new(Mutex::new(false));
+        let background_abandoned = Arc::clone(&abandoned);
+        let background_signer = signer.clone();
+        let background_msg = msg.to_vec();
+
+        thread::Builder::new()
+            .name("sign".to_owned())
+            .spawn(move || {
+                let result = background_signer.sign_with_permit(&background_msg, permit);
+
+                // Hold the lock while sending, so the result is either
+                // received or known to have been abandoned
+                let abandoned = background_abandoned.lock().unwrap();
+
+                if *abandoned {
+                    background_signer.record_stall_completed();
+                    warn!(
+                        "[keyring:{}] stalled signing operation finally completed",
+                        background_signer.provider()
+                    );
+                } else {
+                    let _ = sender.send(result);
+                }
+            })
+            .map_err(|e| format_err!(SigningError, "error spawning signing thread: {}", e))?;
+
+        let remaining = deadline.saturating_duration_since(Instant::now());
+
+        if let Ok(result) = receiver.recv_timeout(remaining) {
+            return result;
+        }
+
+        let mut abandoned = abandoned.lock().unwrap();
+
+        // The signer may have finished just as the deadline passed
+        if let Ok(result) = receiver.try_recv() {
+            return result;
+        }
+
+        *abandoned = true;
+        signer.record_stall();
+
+        error!(
+            "[keyring:{}] CRITICAL: signer didn't respond within {} ms; abandoning request",
+            signer.provider(),
+            timeout.as_millis()
+        );
+
+        fail!(
+            Overloaded,
+            "[keyring:{}] signing timed out after {} ms",
+            signer.provider(),
+            timeout.as_millis()
+        )
+    }
+
+    /// Get the primary signer for the given public key
+    fn get_signer(&self, public_key: &TendermintKey) -> Result<&Signer, Error> {
+        self.get_primary(public_key).ok_or_else(|| {
+            format_err!(InvalidKey, "not in keyring: {}", public_key.to_bech32("")).into()
+        })
+    }
+
+    /// Get the primary signer for the given public key (if present)
+    fn get_primary(&self, public_key: &TendermintKey) -> Option<&Signer> {
+        self.keys
+            .get(public_key)
+            .and_then(|signers| signers.first())
+    }
+
+    /// Iterate over the primary signers which can be used for consensus
+    /// messages
+    fn consensus_signers(&self) -> impl Iterator<Item = &Signer> {
+        self.keys
+            .values()
+            .filter_map(|signers| signers.first())
+            .filter(|signer| {
+                let is_consensus_key = match signer.public_key() {
+                    TendermintKey::ConsensusKey(_) => true,
+                    TendermintKey::AccountKey(_) => false,
+                };
+
+                is_consensus_key && signer.algorithm() == SigningAlgorithm::Ed25519
+            })
     }
 }
 
 /// Initialize the keyring from the configuration file
 pub fn load_config(registry: &mut chain::Registry, config: &ProviderConfig) -> Result<(), Error> {
+    check_chain_ids(registry, config)?;
+
     #[cfg(feature = "softsign")]
     ed25519::softsign::init(registry, &config.softsign)?;
 
@@ -114,5 +456,343 @@ pub fn load_config(registry: &mut chain::Registry, config: &ProviderConfig) -> R
     #[cfg(feature = "ledgertm")]
     ed25519::ledgertm::init(registry, &config.ledgertm)?;
 
+    #[cfg(feature = "exec")]
+    ed25519::exec::init(registry, &config.exec)?;
+
+    #[cfg(feature = "remote")]
+    ed25519::remote::init(registry, &config.remote)?;
+
     Ok(())
 }
+
+/// Ensure every chain referenced by a provider is defined, reporting all of
+/// the undefined ones at once
+fn check_chain_ids(registry: &chain::Registry, config: &ProviderConfig) -> Result<(), Error> {
+    let mut undefined = vec![];
+
+    for (provider, chain_id) in config.chain_ids() {
+        let reference = format!("{} -> `{}`", provider, chain_id);
+
+        if registry.get_chain(&chain_id).is_none() && !undefined.contains(&reference) {
+            undefined.push(reference);
+        }
+    }
+
+    if !undefined.is_empty() {
+        fail!(
+            ConfigError,
+            "provider references undefined chain(s): {} (add a [[chain]] entry for each)",
+            undefined.join(", ")
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(all(test, feature = "softsign"))]
+mod tests {
+    use super::*;
+    use signatory::{ed25519, public_key::PublicKeyed};
+    use signatory_dalek::Ed25519Signer;
+    use subtle_encoding::hex;
+    use tendermint::PublicKey;
+
+    /// Example secp256k1 public key (compressed generator point)
+    const SECP256K1_KEY: &str =
+        "0279BE667EF9DCBBAC55A06295CE870B07029BFCDB2DCE28D959F2815B16F81798";
+
+    fn example_keyring() -> (KeyRing, TendermintKey) {
+        let provider = Ed25519Signer::from(&ed25519::Seed::from_bytes([1u8; 32]).unwrap());
+        let public_key = TendermintKey::ConsensusKey(provider.public_key().unwrap().into());
+        let signer = Signer::new(SigningProvider::SoftSign, public_key, Box::new(provider));
+
+        let mut keyring = KeyRing::new(Format::Hex);
+        keyring.add(signer).unwrap();
+        (keyring, public_key)
+    }
+
+    #[test]
+    fn lookup_present_key() {
+        let (keyring, public_key) = example_keyring();
+        assert!(keyring.contains(&public_key));
+        assert_eq!(
+            keyring.get_provider(&public_key),
+            Some(SigningProvider::SoftSign)
+        );
+    }
+
+    #[test]
+    fn lookup_absent_keys() {
+        let (keyring, public_key) = example_keyring();
+
+        // same key bytes in a different role
+        let account_key = match public_key {
+            TendermintKey::ConsensusKey(pk) => TendermintKey::AccountKey(pk),
+            TendermintKey::AccountKey(_) => unreachable!(),
+        };
+
+        // key on a different curve
+        let secp256k1_key = TendermintKey::ConsensusKey(
+            PublicKey::from_raw_secp256k1(&hex::decode_upper(SECP256K1_KEY).unwrap()).unwrap(),
+        );
+
+        for absent_key in &[account_key, secp256k1_key] {
+            assert!(!keyring.contains(absent_key));
+            assert_eq!(keyring.get_provider(absent_key), None);
+        }
+    }
+
+    /// Signer which takes a fixed amount of time to sign
+    struct SlowSigner(Ed25519Signer, Duration);
+
+    impl signatory::signature::Signer<ed25519::Signature> for SlowSigner {
+        fn try_sign(&self, msg: &[u8]) -> Result<ed25519::Signature, signatory::signature::Error> {
+            thread::sleep(self.1);
+            self.0.try_sign(msg)
+        }
+    }
+
+    #[test]
+    fn stalled_signer_is_abandoned() {
+        let provider = Ed25519Signer::from(&ed25519::Seed::from_bytes([1u8; 32]).unwrap());
+        let public_key = TendermintKey::ConsensusKey(provider.public_key().unwrap().into());
+        let slow_signer = SlowSigner(provider, Duration::from_millis(200));
+        let signer = Signer::new(SigningProvider::SoftSign, public_key, Box::new(slow_signer));
+
+        let mut keyring = KeyRing::new(Format::Hex);
+        keyring.add(signer).unwrap();
+        keyring.set_sign_timeout(Duration::from_millis(20));
+
+        assert!(keyring.sign_ed25519(None, b"example").is_err());
+
+        // No signing while the abandoned operation is still running
+        assert!(keyring.sign_ed25519(None, b"example").is_err());
+
+        thread::sleep(Duration::from_millis(400));
+        keyring.set_sign_timeout(Duration::from_secs(1));
+        assert!(keyring.sign_ed25519(None, b"example").is_ok());
+    }
+
+    #[test]
+    fn stalled_primary_fails_over() {
+        let seed = ed25519::Seed::from_bytes([1u8; 32]).unwrap();
+        let provider = Ed25519Signer::from(&seed);
+        let public_key = TendermintKey::ConsensusKey(provider.public_key().unwrap().into());
+        let stalled = Signer::new(
+            SigningProvider::SoftSign,
+            public_key,
+            Box::new(SlowSigner(
+                Ed25519Signer::from(&seed),
+                Duration::from_millis(500),
+            )),
+        );
+        let working = Signer::new(SigningProvider::SoftSign, public_key, Box::new(provider));
+
+        let mut keyring = KeyRing::new(Format::Hex);
+        keyring.set_duplicate_key_policy(DuplicateKeyPolicy::Failover);
+        keyring.add(stalled).unwrap();
+        keyring.add(working).unwrap();
+        keyring.set_sign_timeout(Duration::from_millis(50));
+
+        // The primary misses the deadline, so the failover signer signs
+        assert!(keyring.sign_ed25519(None, b"example").is_ok());
+
+        // Later requests skip the stalled primary
+        for _ in 0..2 {
+            assert!(keyring.sign_ed25519(None, b"example").is_ok());
+        }
+
+        assert_eq!(keyring.signers().next().unwrap().stalled(), 1);
+    }
+
+    #[test]
+    fn queued_signing_operations_count_against_the_deadline() {
+        let provider = Ed25519Signer::from(&ed25519::Seed::from_bytes([1u8; 32]).unwrap());
+        let public_key = TendermintKey::ConsensusKey(provider.public_key().unwrap().into());
+        let slow_signer = SlowSigner(provider, Duration::from_millis(50));
+        let limit = ConcurrencyLimit::new(1);
+        let signer = Signer::new(SigningProvider::SoftSign, public_key, Box::new(slow_signer))
+            .with_concurrency_limit(Some(limit.clone()));
+
+        let mut keyring = KeyRing::new(Format::Hex);
+        keyring.add(signer).unwrap();
+        keyring.set_sign_timeout(Duration::from_millis(150));
+
+        // Each operation takes 50 ms, so the second of two concurrent ones
+        // still completes within the deadline after waiting for the first
+        let keyring = Arc::new(keyring);
+        let workers = (0..2)
+            .map(|_| {
+                let keyring = Arc::clone(&keyring);
+                thread::spawn(move || keyring.sign_ed25519(None, b"example"))
+            })
+            .collect::<Vec<_>>();
+
+        for worker in workers {
+            assert!(worker.join().unwrap().is_ok());
+        }
+
+        // Waiting for the limit gives up at the deadline
+        let permit = limit.acquire();
+        let err = keyring.sign_ed25519(None, b"example").unwrap_err();
+        assert_eq!(*err.kind(), Overloaded);
+
+        drop(permit);
+        assert!(keyring.sign_ed25519(None, b"example").is_ok());
+        assert_eq!(keyring.signers().next().unwrap().stalled(), 0);
+    }
+
+    #[test]
+    fn stalled_signer_blocks_its_shared_limit() {
+        let stalled_provider = Ed25519Signer::from(&ed25519::Seed::from_bytes([1u8; 32]).unwrap());
+        let stalled_key =
+            TendermintKey::ConsensusKey(stalled_provider.public_key().unwrap().into());
+        let other_provider = Ed25519Signer::from(&ed25519::Seed::from_bytes([2u8; 32]).unwrap());
+        let other_key = TendermintKey::ConsensusKey(other_provider.public_key().unwrap().into());
+
+        let limit = Some(ConcurrencyLimit::new(1));
+        let stalled = Signer::new(
+            SigningProvider::SoftSign,
+            stalled_key,
+            Box::new(SlowSigner(stalled_provider, Duration::from_millis(300))),
+        )
+        .with_concurrency_limit(limit.clone());
+        let other = Signer::new(
+            SigningProvider::SoftSign,
+            other_key,
+            Box::new(other_provider),
+        )
+        .with_concurrency_limit(limit);
+
+        let mut keyring = KeyRing::new(Format::Hex);
+        keyring.add(stalled).unwrap();
+        keyring.add(other).unwrap();
+        keyring.set_sign_timeout(Duration::from_millis(50));
+
+        assert!(keyring
+            .sign_ed25519(Some(&stalled_key), b"example")
+            .is_err());
+
+        // The other key fails fast rather than waiting for the stalled
+        // operation's permit
+        let start = Instant::now();
+        let err = keyring
+            .sign_ed25519(Some(&other_key), b"example")
+            .unwrap_err();
+        assert_eq!(*err.kind(), Overloaded);
+        assert!(start.elapsed() < Duration::from_millis(50));
+
+        // Once the stalled operation completes, the limit can be used again
+        thread::sleep(Duration::from_millis(500));
+        assert!(keyring.sign_ed25519(Some(&other_key), b"example").is_ok());
+    }
+
+    /// Signer which must never be used
+    struct UnusableSigner;
+
+    impl signatory::signature::Signer<ed25519::Signature> for UnusableSigner {
+        fn try_sign(&self, _: &[u8]) -> Result<ed25519::Signature, signatory::signature::Error> {
+            panic!("signer used to answer a public key request");
+        }
+    }
+
+    /// Signer which always fails
+    struct FailingSigner;
+
+    impl signatory::signature::Signer<ed25519::Signature> for FailingSigner {
+        fn try_sign(&self, _: &[u8]) -> Result<ed25519::Signature, signatory::signature::Error> {
+            Err(signatory::signature::Error::from_source("HSM unavailable"))
+        }
+    }
+
+    #[test]
+    fn duplicate_keys_fail_over() {
+        let provider = Ed25519Signer::from(&ed25519::Seed::from_bytes([1u8; 32]).unwrap());
+        let public_key = TendermintKey::ConsensusKey(provider.public_key().unwrap().into());
+        let failing = Signer::new(
+            SigningProvider::SoftSign,
+            public_key,
+            Box::new(FailingSigner),
+        );
+        let working = Signer::new(SigningProvider::SoftSign, public_key, Box::new(provider));
+
+        // rejected by default
+        let mut keyring = KeyRing::new(Format::Hex);
+        keyring.add(failing.clone()).unwrap();
+        assert!(keyring.add(working.clone()).is_err());
+
+        keyring.set_duplicate_key_policy(DuplicateKeyPolicy::Failover);
+        assert!(keyring.sign_ed25519(None, b"example").is_err());
+
+        keyring.add(working).unwrap();
+        assert_eq!(keyring.signers().count(), 2);
+        assert_eq!(keyring.default_pubkey().unwrap(), public_key);
+        assert!(keyring.sign_ed25519(Some(&public_key), b"example").is_ok());
+        assert!(keyring.sign_ed25519(None, b"example").is_ok());
+    }
+
+    #[test]
+    fn default_pubkey_is_served_without_signer() {
+        let provider = Ed25519Signer::from(&ed25519::Seed::from_bytes([1u8; 32]).unwrap());
+        let public_key = TendermintKey::ConsensusKey(provider.public_key().unwrap().into());
+        let signer = Signer::new(
+            SigningProvider::SoftSign,
+            public_key,
+            Box::new(UnusableSigner),
+        );
+
+        let mut keyring = KeyRing::new(Format::Hex);
+        keyring.add(signer).unwrap();
+
+        // Public keys are derived when providers are loaded, so answering a
+        // `PubKeyRequest` never involves a round trip to the signer
+        assert_eq!(keyring.default_pubkey().unwrap(), public_key);
+    }
+
+    #[test]
+    fn attestation_is_optional() {
+        let (keyring, _) = example_keyring();
+        let signer = keyring.signers().next().unwrap().clone();
+        assert!(signer.attestation().is_none());
+
+        let signer =
+            signer.with_attestation(|| Ok(Attestation::new("example", b"document".to_vec())));
+        let attestation = signer.attestation().unwrap().unwrap();
+        assert_eq!(attestation.document, b"document");
+    }
+
+    #[test]
+    fn undefined_chains_are_reported_together() {
+        let chain_config: crate::config::chain::ChainConfig =
+            toml::from_str("id = \"mainnet\"\nkey_format = { type = \"hex\" }\n").unwrap();
+
+        let mut registry = chain::Registry::default();
+        registry
+            .register_chain(
+                chain::Chain::with_state(&chain_config, chain::State::unpersisted()).unwrap(),
+            )
+            .unwrap();
+
+        let config: ProviderConfig = toml::from_str(
+            r#"
+                [[softsign]]
+                chain_ids = ["mainnet", "testnet", "devnet"]
+                path = "/nonexistent/mainnet.key"
+
+                [[softsign]]
+                chain_ids = ["testnet"]
+                path = "/nonexistent/testnet.key"
+            "#,
+        )
+        .unwrap();
+
+        let err = load_config(&mut registry, &config).unwrap_err();
+        assert_eq!(*err.kind(), ConfigError);
+        assert!(
+            err.to_string()
+                .contains("undefined chain(s): softsign -> `testnet`, softsign -> `devnet`"),
+            "{}",
+            err
+        );
+    }
+}
@@ -0,0 +1,23 @@
+//! Signature algorithms supported by the keyring
+
+use std::fmt::{self, Display};
+
+/// Enumeration of signature algorithms (i.e. curves) used by signers
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub enum SigningAlgorithm {
+    /// Ed25519 (EdDSA over Curve25519)
+    Ed25519,
+
+    /// Ed25519ph (Ed25519 over a SHA-512 prehash of the message, RFC 8032).
+    /// Never used for consensus messages.
+    Ed25519ph,
+}
+
+impl Display for SigningAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SigningAlgorithm::Ed25519 => write!(f, "ed25519"),
+            SigningAlgorithm::Ed25519ph => write!(f, "ed25519ph"),
+        }
+    }
+}
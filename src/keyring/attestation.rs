@@ -0,0 +1,46 @@
+//! Attestation of signing keys by the providers holding them
+
+use crate::error::Error;
+use sha2::{Digest, Sha256};
+use std::{fmt, sync::Arc};
+use subtle_encoding::hex;
+
+/// Provider-specific hook which fetches a fresh attestation for a key
+pub type AttestationHook = Arc<dyn Fn() -> Result<Attestation, Error> + Send + Sync>;
+
+/// Evidence from a signing provider (e.g. an HSM) that a key is held by it
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Attestation {
+    /// Format of the attestation document (e.g. `x509-der`)
+    pub format: &'static str,
+
+    /// Attestation document, as produced by the provider
+    pub document: Vec<u8>,
+}
+
+impl Attestation {
+    /// Create a new attestation document of the given format
+    pub fn new(format: &'static str, document: impl Into<Vec<u8>>) -> Self {
+        Self {
+            format,
+            document: document.into(),
+        }
+    }
+
+    /// SHA-256 fingerprint of the attestation document (hex encoded)
+    pub fn fingerprint(&self) -> String {
+        String::from_utf8(hex::encode(Sha256::digest(&self.document))).unwrap()
+    }
+}
+
+impl fmt::Display for Attestation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} ({} bytes, sha256:{})",
+            self.format,
+            self.document.len(),
+            self.fingerprint()
+        )
+    }
+}
@@ -0,0 +1,80 @@
+//! Optional capabilities of loaded signers (e.g. attestation support).
+//!
+//! These are derived from what each provider registered when the signer was
+//! loaded, so querying them never involves a round trip to an HSM. They're
+//! displayed by `tmkms test` and the management API.
+
+use serde::Serialize;
+use std::fmt;
+
+/// Optional capabilities supported by a signer
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Serialize)]
+pub struct Capabilities {
+    /// Can the provider attest to the key (e.g. an HSM-signed certificate)?
+    pub attestation: bool,
+
+    /// Can the provider generate random bytes (e.g. using an HSM's RNG)?
+    pub random_bytes: bool,
+
+    /// Does the signer produce Ed25519ph (prehashed) signatures?
+    pub prehash: bool,
+
+    /// Does the provider transparently reconnect to its backend (e.g. an
+    /// HSM or sign server) after connection errors?
+    pub reconnect: bool,
+}
+
+impl Capabilities {
+    /// Names of the supported capabilities
+    pub fn names(&self) -> Vec<&'static str> {
+        let mut names = vec![];
+
+        if self.attestation {
+            names.push("attestation");
+        }
+
+        if self.random_bytes {
+            names.push("random_bytes");
+        }
+
+        if self.prehash {
+            names.push("prehash");
+        }
+
+        if self.reconnect {
+            names.push("reconnect");
+        }
+
+        names
+    }
+}
+
+impl fmt::Display for Capabilities {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names = self.names();
+
+        if names.is_empty() {
+            write!(f, "none")
+        } else {
+            write!(f, "{}", names.join(", "))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display_capabilities() {
+        assert_eq!(Capabilities::default().to_string(), "none");
+
+        let capabilities = Capabilities {
+            attestation: true,
+            reconnect: true,
+            ..Capabilities::default()
+        };
+
+        assert_eq!(capabilities.to_string(), "attestation, reconnect");
+    }
+}
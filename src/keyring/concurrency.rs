@@ -0,0 +1,204 @@
+//! Limits on the number of concurrent signing operations per provider.
+//!
+//! Some backends (e.g. an HSM with a small number of sessions) fail when
+//! asked to sign too many messages at once, which can happen when they're
+//! shared by several chains. Signers loaded from the same provider share a
+//! limit: signing operations beyond it wait for earlier ones to complete.
+
+use crate::{
+    error::{Error, ErrorKind::*},
+    prelude::*,
+};
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Condvar, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+/// Limit on concurrent signing operations. Clones share the same limit, so
+/// a provider's signers are each given a clone.
+#[derive(Clone, Debug)]
+pub struct ConcurrencyLimit {
+    /// Maximum number of concurrent signing operations
+    max: usize,
+
+    /// Signing operations currently in progress
+    in_flight: Arc<InFlight>,
+}
+
+/// Signing operations in progress under a `ConcurrencyLimit`
+#[derive(Debug, Default)]
+struct InFlight {
+    /// Number of signing operations in progress
+    count: Mutex<usize>,
+
+    /// Notified when a signing operation completes
+    available: Condvar,
+
+    /// Number of signing operations in progress which have been abandoned
+    /// (e.g. because they missed a deadline) and are still holding permits
+    abandoned: AtomicUsize,
+}
+
+impl ConcurrencyLimit {
+    /// Create a limit from a provider's `max_concurrent_signs` setting (if
+    /// configured)
+    pub fn from_config(max_concurrent_signs: Option<usize>) -> Result<Option<Self>, Error> {
+        match max_concurrent_signs {
+            Some(0) => fail!(ConfigError, "max_concurrent_signs must be at least 1"),
+            Some(max) => Ok(Some(Self::new(max))),
+            None => Ok(None),
+        }
+    }
+
+    /// Create a limit of `max` concurrent signing operations
+    pub fn new(max: usize) -> Self {
+        Self {
+            max,
+            in_flight: Arc::new(InFlight::default()),
+        }
+    }
+
+    /// Maximum number of concurrent signing operations
+    pub fn max(&self) -> usize {
+        self.max
+    }
+
+    /// Number of signing operations currently in progress
+    pub fn in_flight(&self) -> usize {
+        *self.in_flight.count.lock().unwrap()
+    }
+
+    /// Wait until a signing operation can start, returning a permit which
+    /// allows another to start once it's dropped
+    pub fn acquire(&self) -> Permit {
+        let mut count = self.in_flight.count.lock().unwrap();
+
+        if *count >= self.max {
+            debug!(
+                "waiting for one of {} in-flight signing operations to complete",
+                *count
+            );
+        }
+
+        while *count >= self.max {
+            count = self.in_flight.available.wait(count).unwrap();
+        }
+
+        *count += 1;
+        Permit(Arc::clone(&self.in_flight))
+    }
+
+    /// Like `acquire`, but gives up (returning `None`) if no signing
+    /// operation can start within the given timeout
+    pub fn acquire_timeout(&self, timeout: Duration) -> Option<Permit> {
+        let deadline = Instant::now() + timeout;
+        let mut count = self.in_flight.count.lock().unwrap();
+
+        if *count >= self.max {
+            debug!(
+                "waiting up to {} ms for one of {} in-flight signing operations to complete",
+                timeout.as_millis(),
+                *count
+            );
+        }
+
+        while *count >= self.max {
+            let now = Instant::now();
+
+            if now >= deadline {
+                return None;
+            }
+
+            count = self
+                .in_flight
+                .available
+                .wait_timeout(count, deadline - now)
+                .unwrap()
+                .0;
+        }
+
+        *count += 1;
+        Some(Permit(Arc::clone(&self.in_flight)))
+    }
+
+    /// Number of abandoned signing operations still holding permits
+    pub fn abandoned(&self) -> usize {
+        self.in_flight.abandoned.load(Ordering::SeqCst)
+    }
+
+    /// Note that a signing operation holding a permit was abandoned
+    pub fn record_abandoned(&self) {
+        self.in_flight.abandoned.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Note that an abandoned signing operation finally completed
+    pub fn record_abandoned_completed(&self) {
+        self.in_flight.abandoned.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Permission to perform a signing operation (see `ConcurrencyLimit`).
+///
+/// Permits can be moved to other threads, e.g. to sign in the background
+/// while waiting for a deadline.
+pub struct Permit(Arc<InFlight>);
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        *self.0.count.lock().unwrap() -= 1;
+        self.0.available.notify_one();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{sync::mpsc, thread};
+
+    #[test]
+    fn operations_beyond_the_limit_wait() {
+        let limit = ConcurrencyLimit::new(2);
+        let first = limit.acquire();
+        let _second = limit.acquire();
+        assert_eq!(limit.in_flight(), 2);
+
+        let (sender, receiver) = mpsc::channel();
+        let waiting_limit = limit.clone();
+
+        let waiter = thread::spawn(move || {
+            let _permit = waiting_limit.acquire();
+            sender.send(()).unwrap();
+        });
+
+        assert!(receiver.recv_timeout(Duration::from_millis(50)).is_err());
+
+        drop(first);
+        receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+        waiter.join().unwrap();
+
+        assert_eq!(limit.in_flight(), 1);
+    }
+
+    #[test]
+    fn waiting_can_time_out() {
+        let limit = ConcurrencyLimit::new(1);
+        let permit = limit.acquire();
+
+        let start = Instant::now();
+        assert!(limit.acquire_timeout(Duration::from_millis(50)).is_none());
+        assert!(start.elapsed() >= Duration::from_millis(50));
+
+        drop(permit);
+        assert!(limit.acquire_timeout(Duration::from_millis(50)).is_some());
+        assert_eq!(limit.in_flight(), 0);
+    }
+
+    #[test]
+    fn zero_is_rejected() {
+        assert!(ConcurrencyLimit::from_config(Some(0)).is_err());
+        assert!(ConcurrencyLimit::from_config(None).unwrap().is_none());
+    }
+}
@@ -2,8 +2,12 @@
 
 pub use signatory::ed25519::{PublicKey, Seed, Signature, PUBLIC_KEY_SIZE};
 
+#[cfg(feature = "exec")]
+pub mod exec;
 #[cfg(feature = "ledgertm")]
 pub mod ledgertm;
+#[cfg(feature = "remote")]
+pub mod remote;
 pub mod signer;
 #[cfg(feature = "softsign")]
 pub mod softsign;
@@ -0,0 +1,295 @@
+//! Signer which invokes an external command for each signature.
+//!
+//! This is an escape hatch for integrating signers which have no Rust SDK
+//! (e.g. in-house HSMs) using any scriptable tool. The message to sign is
+//! written to the command's stdin, and the command writes the signature to
+//! its stdout and exits successfully.
+//!
+//! Security tradeoffs: the KMS can't vouch for anything the command does.
+//! Anyone who can modify the command (or anything it executes) can obtain
+//! signatures over arbitrary messages, so it and its dependencies must be
+//! protected at least as well as the KMS configuration. The KMS does:
+//!
+//! - run the command with an empty environment (except a fixed `PATH`), so
+//!   secrets in the KMS's environment aren't inherited
+//! - kill the command if it doesn't finish within the configured timeout
+//! - bound the amount of output it reads from the command
+//! - verify every signature against the configured public key, so a
+//!   misbehaving command can't hand back a signature from another key
+//!
+//! Double-signing protection still happens in the KMS before the command is
+//! invoked, but the command itself sees (and could sign) anything.
+
+use super::{signer, Signer};
+use crate::{
+    chain,
+    config::provider::exec::{ExecConfig, SignatureEncoding},
+    error::{Error, ErrorKind::*},
+    keyring::{ConcurrencyLimit, SigningProvider},
+    prelude::*,
+};
+use signatory::{
+    ed25519,
+    signature::{self, Verifier},
+};
+use signatory_dalek::Ed25519Verifier;
+use std::{
+    ffi::OsString,
+    io::{self, Read, Write},
+    process::{Command, Stdio},
+    time::Duration,
+};
+use subtle_encoding::{base64, hex};
+use tendermint::TendermintKey;
+use wait_timeout::ChildExt;
+
+/// `PATH` the command is run with
+const PATH: &str = "/usr/local/bin:/usr/bin:/bin";
+
+/// Maximum number of bytes read from the command's stdout
+const MAX_OUTPUT_LEN: u64 = 1024;
+
+/// Maximum number of bytes of the command's stderr included in errors
+const MAX_STDERR_LEN: u64 = 256;
+
+/// Create external command signers from the given configuration
+pub fn init(chain_registry: &mut chain::Registry, configs: &[ExecConfig]) -> Result<(), Error> {
+    for config in configs {
+        if config.cmd.is_empty() {
+            fail!(ConfigError, "exec provider `cmd` must not be empty");
+        }
+
+        let public_key = parse_public_key(&config.public_key)?;
+
+        let provider = ExecSigner {
+            cmd: config.cmd.clone(),
+            verifier: Ed25519Verifier::from(&public_key),
+            encoding: config.signature_encoding,
+            timeout: Duration::from_millis(config.timeout_ms),
+        };
+
+        let signer = Signer::new(
+            SigningProvider::Exec,
+            TendermintKey::ConsensusKey(public_key.into()),
+            Box::new(provider),
+        )
+        .with_metadata(config.metadata.clone())
+        .with_concurrency_limit(ConcurrencyLimit::from_config(config.max_concurrent_signs)?);
+
+        for chain_id in &config.chain_ids {
+            chain_registry.add_to_keyring(chain_id, signer.clone())?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Parse the configured (hex) public key
+fn parse_public_key(public_key: &str) -> Result<ed25519::PublicKey, Error> {
+    hex::decode_upper(public_key)
+        .or_else(|_| hex::decode(public_key))
+        .ok()
+        .and_then(|bytes| ed25519::PublicKey::from_bytes(&bytes))
+        .ok_or_else(|| {
+            format_err!(
+                InvalidKey,
+                "invalid exec provider public_key {:?}: expected a hex Ed25519 key",
+                public_key
+            )
+            .into()
+        })
+}
+
+/// Signer which invokes an external command
+struct ExecSigner {
+    /// Command (with arguments) to invoke
+    cmd: Vec<OsString>,
+
+    /// Verifier for the configured public key
+    verifier: Ed25519Verifier,
+
+    /// Encoding of the signature written to stdout
+    encoding: SignatureEncoding,
+
+    /// Time the command has to produce a signature
+    timeout: Duration,
+}
+
+impl ExecSigner {
+    /// Run the command to sign the given message, returning its output
+    fn run(&self, msg: &[u8]) -> Result<Vec<u8>, String> {
+        let program = self.cmd[0].to_string_lossy();
+
+        let mut child = Command::new(&self.cmd[0])
+            .args(&self.cmd[1..])
+            .env_clear()
+            .env("PATH", PATH)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("couldn't run `{}`: {}", program, e))?;
+
+        // A command which exits without reading its input is reported by its
+        // exit status below, rather than as a broken pipe
+        match child.stdin.take().unwrap().write_all(msg) {
+            Err(e) if e.kind() != io::ErrorKind::BrokenPipe => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(format!("error writing to `{}`: {}", program, e));
+            }
+            _ => (),
+        }
+
+        let status = match child.wait_timeout(self.timeout) {
+            Ok(Some(status)) => status,
+            Ok(None) => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(format!(
+                    "`{}` timed out after {} ms",
+                    program,
+                    self.timeout.as_millis()
+                ));
+            }
+            Err(e) => return Err(format!("error waiting for `{}`: {}", program, e)),
+        };
+
+        if !status.success() {
+            let mut stderr = String::new();
+            let _ = child
+                .stderr
+                .take()
+                .unwrap()
+                .take(MAX_STDERR_LEN)
+                .read_to_string(&mut stderr);
+
+            return Err(format!(
+                "`{}` exited with {}: {}",
+                program,
+                status,
+                stderr.trim()
+            ));
+        }
+
+        let mut output = vec![];
+        child
+            .stdout
+            .take()
+            .unwrap()
+            .take(MAX_OUTPUT_LEN)
+            .read_to_end(&mut output)
+            .map_err(|e| format!("error reading from `{}`: {}", program, e))?;
+
+        Ok(output)
+    }
+
+    /// Decode the signature written to stdout
+    fn decode(&self, output: &[u8]) -> Result<Vec<u8>, String> {
+        let trimmed = || String::from_utf8_lossy(output).trim().to_owned();
+
+        match self.encoding {
+            SignatureEncoding::Raw => Ok(output.to_vec()),
+            SignatureEncoding::Hex => hex::decode_upper(trimmed())
+                .or_else(|_| hex::decode(trimmed()))
+                .map_err(|e| format!("invalid hex signature: {}", e)),
+            SignatureEncoding::Base64 => {
+                base64::decode(trimmed()).map_err(|e| format!("invalid base64 signature: {}", e))
+            }
+        }
+    }
+}
+
+impl signature::Signer<ed25519::Signature> for ExecSigner {
+    fn try_sign(&self, msg: &[u8]) -> Result<ed25519::Signature, signature::Error> {
+        let signature_bytes = self
+            .run(msg)
+            .and_then(|output| self.decode(&output))
+            .map_err(signature::Error::from_source)?;
+
+        let signature = signer::parse_signature(&signature_bytes)?;
+
+        self.verifier.verify(msg, &signature).map_err(|_| {
+            signature::Error::from_source("signature doesn't match the configured public_key")
+        })?;
+
+        Ok(signature)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use signatory::{public_key::PublicKeyed, signature::Signer as _};
+    use signatory_dalek::Ed25519Signer;
+
+    const MESSAGE: &[u8] = b"example message";
+
+    /// Create a signer which runs the given shell script, where `{sig}` is
+    /// replaced with the hex-encoded signature of `MESSAGE`
+    fn exec_signer(script: &str, timeout_ms: u64) -> Signer {
+        let key = Ed25519Signer::from(&ed25519::Seed::from_bytes([1u8; 32]).unwrap());
+        let public_key = key.public_key().unwrap();
+        let signature_hex = String::from_utf8(hex::encode(&key.sign(MESSAGE).to_bytes()[..]));
+
+        let provider = ExecSigner {
+            cmd: vec![
+                "sh".into(),
+                "-c".into(),
+                script.replace("{sig}", &signature_hex.unwrap()).into(),
+            ],
+            verifier: Ed25519Verifier::from(&public_key),
+            encoding: SignatureEncoding::Hex,
+            timeout: Duration::from_millis(timeout_ms),
+        };
+
+        Signer::new(
+            SigningProvider::Exec,
+            TendermintKey::ConsensusKey(public_key.into()),
+            Box::new(provider),
+        )
+    }
+
+    /// Sign `MESSAGE`, returning the error message
+    fn sign_err(signer: &Signer, msg: &[u8]) -> String {
+        let err = signer.sign(msg).unwrap_err();
+        assert_eq!(*err.kind(), SigningError);
+        err.to_string()
+    }
+
+    #[test]
+    fn signs_via_command() {
+        let signer = exec_signer("cat > /dev/null; echo {sig}", 5000);
+        assert!(signer.sign(MESSAGE).is_ok());
+
+        // signatures of anything else are rejected
+        let err = sign_err(&signer, b"other message");
+        assert!(err.contains("configured public_key"), "{}", err);
+    }
+
+    #[test]
+    fn environment_is_sanitized() {
+        std::env::set_var("TMKMS_EXEC_TEST_SECRET", "secret");
+
+        let script = format!(
+            "[ -z \"$TMKMS_EXEC_TEST_SECRET\" ] && [ \"$PATH\" = {} ] && echo {{sig}}",
+            PATH
+        );
+
+        assert!(exec_signer(&script, 5000).sign(MESSAGE).is_ok());
+    }
+
+    #[test]
+    fn failures_are_errors() {
+        let signer = exec_signer("echo 'HSM unavailable' >&2; exit 3", 5000);
+        let err = sign_err(&signer, MESSAGE);
+        assert!(err.contains("exit status: 3"), "{}", err);
+        assert!(err.contains("HSM unavailable"), "{}", err);
+
+        let err = sign_err(&exec_signer("sleep 5", 100), MESSAGE);
+        assert!(err.contains("timed out after 100 ms"), "{}", err);
+
+        let err = sign_err(&exec_signer("echo 0102", 5000), MESSAGE);
+        assert!(err.contains("got 2 bytes"), "{}", err);
+    }
+}
@@ -38,7 +38,8 @@ pub fn init(
         SigningProvider::LedgerTm,
         consensus_pubkey,
         Box::new(provider),
-    );
+    )
+    .with_metadata(ledgertm_configs[0].metadata.clone());
 
     for chain_id in &ledgertm_configs[0].chain_ids {
         chain_registry.add_to_keyring(chain_id, signer.clone())?;
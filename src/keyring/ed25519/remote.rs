@@ -0,0 +1,302 @@
+//! Signer which forwards requests to a remote KMS's sign server.
+//!
+//! This KMS keeps handling the validator connections and double-signing
+//! protection, while the signing keys stay on the remote KMS (see the
+//! `sign_server` module). Every signature the server returns is verified
+//! against the configured public key.
+
+use super::{signer, Signer};
+use crate::{
+    chain,
+    config::{provider::remote::RemoteConfig, validator::load_or_generate_secret_key},
+    connection::{secret_connection::SecretConnection, tcp::open_secret_connection},
+    error::{Error, ErrorKind::*},
+    keyring::{ConcurrencyLimit, SigningProvider},
+    prelude::*,
+    sign_server::{read_message, write_message, SignRequest, SignResponse},
+};
+use signatory::{
+    ed25519,
+    signature::{self, Verifier},
+};
+use signatory_dalek::Ed25519Verifier;
+use std::{net::TcpStream, sync::Mutex};
+use subtle_encoding::hex;
+use tendermint::{net, node, TendermintKey};
+
+/// Create remote signers from the given configuration
+pub fn init(chain_registry: &mut chain::Registry, configs: &[RemoteConfig]) -> Result<(), Error> {
+    for config in configs {
+        let (host, port, peer_id) = match &config.addr {
+            net::Address::Tcp {
+                peer_id: Some(peer_id),
+                host,
+                port,
+            } => (host.clone(), *port, *peer_id),
+            _ => fail!(
+                ConfigError,
+                "remote provider `addr` must be `tcp://<node_id>@<host>:<port>`: {}",
+                &config.addr
+            ),
+        };
+
+        let public_key = parse_public_key(&config.public_key)?;
+        let secret_key = load_or_generate_secret_key(&config.secret_key)?;
+        let concurrency_limit = ConcurrencyLimit::from_config(config.max_concurrent_signs)?;
+
+        // Requests identify the chain, so each chain gets its own signer
+        for chain_id in &config.chain_ids {
+            let provider = RemoteSigner {
+                chain_id: *chain_id,
+                host: host.clone(),
+                port,
+                peer_id,
+                secret_key: secret_key.clone(),
+                public_key,
+                timeout: config.timeout,
+                connection: Mutex::new(None),
+            };
+
+            let signer = Signer::new(
+                SigningProvider::Remote,
+                TendermintKey::ConsensusKey(public_key.into()),
+                Box::new(provider),
+            )
+            .with_metadata(config.metadata.clone())
+            .with_concurrency_limit(concurrency_limit.clone())
+            .with_reconnect();
+
+            chain_registry.add_to_keyring(chain_id, signer)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Parse the configured (hex) public key
+fn parse_public_key(public_key: &str) -> Result<ed25519::PublicKey, Error> {
+    hex::decode_upper(public_key)
+        .or_else(|_| hex::decode(public_key))
+        .ok()
+        .and_then(|bytes| ed25519::PublicKey::from_bytes(&bytes))
+        .ok_or_else(|| {
+            format_err!(
+                InvalidKey,
+                "invalid remote provider public_key {:?}: expected a hex Ed25519 key",
+                public_key
+            )
+            .into()
+        })
+}
+
+/// Signer which forwards requests to a sign server
+struct RemoteSigner {
+    /// Chain requests are made on behalf of
+    chain_id: chain::Id,
+
+    /// Host of the sign server
+    host: String,
+
+    /// Port of the sign server
+    port: u16,
+
+    /// Node ID of the sign server
+    peer_id: node::Id,
+
+    /// Our Secret Connection identity key
+    secret_key: ed25519::Seed,
+
+    /// Public key of the signing key held by the sign server
+    public_key: ed25519::PublicKey,
+
+    /// Read/write timeout of the connection, in seconds
+    timeout: Option<u16>,
+
+    /// Connection to the sign server (opened on first use, and reopened
+    /// after errors)
+    connection: Mutex<Option<SecretConnection<TcpStream>>>,
+}
+
+impl RemoteSigner {
+    /// Open a connection to the sign server
+    fn connect(&self) -> Result<SecretConnection<TcpStream>, Error> {
+        open_secret_connection(
+            &self.host,
+            self.port,
+            &self.secret_key,
+            &Some(self.peer_id),
+            self.timeout,
+            None,
+            None,
+            None,
+        )
+    }
+
+    /// Send a request over the given connection and read the response
+    fn request(
+        conn: &mut SecretConnection<TcpStream>,
+        request: &SignRequest,
+    ) -> Result<SignResponse, Error> {
+        write_message(conn, request)?;
+
+        read_message(conn)?
+            .ok_or_else(|| format_err!(IoError, "sign server closed the connection").into())
+    }
+
+    /// Sign the given message using the sign server, reconnecting (once) if
+    /// an existing connection has gone stale
+    fn sign_remotely(&self, msg: &[u8]) -> Result<Vec<u8>, Error> {
+        let request = SignRequest {
+            chain_id: self.chain_id.to_string(),
+            public_key: self.public_key.as_bytes().to_vec(),
+            msg: msg.to_vec(),
+        };
+
+        let mut connection = self.connection.lock().unwrap();
+
+        let (mut conn, reused) = match connection.take() {
+            Some(conn) => (conn, true),
+            None => (self.connect()?, false),
+        };
+
+        let response = match Self::request(&mut conn, &request) {
+            Ok(response) => response,
+            Err(e) if reused => {
+                debug!(
+                    "[{}] reconnecting to sign server {}:{}: {}",
+                    self.chain_id, self.host, self.port, e
+                );
+
+                conn = self.connect()?;
+                Self::request(&mut conn, &request)?
+            }
+            Err(e) => return Err(e),
+        };
+
+        *connection = Some(conn);
+
+        if !response.error.is_empty() {
+            fail!(
+                SigningError,
+                "sign server {}:{} refused request: {}",
+                self.host,
+                self.port,
+                response.error
+            );
+        }
+
+        Ok(response.signature)
+    }
+}
+
+impl signature::Signer<ed25519::Signature> for RemoteSigner {
+    fn try_sign(&self, msg: &[u8]) -> Result<ed25519::Signature, signature::Error> {
+        let signature_bytes = self
+            .sign_remotely(msg)
+            .map_err(|e| signature::Error::from_source(e.to_string()))?;
+
+        let signature = signer::parse_signature(&signature_bytes)?;
+
+        Ed25519Verifier::from(&self.public_key)
+            .verify(msg, &signature)
+            .map_err(|_| {
+                signature::Error::from_source("signature doesn't match the configured public_key")
+            })?;
+
+        Ok(signature)
+    }
+}
+
+#[cfg(all(test, feature = "softsign"))]
+mod tests {
+    use super::*;
+    use crate::{
+        chain::{state::State, Chain},
+        config::{chain::ChainConfig, sign_server::SignServerConfig},
+        connection::secret_connection::PublicKey,
+        keyring::SecretKeyEncoding,
+        sign_server,
+    };
+    use signatory::{encoding::Encode, public_key::PublicKeyed};
+    use signatory_dalek::Ed25519Signer;
+    use std::path::PathBuf;
+
+    /// Write a Secret Connection identity key to a file, returning its
+    /// node ID
+    fn identity_key(dir: &tempfile::TempDir, name: &str, seed: u8) -> (PathBuf, node::Id) {
+        let path = dir.path().join(name);
+        let seed = ed25519::Seed::from_bytes([seed; 32]).unwrap();
+        seed.encode_to_file(&path, &SecretKeyEncoding::default())
+            .unwrap();
+
+        let public_key = PublicKey::from(Ed25519Signer::from(&seed).public_key().unwrap());
+        (path, public_key.peer_id())
+    }
+
+    #[test]
+    fn signs_via_sign_server() {
+        let dir = tempfile::tempdir().unwrap();
+        let (server_key_path, server_id) = identity_key(&dir, "server.key", 1);
+        let (client_key_path, client_id) = identity_key(&dir, "client.key", 2);
+
+        // Register the signing key with a chain on the "backend"
+        let chain_id: chain::Id = "remote-provider-test".parse().unwrap();
+        let signing_key = Ed25519Signer::from(&ed25519::Seed::from_bytes([3u8; 32]).unwrap());
+        let public_key = signing_key.public_key().unwrap();
+
+        let chain_config: ChainConfig = toml::from_str(&format!(
+            "id = \"{}\"\nkey_format = {{ type = \"hex\" }}\nenabled = true\n",
+            chain_id
+        ))
+        .unwrap();
+
+        let mut chain = Chain::with_state(&chain_config, State::unpersisted()).unwrap();
+        chain
+            .keyring
+            .add(Signer::new(
+                SigningProvider::Remote,
+                TendermintKey::ConsensusKey(public_key.into()),
+                Box::new(signing_key),
+            ))
+            .unwrap();
+        chain::REGISTRY.register(chain).unwrap();
+
+        let server = sign_server::Server::new(&SignServerConfig {
+            laddr: "tcp://127.0.0.1:0".parse().unwrap(),
+            secret_key: server_key_path,
+            allowed_peer_ids: vec![client_id],
+            timeout: None,
+        })
+        .unwrap();
+
+        let port = server.local_addr().unwrap().port();
+        server.spawn().unwrap();
+
+        let remote_signer = |chain_id: &str| RemoteSigner {
+            chain_id: chain_id.parse().unwrap(),
+            host: "127.0.0.1".to_owned(),
+            port,
+            peer_id: server_id,
+            secret_key: load_or_generate_secret_key(&client_key_path).unwrap(),
+            public_key,
+            timeout: None,
+            connection: Mutex::new(None),
+        };
+
+        let provider = remote_signer(chain_id.as_str());
+        let signature = signature::Signer::try_sign(&provider, b"example").unwrap();
+        assert!(Ed25519Verifier::from(&public_key)
+            .verify(b"example", &signature)
+            .is_ok());
+
+        // The connection is reused for subsequent requests
+        assert!(signature::Signer::try_sign(&provider, b"another example").is_ok());
+
+        // Requests for chains the key isn't registered for are refused
+        let err = remote_signer("other-chain")
+            .sign_remotely(b"example")
+            .unwrap_err();
+        assert_eq!(*err.kind(), SigningError);
+        assert!(err.to_string().contains("isn't registered"), "{}", err);
+    }
+}
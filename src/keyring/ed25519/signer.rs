@@ -2,13 +2,26 @@
 
 use crate::{
     error::{Error, ErrorKind::*},
-    keyring::SigningProvider,
+    keyring::{
+        attestation::{Attestation, AttestationHook},
+        concurrency::Permit,
+        Capabilities, ConcurrencyLimit, Metadata, SigningAlgorithm, SigningProvider,
+    },
     prelude::*,
 };
-use signatory::{ed25519::Signature, signature};
-use std::sync::Arc;
+use signatory::{
+    ed25519::{self, Signature},
+    signature,
+};
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
 use tendermint::TendermintKey;
 
+/// Provider-specific hook which fills a buffer with random bytes
+pub type RandomBytesHook = Arc<dyn Fn(&mut [u8]) -> Result<(), Error> + Send + Sync>;
+
 /// Trait object wrapper for an Ed25519 signers
 #[derive(Clone)]
 pub struct Signer {
@@ -18,8 +31,31 @@ pub struct Signer {
     /// Tendermint public key
     public_key: TendermintKey,
 
+    /// Signature algorithm used by this signer
+    algorithm: SigningAlgorithm,
+
     /// Signer trait object
     signer: Arc<Box<dyn signature::Signer<Signature> + Send + Sync>>,
+
+    /// Operator-defined metadata for this key
+    metadata: Metadata,
+
+    /// Hook for fetching an attestation of this key (if supported)
+    attestation: Option<AttestationHook>,
+
+    /// Hook for generating random bytes using the provider (if supported)
+    random_bytes: Option<RandomBytesHook>,
+
+    /// Does the provider reconnect to its backend after connection errors?
+    reconnect: bool,
+
+    /// Limit on concurrent signing operations, shared with the other signers
+    /// from the same provider (if configured)
+    concurrency_limit: Option<ConcurrencyLimit>,
+
+    /// Number of signing operations which missed the keyring's deadline and
+    /// are still running in the background
+    stalled: Arc<AtomicUsize>,
 }
 
 impl Signer {
@@ -32,25 +68,296 @@ impl Signer {
         Self {
             provider,
             public_key,
+            algorithm: SigningAlgorithm::Ed25519,
             signer: Arc::new(signer),
+            metadata: Metadata::default(),
+            attestation: None,
+            random_bytes: None,
+            reconnect: false,
+            concurrency_limit: None,
+            stalled: Arc::new(AtomicUsize::new(0)),
         }
     }
 
-    /// Get the Tendermint public key for this signer
+    /// Create a new signer which produces Ed25519ph signatures.
+    ///
+    /// The provided signer must compute the SHA-512 prehash of the messages
+    /// it's given itself. Ed25519ph signers are always registered as account
+    /// keys and are never used to sign consensus messages.
+    pub fn new_ed25519ph(
+        provider: SigningProvider,
+        public_key: ed25519::PublicKey,
+        signer: Box<dyn signature::Signer<Signature> + Send + Sync>,
+    ) -> Self {
+        Self {
+            provider,
+            public_key: TendermintKey::AccountKey(public_key.into()),
+            algorithm: SigningAlgorithm::Ed25519ph,
+            signer: Arc::new(signer),
+            metadata: Metadata::default(),
+            attestation: None,
+            random_bytes: None,
+            reconnect: false,
+            concurrency_limit: None,
+            stalled: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// Get the Tendermint public key for this signer. This is derived when
+    /// the provider is loaded, so it never involves a round trip to an HSM.
     pub fn public_key(&self) -> TendermintKey {
         self.public_key
     }
 
+    /// Get the raw bytes of this signer's public key: 32 bytes for Ed25519,
+    /// or 33 bytes (compressed) for secp256k1
+    pub fn public_key_bytes(&self) -> Vec<u8> {
+        self.public_key.as_bytes()
+    }
+
     /// Get the provider for this signer
     pub fn provider(&self) -> SigningProvider {
         self.provider
     }
 
-    /// Sign the given message using this signer
+    /// Get the signature algorithm used by this signer
+    pub fn algorithm(&self) -> SigningAlgorithm {
+        self.algorithm
+    }
+
+    /// Attach operator-defined metadata to this key
+    pub fn with_metadata(mut self, metadata: Metadata) -> Self {
+        self.metadata = metadata;
+        self
+    }
+
+    /// Get the operator-defined metadata for this key
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    /// Set a provider-specific hook for fetching an attestation of this key
+    pub fn with_attestation<F>(mut self, hook: F) -> Self
+    where
+        F: Fn() -> Result<Attestation, Error> + Send + Sync + 'static,
+    {
+        self.attestation = Some(Arc::new(hook));
+        self
+    }
+
+    /// Fetch a fresh attestation of this key from its provider. Returns
+    /// `None` if the provider doesn't support attestation.
+    pub fn attestation(&self) -> Option<Result<Attestation, Error>> {
+        self.attestation.as_ref().map(|hook| hook())
+    }
+
+    /// Set a provider-specific hook for generating random bytes (e.g. using
+    /// an HSM's RNG)
+    pub fn with_random_bytes<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&mut [u8]) -> Result<(), Error> + Send + Sync + 'static,
+    {
+        self.random_bytes = Some(Arc::new(hook));
+        self
+    }
+
+    /// Fill the given buffer with random bytes from this signer's provider.
+    /// Returns `None` if the provider doesn't support generating randomness.
+    pub fn random_bytes(&self, buffer: &mut [u8]) -> Option<Result<(), Error>> {
+        self.random_bytes.as_ref().map(|hook| hook(buffer))
+    }
+
+    /// Declare that the provider transparently reconnects to its backend
+    /// (e.g. an HSM or sign server) after connection errors
+    pub fn with_reconnect(mut self) -> Self {
+        self.reconnect = true;
+        self
+    }
+
+    /// Limit concurrent signing operations, sharing the limit with the other
+    /// signers it's set for (if any)
+    pub fn with_concurrency_limit(mut self, limit: Option<ConcurrencyLimit>) -> Self {
+        self.concurrency_limit = limit;
+        self
+    }
+
+    /// Get the limit on concurrent signing operations (if any)
+    pub fn concurrency_limit(&self) -> Option<&ConcurrencyLimit> {
+        self.concurrency_limit.as_ref()
+    }
+
+    /// Wait until the concurrency limit (if any) allows another signing
+    /// operation, returning a permit to pass to `sign_with_permit`
+    pub fn acquire_permit(&self) -> Option<Permit> {
+        self.concurrency_limit
+            .as_ref()
+            .map(ConcurrencyLimit::acquire)
+    }
+
+    /// Number of this signer's signing operations which missed the keyring's
+    /// deadline and are still running in the background
+    pub fn stalled(&self) -> usize {
+        self.stalled.load(Ordering::SeqCst)
+    }
+
+    /// Note that a signing operation missed the keyring's deadline (its
+    /// permit, if any, is counted as abandoned by the concurrency limit)
+    pub fn record_stall(&self) {
+        self.stalled.fetch_add(1, Ordering::SeqCst);
+
+        if let Some(limit) = &self.concurrency_limit {
+            limit.record_abandoned();
+        }
+    }
+
+    /// Note that a stalled signing operation finally completed
+    pub fn record_stall_completed(&self) {
+        self.stalled.fetch_sub(1, Ordering::SeqCst);
+
+        if let Some(limit) = &self.concurrency_limit {
+            limit.record_abandoned_completed();
+        }
+    }
+
+    /// Get the optional capabilities supported by this signer
+    pub fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            attestation: self.attestation.is_some(),
+            random_bytes: self.random_bytes.is_some(),
+            prehash: self.algorithm == SigningAlgorithm::Ed25519ph,
+            reconnect: self.reconnect,
+        }
+    }
+
+    /// Sign the given message using this signer, waiting for the
+    /// concurrency limit (if any) to allow it first
     pub fn sign(&self, msg: &[u8]) -> Result<Signature, Error> {
-        Ok(self
-            .signer
-            .try_sign(msg)
-            .map_err(|e| format_err!(SigningError, "{}", e))?)
+        self.sign_with_permit(msg, self.acquire_permit())
+    }
+
+    /// Sign the given message using this signer, with a permit already
+    /// acquired from `acquire_permit` (which is released afterwards)
+    pub fn sign_with_permit(
+        &self,
+        msg: &[u8],
+        _permit: Option<Permit>,
+    ) -> Result<Signature, Error> {
+        Ok(self.signer.try_sign(msg).map_err(|e| {
+            let reason = std::error::Error::source(&e)
+                .map(ToString::to_string)
+                .unwrap_or_else(|| e.to_string());
+
+            format_err!(SigningError, "{} provider: {}", self.provider, reason)
+        })?)
+    }
+}
+
+/// Parse the raw bytes of a signature returned by a provider, rejecting
+/// signatures of the wrong length (e.g. from a buggy HSM) with an error
+/// rather than a panic
+pub fn parse_signature(bytes: &[u8]) -> Result<Signature, signature::Error> {
+    if bytes.len() != ed25519::SIGNATURE_SIZE {
+        return Err(signature::Error::from_source(format!(
+            "expected a {}-byte signature, got {} bytes",
+            ed25519::SIGNATURE_SIZE,
+            bytes.len()
+        )));
+    }
+
+    signature::Signature::from_bytes(bytes)
+}
+
+#[cfg(all(test, feature = "softsign"))]
+mod tests {
+    use super::*;
+    use signatory::public_key::PublicKeyed;
+    use signatory_dalek::Ed25519Signer;
+    use subtle_encoding::hex;
+    use tendermint::PublicKey;
+
+    /// Example secp256k1 public key (compressed generator point)
+    const SECP256K1_KEY: &str =
+        "0279BE667EF9DCBBAC55A06295CE870B07029BFCDB2DCE28D959F2815B16F81798";
+
+    fn example_signer(public_key: Option<PublicKey>) -> Signer {
+        let provider = Ed25519Signer::from(&ed25519::Seed::from_bytes([1u8; 32]).unwrap());
+        let public_key = public_key.unwrap_or_else(|| provider.public_key().unwrap().into());
+
+        Signer::new(
+            SigningProvider::SoftSign,
+            TendermintKey::AccountKey(public_key),
+            Box::new(provider),
+        )
+    }
+
+    /// Provider which returns a fixed (possibly malformed) signature
+    struct RawSigner(Vec<u8>);
+
+    impl signature::Signer<Signature> for RawSigner {
+        fn try_sign(&self, _msg: &[u8]) -> Result<Signature, signature::Error> {
+            parse_signature(&self.0)
+        }
+    }
+
+    #[test]
+    fn wrong_signature_length_is_an_error() {
+        let public_key = *example_signer(None).public_key();
+
+        for &len in &[ed25519::SIGNATURE_SIZE - 1, ed25519::SIGNATURE_SIZE + 1] {
+            let signer = Signer::new(
+                SigningProvider::SoftSign,
+                TendermintKey::AccountKey(public_key),
+                Box::new(RawSigner(vec![0; len])),
+            );
+
+            let err = signer.sign(b"example").unwrap_err();
+            assert_eq!(*err.kind(), SigningError);
+
+            let message = err.to_string();
+            assert!(message.contains("softsign"), "{}", message);
+            assert!(
+                message.contains(&format!("got {} bytes", len)),
+                "{}",
+                message
+            );
+        }
+    }
+
+    #[test]
+    fn capabilities_reflect_hooks() {
+        let signer = example_signer(None);
+        assert_eq!(signer.capabilities(), Capabilities::default());
+
+        let signer = signer
+            .with_attestation(|| Ok(Attestation::new("example", vec![])))
+            .with_reconnect();
+
+        let capabilities = signer.capabilities();
+        assert!(capabilities.attestation);
+        assert!(capabilities.reconnect);
+        assert!(!capabilities.random_bytes);
+        assert!(!capabilities.prehash);
+    }
+
+    #[test]
+    fn ed25519_public_key_bytes() {
+        let signer = example_signer(None);
+        let bytes = signer.public_key_bytes();
+
+        assert_eq!(bytes.len(), ed25519::PUBLIC_KEY_SIZE);
+        assert_eq!(
+            PublicKey::from_raw_ed25519(&bytes),
+            Some(*signer.public_key())
+        );
+    }
+
+    #[test]
+    fn secp256k1_public_key_bytes() {
+        let public_key =
+            PublicKey::from_raw_secp256k1(&hex::decode_upper(SECP256K1_KEY).unwrap()).unwrap();
+        let bytes = example_signer(Some(public_key)).public_key_bytes();
+
+        assert_eq!(bytes.len(), 33);
+        assert_eq!(PublicKey::from_raw_secp256k1(&bytes), Some(public_key));
     }
 }
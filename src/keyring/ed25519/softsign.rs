@@ -2,44 +2,199 @@
 //!
 //! This is mainly intended for testing/CI. Ideally real validators will use HSMs
 
-use super::Signer;
+use super::{signer, Signer};
 use crate::{
     chain,
-    config::provider::softsign::{KeyFormat, SoftsignConfig},
+    config::provider::softsign::{KeyFormat, KeyType, SoftsignConfig, DEFAULT_KEY_PATTERN},
     error::{Error, ErrorKind::*},
     keyring::{SecretKeyEncoding, SigningProvider},
     prelude::*,
 };
-use signatory::{ed25519, encoding::Decode, public_key::PublicKeyed};
+use sha2::{Digest, Sha512};
+use signatory::{ed25519, encoding::Decode, public_key::PublicKeyed, signature};
 use signatory_dalek::Ed25519Signer;
-use std::{fs, process};
+use std::{fs, path::Path};
 use tendermint::{config::PrivValidatorKey, PrivateKey, TendermintKey};
 
 /// Create software-backed Ed25519 signer objects from the given configuration
+///
+/// Each `[[providers.softsign]]` entry loads one key, which is added to the
+/// keyrings of all of the chains in its `chain_ids`.
 pub fn init(chain_registry: &mut chain::Registry, configs: &[SoftsignConfig]) -> Result<(), Error> {
-    if configs.is_empty() {
-        return Ok(());
+    for config in configs {
+        add_key(chain_registry, config)?;
     }
 
-    // TODO(tarcieri): support for multiple softsign keys?
-    if configs.len() != 1 {
+    Ok(())
+}
+
+/// Load the key(s) for a single softsign entry and add them to their chains'
+/// keyrings
+fn add_key(chain_registry: &mut chain::Registry, config: &SoftsignConfig) -> Result<(), Error> {
+    match (&config.path, &config.key_dir) {
+        (Some(path), None) => {
+            add_key_file(chain_registry, config, path.as_ref(), &config.chain_ids)
+        }
+        (None, Some(key_dir)) => add_key_dir(chain_registry, config, key_dir),
+        _ => fail!(
+            ConfigError,
+            "softsign keys must be configured with exactly one of `path` or `key_dir`"
+        ),
+    }
+}
+
+/// Load every key in `key_dir` whose filename matches the configured pattern,
+/// adding each to the keyring of the chain named by its filename
+fn add_key_dir(
+    chain_registry: &mut chain::Registry,
+    config: &SoftsignConfig,
+    key_dir: &Path,
+) -> Result<(), Error> {
+    if !config.chain_ids.is_empty() {
+        fail!(
+            ConfigError,
+            "softsign `key_dir` {}: chain IDs are taken from key filenames; remove `chain_ids`",
+            key_dir.display()
+        );
+    }
+
+    let pattern = config
+        .key_pattern
+        .as_ref()
+        .map(AsRef::as_ref)
+        .unwrap_or(DEFAULT_KEY_PATTERN);
+
+    if pattern.matches('*').count() != 1 {
+        fail!(
+            ConfigError,
+            "softsign `key_pattern` must contain exactly one `*`: {:?}",
+            pattern
+        );
+    }
+
+    let mut key_files = vec![];
+
+    for entry in fs::read_dir(key_dir).map_err(|e| {
+        format_err!(
+            ConfigError,
+            "couldn't read softsign key_dir {}: {}",
+            key_dir.display(),
+            e
+        )
+    })? {
+        let path = entry?.path();
+
+        if !path.is_file() {
+            continue;
+        }
+
+        let chain_id = match path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| match_key_pattern(pattern, name))
+        {
+            Some(chain_id) => chain_id.parse::<chain::Id>().map_err(|e| {
+                format_err!(
+                    ConfigError,
+                    "invalid chain ID in softsign key filename {}: {}",
+                    path.display(),
+                    e
+                )
+            })?,
+            None => continue,
+        };
+
+        key_files.push((path, chain_id));
+    }
+
+    if key_files.is_empty() {
         fail!(
             ConfigError,
-            "expected one [softsign.provider] in config, found: {}",
-            configs.len()
+            "no softsign keys matching {:?} found in {}",
+            pattern,
+            key_dir.display()
         );
     }
 
-    let config = &configs[0];
+    // Load keys in a deterministic order
+    key_files.sort();
+
+    for (path, chain_id) in &key_files {
+        add_key_file(chain_registry, config, path, &[*chain_id])?;
+    }
+
+    Ok(())
+}
+
+/// Get the part of `file_name` matched by the single `*` in `pattern`
+fn match_key_pattern<'a>(pattern: &str, file_name: &'a str) -> Option<&'a str> {
+    let wildcard = pattern.find('*')?;
+    let (prefix, suffix) = (&pattern[..wildcard], &pattern[(wildcard + 1)..]);
+
+    if file_name.len() > prefix.len() + suffix.len()
+        && file_name.starts_with(prefix)
+        && file_name.ends_with(suffix)
+    {
+        Some(&file_name[prefix.len()..(file_name.len() - suffix.len())])
+    } else {
+        None
+    }
+}
+
+/// Load the key in the given file and add it to the given chains' keyrings
+fn add_key_file(
+    chain_registry: &mut chain::Registry,
+    config: &SoftsignConfig,
+    path: &Path,
+    chain_ids: &[chain::Id],
+) -> Result<(), Error> {
     let key_format = config.key_format.as_ref().cloned().unwrap_or_default();
+    let seed = load_seed(path, key_format)?;
+
+    let provider = Ed25519Signer::from(&seed);
+    let public_key = provider.public_key().map_err(|_| Error::from(InvalidKey))?;
 
+    let signer = if config.prehash {
+        if config.key_type == KeyType::Consensus {
+            fail!(
+                ConfigError,
+                "softsign key {}: prehash keys must have `key_type = \"account\"`",
+                path.display()
+            );
+        }
+
+        Signer::new_ed25519ph(
+            SigningProvider::SoftSign,
+            public_key,
+            Box::new(Ed25519phSigner::from(&seed)),
+        )
+    } else {
+        let public_key = match config.key_type {
+            KeyType::Account => TendermintKey::AccountKey(public_key.into()),
+            KeyType::Consensus => TendermintKey::ConsensusKey(public_key.into()),
+        };
+
+        Signer::new(SigningProvider::SoftSign, public_key, Box::new(provider))
+    };
+
+    let signer = signer.with_metadata(config.metadata.clone());
+
+    for chain_id in chain_ids {
+        chain_registry.add_to_keyring(chain_id, signer.clone())?;
+    }
+
+    Ok(())
+}
+
+/// Load an Ed25519 seed from a softsign key file in the given format
+pub fn load_seed(path: &Path, key_format: KeyFormat) -> Result<ed25519::Seed, Error> {
     let seed = match key_format {
         KeyFormat::Base64 => {
-            let base64 = fs::read_to_string(&config.path).map_err(|e| {
+            let base64 = fs::read_to_string(path).map_err(|e| {
                 format_err!(
                     ConfigError,
                     "couldn't read key from {}: {}",
-                    &config.path.as_ref().display(),
+                    path.display(),
                     e
                 )
             })?;
@@ -52,18 +207,18 @@ pub fn init(chain_registry: &mut chain::Registry, configs: &[SoftsignConfig]) ->
                     format_err!(
                         ConfigError,
                         "can't decode key from {}: {}",
-                        config.path.as_ref().display(),
+                        path.display(),
                         e
                     )
                 },
             )?
         }
         KeyFormat::Raw => {
-            let bytes = fs::read(&config.path).map_err(|e| {
+            let bytes = fs::read(path).map_err(|e| {
                 format_err!(
                     ConfigError,
                     "couldn't read key from {}: {}",
-                    &config.path.as_ref().display(),
+                    path.display(),
                     e
                 )
             })?;
@@ -72,16 +227,13 @@ pub fn init(chain_registry: &mut chain::Registry, configs: &[SoftsignConfig]) ->
                 format_err!(
                     ConfigError,
                     "malformed 'raw' softsign key: {}",
-                    config.path.as_ref().display(),
+                    path.display(),
                 )
             })?
         }
         KeyFormat::Json => {
-            let private_key = PrivValidatorKey::load_json_file(&config.path)
-                .unwrap_or_else(|e| {
-                    status_err!("couldn't load {}: {}", config.path.as_ref().display(), e);
-                    process::exit(1);
-                })
+            let private_key = PrivValidatorKey::load_json_file(&path)
+                .map_err(|e| format_err!(ConfigError, "couldn't load {}: {}", path.display(), e))?
                 .priv_key;
 
             match private_key {
@@ -93,21 +245,121 @@ pub fn init(chain_registry: &mut chain::Registry, configs: &[SoftsignConfig]) ->
         }
     };
 
-    let provider = Ed25519Signer::from(&seed);
-    let public_key = provider.public_key().map_err(|_| Error::from(InvalidKey))?;
+    Ok(seed)
+}
 
-    // TODO(tarcieri): support for adding account keys into keyrings
-    let consensus_pubkey = TendermintKey::ConsensusKey(public_key.into());
+/// Ed25519ph signer: signs the SHA-512 prehash of messages (RFC 8032 5.1)
+pub struct Ed25519phSigner(ed25519_dalek::Keypair);
 
-    let signer = Signer::new(
-        SigningProvider::SoftSign,
-        consensus_pubkey,
-        Box::new(provider),
-    );
+impl<'a> From<&'a ed25519::Seed> for Ed25519phSigner {
+    fn from(seed: &'a ed25519::Seed) -> Self {
+        let secret = ed25519_dalek::SecretKey::from_bytes(seed.as_secret_slice()).unwrap();
+        let public = ed25519_dalek::PublicKey::from(&secret);
+        Ed25519phSigner(ed25519_dalek::Keypair { secret, public })
+    }
+}
 
-    for chain_id in &config.chain_ids {
-        chain_registry.add_to_keyring(chain_id, signer.clone())?;
+impl signature::Signer<ed25519::Signature> for Ed25519phSigner {
+    fn try_sign(&self, msg: &[u8]) -> Result<ed25519::Signature, signature::Error> {
+        let mut prehash = Sha512::default();
+        prehash.input(msg);
+
+        let signature = self.0.sign_prehashed(prehash, None);
+        signer::parse_signature(&signature.to_bytes())
     }
+}
 
-    Ok(())
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keyring::{self, KeyRing};
+    use signatory::signature::Signer as _;
+    use subtle_encoding::hex;
+
+    /// Ed25519ph test vector from RFC 8032 Section 7.3 ("TEST abc")
+    const RFC8032_PH_SECRET_KEY: &str =
+        "833fe62409237b9d62ec77587520911e9a759cec1d19755b7da901b96dca3d42";
+    const RFC8032_PH_PUBLIC_KEY: &str =
+        "ec172b93ad5e563bf4932c70e1245034c35467ef2efd4d64ebf819683467e2bf";
+    const RFC8032_PH_MESSAGE: &[u8] = b"abc";
+    const RFC8032_PH_SIGNATURE: &str =
+        "98a70222f0b8121aa9d30f813d683f809e462b469c7ff87639499bb94e6dae41\
+         31f85042463c2a355a2003d062adf5aaa10b8c61e636062aaad11c2a26083406";
+
+    #[test]
+    fn ed25519ph_rfc8032_test_vector() {
+        let seed = ed25519::Seed::from_bytes(hex::decode(RFC8032_PH_SECRET_KEY).unwrap()).unwrap();
+        let signer = Ed25519phSigner::from(&seed);
+
+        assert_eq!(
+            signer.0.public.as_bytes()[..],
+            hex::decode(RFC8032_PH_PUBLIC_KEY).unwrap()[..]
+        );
+
+        let signature = signer.sign(RFC8032_PH_MESSAGE);
+        assert_eq!(
+            signature.as_ref(),
+            &hex::decode(RFC8032_PH_SIGNATURE).unwrap()[..]
+        );
+    }
+
+    #[test]
+    fn ed25519ph_differs_from_ed25519() {
+        let seed = ed25519::Seed::from_bytes(hex::decode(RFC8032_PH_SECRET_KEY).unwrap()).unwrap();
+        let ph_signature = Ed25519phSigner::from(&seed).sign(RFC8032_PH_MESSAGE);
+        let signature = Ed25519Signer::from(&seed).sign(RFC8032_PH_MESSAGE);
+        assert_ne!(ph_signature.as_ref(), signature.as_ref());
+    }
+
+    #[test]
+    fn ed25519ph_keys_never_sign_consensus_messages() {
+        let seed = ed25519::Seed::from_bytes(hex::decode(RFC8032_PH_SECRET_KEY).unwrap()).unwrap();
+        let public_key = Ed25519Signer::from(&seed).public_key().unwrap();
+        let signer = Signer::new_ed25519ph(
+            SigningProvider::SoftSign,
+            public_key,
+            Box::new(Ed25519phSigner::from(&seed)),
+        );
+        let ph_pubkey = signer.public_key();
+
+        let mut keyring = KeyRing::new(keyring::Format::Hex);
+        keyring.add(signer).unwrap();
+
+        assert!(keyring.sign_ed25519(None, RFC8032_PH_MESSAGE).is_err());
+        assert!(keyring
+            .sign_ed25519(Some(&ph_pubkey), RFC8032_PH_MESSAGE)
+            .is_err());
+        assert!(keyring
+            .sign_ed25519ph(&ph_pubkey, RFC8032_PH_MESSAGE)
+            .is_ok());
+    }
+
+    #[test]
+    fn account_keys_are_not_default_consensus_keys() {
+        let seed = ed25519::Seed::from_bytes(hex::decode(RFC8032_PH_SECRET_KEY).unwrap()).unwrap();
+        let provider = Ed25519Signer::from(&seed);
+        let account_key = TendermintKey::AccountKey(provider.public_key().unwrap().into());
+        let signer = Signer::new(SigningProvider::SoftSign, account_key, Box::new(provider));
+
+        let mut keyring = KeyRing::new(keyring::Format::Hex);
+        keyring.add(signer).unwrap();
+
+        assert!(keyring.default_pubkey().is_err());
+        assert!(keyring.sign_ed25519(None, b"example").is_err());
+        assert!(keyring.sign_ed25519(Some(&account_key), b"example").is_ok());
+    }
+
+    #[test]
+    fn key_pattern_matching() {
+        assert_eq!(
+            match_key_pattern("*.key", "cosmoshub-3.key"),
+            Some("cosmoshub-3")
+        );
+        assert_eq!(
+            match_key_pattern("validator-*.json", "validator-irishub.json"),
+            Some("irishub")
+        );
+        assert_eq!(match_key_pattern("*.key", "cosmoshub-3.json"), None);
+        assert_eq!(match_key_pattern("*.key", ".key"), None);
+    }
 }
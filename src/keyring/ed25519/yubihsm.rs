@@ -4,7 +4,7 @@ use crate::{
     chain,
     config::provider::yubihsm::YubihsmConfig,
     error::{Error, ErrorKind::*},
-    keyring::{ed25519::Signer, SigningProvider},
+    keyring::{ed25519::Signer, Attestation, ConcurrencyLimit, SigningProvider},
     prelude::*,
 };
 use signatory::public_key::PublicKeyed;
@@ -28,6 +28,8 @@ pub fn init(
         );
     }
 
+    let concurrency_limit = ConcurrencyLimit::from_config(yubihsm_configs[0].max_concurrent_signs)?;
+
     for config in &yubihsm_configs[0].keys {
         let signer = yubihsm::ed25519::Signer::create(crate::yubihsm::client().clone(), config.key)
             .map_err(|_| {
@@ -48,7 +50,48 @@ pub fn init(
         // TODO(tarcieri): support for adding account keys into keyrings
         let consensus_pubkey = TendermintKey::ConsensusKey(public_key.into());
 
-        let signer = Signer::new(SigningProvider::Yubihsm, consensus_pubkey, Box::new(signer));
+        let key_id = config.key;
+        let signer = Signer::new(SigningProvider::Yubihsm, consensus_pubkey, Box::new(signer))
+            .with_metadata(config.metadata.clone())
+            .with_concurrency_limit(concurrency_limit.clone())
+            .with_reconnect()
+            .with_attestation(move || {
+                let certificate = crate::yubihsm::client()
+                    .sign_attestation_certificate(key_id, None)
+                    .map_err(|e| {
+                        format_err!(
+                            YubihsmError,
+                            "couldn't attest YubiHSM key ID 0x{:04x}: {}",
+                            key_id,
+                            e
+                        )
+                    })?;
+
+                Ok(Attestation::new("x509-der", certificate.into_vec()))
+            })
+            .with_random_bytes(|buffer| {
+                let bytes = crate::yubihsm::client()
+                    .get_pseudo_random(buffer.len())
+                    .map_err(|e| {
+                        format_err!(
+                            YubihsmError,
+                            "couldn't get random bytes from YubiHSM: {}",
+                            e
+                        )
+                    })?;
+
+                if bytes.len() != buffer.len() {
+                    fail!(
+                        YubihsmError,
+                        "YubiHSM returned {} random bytes (expected {})",
+                        bytes.len(),
+                        buffer.len()
+                    );
+                }
+
+                buffer.copy_from_slice(&bytes);
+                Ok(())
+            });
 
         for chain_id in &config.chain_ids {
             chain_registry.add_to_keyring(chain_id, signer.clone())?;
@@ -1,7 +1,15 @@
 //! Chain-specific key configuration
 
+use crate::{
+    error::{Error, ErrorKind::*},
+    prelude::*,
+};
 use serde::Deserialize;
-use tendermint::TendermintKey;
+use subtle_encoding::{bech32, hex};
+use tendermint::{account, PublicKey, TendermintKey};
+
+/// Amino prefix of Ed25519 public keys
+const ED25519_AMINO_PREFIX: [u8; 5] = [0x16, 0x24, 0xde, 0x64, 0x20];
 
 /// Options for how keys for this chain are represented
 #[derive(Clone, Debug, Deserialize)]
@@ -23,7 +31,10 @@ pub enum Format {
 }
 
 impl Format {
-    /// Serialize a `TendermintKey` according to chain-specific rules
+    /// Serialize a `TendermintKey` according to chain-specific rules.
+    ///
+    /// This is infallible for every `TendermintKey` variant and key type, so it
+    /// is safe to use when constructing log messages and errors.
     pub fn serialize(&self, public_key: TendermintKey) -> String {
         match self {
             Format::Bech32 {
@@ -36,4 +47,169 @@ impl Format {
             Format::Hex => public_key.to_hex(),
         }
     }
+
+    /// Serialize the address derived from a `TendermintKey` (e.g. the
+    /// consensus address of a validator) according to chain-specific rules.
+    ///
+    /// Bech32 addresses use the key's prefix without its `pub` suffix, per
+    /// the Cosmos SDK convention (e.g. `cosmosvalconspub` keys have
+    /// `cosmosvalcons` addresses). Otherwise, they're upper-case hex like
+    /// Tendermint displays them. This is infallible, like `serialize`.
+    pub fn serialize_address(&self, public_key: TendermintKey) -> String {
+        let address = match *public_key {
+            PublicKey::Ed25519(pk) => account::Id::from(pk),
+            PublicKey::Secp256k1(pk) => account::Id::from(pk),
+        };
+
+        let key_prefix = match self {
+            Format::Bech32 {
+                account_key_prefix,
+                consensus_key_prefix,
+            } => match public_key {
+                TendermintKey::AccountKey(_) => Some(account_key_prefix),
+                TendermintKey::ConsensusKey(_) => Some(consensus_key_prefix),
+            },
+            Format::Hex => None,
+        };
+
+        match key_prefix {
+            Some(prefix) if prefix.len() > 3 && prefix.ends_with("pub") => {
+                bech32::encode(&prefix[..prefix.len() - 3], address.as_bytes())
+            }
+            _ => address.to_string(),
+        }
+    }
+
+    /// Parse an Ed25519 consensus key serialized according to chain-specific
+    /// rules (i.e. the inverse of `serialize`)
+    pub fn parse_consensus_key(&self, serialized: &str) -> Result<TendermintKey, Error> {
+        let amino_bytes = match self {
+            Format::Bech32 {
+                consensus_key_prefix,
+                ..
+            } => {
+                let (hrp, data) = bech32::decode(serialized)
+                    .map_err(|e| format_err!(InvalidKey, "invalid key {}: {}", serialized, e))?;
+
+                if &hrp != consensus_key_prefix {
+                    fail!(
+                        InvalidKey,
+                        "invalid key {}: expected prefix {}",
+                        serialized,
+                        consensus_key_prefix
+                    );
+                }
+
+                data
+            }
+            Format::Hex => hex::decode_upper(serialized)
+                .or_else(|_| hex::decode(serialized))
+                .map_err(|e| format_err!(InvalidKey, "invalid key {}: {}", serialized, e))?,
+        };
+
+        if !amino_bytes.starts_with(&ED25519_AMINO_PREFIX) {
+            fail!(InvalidKey, "invalid key {}: not an Ed25519 key", serialized);
+        }
+
+        PublicKey::from_raw_ed25519(&amino_bytes[ED25519_AMINO_PREFIX.len()..])
+            .map(TendermintKey::ConsensusKey)
+            .ok_or_else(|| format_err!(InvalidKey, "invalid key {}", serialized).into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Example Ed25519 public key
+    const ED25519_KEY: [u8; 32] = [
+        0xcb, 0xa8, 0xce, 0xc7, 0x29, 0x88, 0xa6, 0xcd, 0x9a, 0x61, 0x90, 0x2f, 0x38, 0xeb, 0xc3,
+        0x83, 0xbb, 0x4e, 0x3c, 0xca, 0xbb, 0x5a, 0x78, 0xdf, 0x69, 0x50, 0x0f, 0xd5, 0xd5, 0xc6,
+        0xec, 0x1e,
+    ];
+
+    /// Example secp256k1 public key (compressed generator point)
+    const SECP256K1_KEY: &str =
+        "0279BE667EF9DCBBAC55A06295CE870B07029BFCDB2DCE28D959F2815B16F81798";
+
+    fn example_keys() -> Vec<PublicKey> {
+        vec![
+            PublicKey::from_raw_ed25519(&ED25519_KEY).unwrap(),
+            PublicKey::from_raw_secp256k1(&hex::decode_upper(SECP256K1_KEY).unwrap()).unwrap(),
+        ]
+    }
+
+    fn bech32_format() -> Format {
+        Format::Bech32 {
+            account_key_prefix: "cosmospub".to_owned(),
+            consensus_key_prefix: "cosmosvalconspub".to_owned(),
+        }
+    }
+
+    #[test]
+    fn bech32_serializes_every_key_variant() {
+        for pk in example_keys() {
+            for (key, prefix) in &[
+                (TendermintKey::AccountKey(pk), "cosmospub"),
+                (TendermintKey::ConsensusKey(pk), "cosmosvalconspub"),
+            ] {
+                let (hrp, data) = bech32::decode(bech32_format().serialize(*key)).unwrap();
+                assert_eq!(&hrp, prefix);
+                assert_eq!(data, pk.to_amino_bytes());
+            }
+        }
+    }
+
+    #[test]
+    fn hex_serializes_every_key_variant() {
+        for pk in example_keys() {
+            for key in &[
+                TendermintKey::AccountKey(pk),
+                TendermintKey::ConsensusKey(pk),
+            ] {
+                let serialized = Format::Hex.serialize(*key);
+                assert_eq!(hex::decode_upper(serialized).unwrap(), pk.to_amino_bytes());
+            }
+        }
+    }
+
+    #[test]
+    fn serialize_address() {
+        let key = TendermintKey::ConsensusKey(PublicKey::from_raw_ed25519(&ED25519_KEY).unwrap());
+        let address = Format::Hex.serialize_address(key);
+        assert_eq!(address.len(), 40);
+        assert_eq!(address, address.to_uppercase());
+
+        let (hrp, data) = bech32::decode(bech32_format().serialize_address(key)).unwrap();
+        assert_eq!(hrp, "cosmosvalcons");
+        assert_eq!(hex::encode_upper(data), address.as_bytes());
+
+        let account_address = bech32_format().serialize_address(TendermintKey::AccountKey(*key));
+        assert!(
+            account_address.starts_with("cosmos1"),
+            "{}",
+            account_address
+        );
+
+        // prefixes which don't follow the convention fall back to hex
+        let format = Format::Bech32 {
+            account_key_prefix: "acct".to_owned(),
+            consensus_key_prefix: "cons".to_owned(),
+        };
+        assert_eq!(format.serialize_address(key), address);
+    }
+
+    #[test]
+    fn parse_consensus_key_roundtrip() {
+        let key = TendermintKey::ConsensusKey(PublicKey::from_raw_ed25519(&ED25519_KEY).unwrap());
+
+        for format in &[bech32_format(), Format::Hex] {
+            let serialized = format.serialize(key);
+            assert_eq!(format.parse_consensus_key(&serialized).unwrap(), key);
+        }
+
+        // account keys have a different prefix
+        let account_key = bech32_format().serialize(TendermintKey::AccountKey(*key));
+        assert!(bech32_format().parse_consensus_key(&account_key).is_err());
+    }
 }
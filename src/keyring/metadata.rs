@@ -0,0 +1,125 @@
+//! Operator-defined metadata attached to keys (e.g. team, environment,
+//! rotation date).
+//!
+//! Metadata is purely informational: it's displayed alongside keys in logs,
+//! `tmkms test`, and the management API, and never affects signing. The
+//! number and size of entries are bounded so it's safe to use as labels.
+
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, convert::TryFrom, fmt};
+
+/// Maximum number of metadata entries per key
+pub const MAX_ENTRIES: usize = 8;
+
+/// Maximum length of a metadata key
+pub const MAX_KEY_LEN: usize = 32;
+
+/// Maximum length of a metadata value
+pub const MAX_VALUE_LEN: usize = 64;
+
+/// Metadata attached to a key
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(try_from = "BTreeMap<String, String>")]
+pub struct Metadata(BTreeMap<String, String>);
+
+impl Metadata {
+    /// Is there no metadata?
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Get the value for the given key
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).map(String::as_str)
+    }
+
+    /// Iterate over the entries, ordered by key
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+}
+
+impl TryFrom<BTreeMap<String, String>> for Metadata {
+    type Error = String;
+
+    fn try_from(entries: BTreeMap<String, String>) -> Result<Self, String> {
+        if entries.len() > MAX_ENTRIES {
+            return Err(format!(
+                "too many metadata entries: {} (max {})",
+                entries.len(),
+                MAX_ENTRIES
+            ));
+        }
+
+        for (key, value) in &entries {
+            let valid_key = !key.is_empty()
+                && key.len() <= MAX_KEY_LEN
+                && key
+                    .bytes()
+                    .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'_');
+
+            if !valid_key {
+                return Err(format!(
+                    "invalid metadata key {:?}: must be 1-{} characters of a-z, 0-9, or _",
+                    key, MAX_KEY_LEN
+                ));
+            }
+
+            if value.len() > MAX_VALUE_LEN || !value.bytes().all(|b| b.is_ascii_graphic()) {
+                return Err(format!(
+                    "invalid metadata value for {}: must be at most {} printable \
+                     ASCII characters with no spaces",
+                    key, MAX_VALUE_LEN
+                ));
+            }
+        }
+
+        Ok(Metadata(entries))
+    }
+}
+
+impl fmt::Display for Metadata {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (key, value)) in self.iter().enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+
+            write!(f, "{}={}", key, value)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entries(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn display_metadata() {
+        let metadata = Metadata::try_from(entries(&[("team", "infra"), ("env", "prod")])).unwrap();
+
+        assert_eq!(metadata.to_string(), "env=prod team=infra");
+        assert_eq!(metadata.get("team"), Some("infra"));
+    }
+
+    #[test]
+    fn metadata_is_bounded() {
+        let too_many: BTreeMap<_, _> = (0..=MAX_ENTRIES)
+            .map(|i| (format!("key{}", i), "value".to_owned()))
+            .collect();
+
+        assert!(Metadata::try_from(too_many).is_err());
+        assert!(Metadata::try_from(entries(&[("Team", "infra")])).is_err());
+        assert!(Metadata::try_from(entries(&[("team", "two words")])).is_err());
+        assert!(Metadata::try_from(entries(&[("team", &"x".repeat(65))])).is_err());
+    }
+}
@@ -13,6 +13,14 @@ pub enum SigningProvider {
     #[cfg(feature = "ledgertm")]
     LedgerTm,
 
+    /// External command
+    #[cfg(feature = "exec")]
+    Exec,
+
+    /// Remote KMS's sign server
+    #[cfg(feature = "remote")]
+    Remote,
+
     /// Software signer (not intended for production use)
     #[cfg(feature = "softsign")]
     SoftSign,
@@ -27,6 +35,12 @@ impl Display for SigningProvider {
             #[cfg(feature = "ledgertm")]
             SigningProvider::LedgerTm => write!(f, "ledgertm"),
 
+            #[cfg(feature = "exec")]
+            SigningProvider::Exec => write!(f, "exec"),
+
+            #[cfg(feature = "remote")]
+            SigningProvider::Remote => write!(f, "remote"),
+
             #[cfg(feature = "softsign")]
             SigningProvider::SoftSign => write!(f, "softsign"),
         }
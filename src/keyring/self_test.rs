@@ -0,0 +1,73 @@
+//! Self-test of signing keys: sign a fixed message and verify the signature
+//! against the key's public key. Used by `tmkms test` and by chains with
+//! `prewarm` enabled.
+
+use super::{ed25519::Signer, SigningAlgorithm};
+use crate::{
+    error::{Error, ErrorKind::*},
+    prelude::*,
+};
+use signatory::{ed25519, signature::Verifier};
+use signatory_dalek::Ed25519Verifier;
+
+/// Fixed message signed by the self-test.
+///
+/// This is printable ASCII, so it can never be mistaken for an amino-encoded
+/// consensus message (which begins with a length prefix followed by `0x08`).
+pub const TEST_MESSAGE: &[u8] = b"tmkms self-test: not a consensus message";
+
+/// Sign the test message and verify the signature against the signer's
+/// public key
+pub fn self_test(signer: &Signer) -> Result<(), Error> {
+    let public_key = signer
+        .public_key()
+        .ed25519()
+        .ok_or_else(|| format_err!(InvalidKey, "not an Ed25519 key"))?;
+
+    let signature = signer.sign(TEST_MESSAGE)?;
+
+    match signer.algorithm() {
+        SigningAlgorithm::Ed25519 => verify_ed25519(&public_key, &signature),
+        SigningAlgorithm::Ed25519ph => verify_ed25519ph(&public_key, &signature),
+    }
+}
+
+/// Verify an Ed25519 signature over the test message
+fn verify_ed25519(
+    public_key: &ed25519::PublicKey,
+    signature: &ed25519::Signature,
+) -> Result<(), Error> {
+    Ed25519Verifier::from(public_key)
+        .verify(TEST_MESSAGE, signature)
+        .map_err(|e| format_err!(SigningError, "signature verification failed: {}", e).into())
+}
+
+/// Verify an Ed25519ph signature over the test message
+#[cfg(feature = "softsign")]
+fn verify_ed25519ph(
+    public_key: &ed25519::PublicKey,
+    signature: &ed25519::Signature,
+) -> Result<(), Error> {
+    use sha2::{Digest, Sha512};
+
+    let public_key = ed25519_dalek::PublicKey::from_bytes(public_key.as_bytes())
+        .map_err(|e| format_err!(InvalidKey, "{}", e))?;
+    let signature = ed25519_dalek::Signature::from_bytes(signature.as_ref())
+        .map_err(|e| format_err!(SigningError, "{}", e))?;
+
+    let mut prehash = Sha512::default();
+    prehash.input(TEST_MESSAGE);
+
+    public_key
+        .verify_prehashed(prehash, None, &signature)
+        .map_err(|e| format_err!(SigningError, "signature verification failed: {}", e).into())
+}
+
+/// Ed25519ph signers are only provided by `softsign`
+#[cfg(not(feature = "softsign"))]
+fn verify_ed25519ph(_: &ed25519::PublicKey, _: &ed25519::Signature) -> Result<(), Error> {
+    fail!(
+        SigningError,
+        "Ed25519ph verification requires the `softsign` feature"
+    )
+}
@@ -4,10 +4,16 @@
 #![deny(warnings, rust_2018_idioms, missing_docs, unused_qualifications)]
 #![doc(html_root_url = "https://docs.rs/tmkms/0.7.3")]
 
-#[cfg(not(any(feature = "softsign", feature = "yubihsm", feature = "ledgertm")))]
+#[cfg(not(any(
+    feature = "softsign",
+    feature = "yubihsm",
+    feature = "ledgertm",
+    feature = "exec",
+    feature = "remote"
+)))]
 compile_error!(
     "please enable one of the following backends with cargo's --features argument: \
-     yubihsm, ledgertm, softsign (e.g. --features=yubihsm)"
+     yubihsm, ledgertm, softsign, exec, remote (e.g. --features=yubihsm)"
 );
 
 pub mod application;
@@ -15,12 +21,16 @@ pub mod chain;
 pub mod client;
 pub mod commands;
 pub mod config;
+pub mod confirm;
 pub mod connection;
 pub mod error;
 pub mod keyring;
+pub mod management;
 pub mod prelude;
+pub mod redact;
 pub mod rpc;
 pub mod session;
+pub mod sign_server;
 
 #[cfg(feature = "yubihsm")]
 pub mod yubihsm;
@@ -0,0 +1,528 @@
+//! Management API: an optional HTTP control channel for operating the KMS.
+//!
+//! This is entirely separate from the connections to validators, is disabled
+//! unless a `[management]` section is present in the configuration, and
+//! requires every request to carry a bearer token:
+//!
+//! - `GET /status`: KMS version, and per-chain signing state and keys
+//! - `POST /chains/<chain_id>/pause`: stop signing for a chain
+//! - `POST /chains/<chain_id>/resume`: resume signing for a chain (including
+//!   after it was halted due to an error)
+//! - `POST /chains/<chain_id>/enable`: enable a chain configured with
+//!   `enabled = false`, connecting to its validators
+//! - `POST /chains/<chain_id>/promote`: promote a standby KMS to active
+//! - `POST /chains/<chain_id>/demote`: demote an active KMS to standby
+//! - `PUT /chains/<chain_id>/halt-height`: refuse to sign at or above the
+//!   given height (request body, e.g. `2000000`)
+//! - `DELETE /chains/<chain_id>/halt-height`: clear the halt height
+//! - `POST /chains/<chain_id>/persist`: write a chain's state file and
+//!   `fsync` it now (regardless of its fsync policy), returning the persisted
+//!   height/round/step
+//! - `POST /persist`: persist the state of every chain (except those this
+//!   KMS is a standby for)
+//! - `GET /chains/<chain_id>/events`: recent signing events for a chain
+//! - `GET /chains/<chain_id>/attestation`: fresh attestations of a chain's
+//!   keys, from providers which support it
+//! - `GET /log-level`: get the current log filter
+//! - `PUT /log-level`: set the log filter (request body, e.g. `tmkms=debug`)
+
+use crate::{
+    chain,
+    config::management::ManagementConfig,
+    error::{Error, ErrorKind::*},
+    keyring::{Capabilities, Metadata},
+    prelude::*,
+    KmsApplication,
+};
+use abscissa_core::{application, trace::Tracing};
+use serde::Serialize;
+use serde_json::json;
+use std::{io::Read, thread};
+use subtle::ConstantTimeEq;
+use subtle_encoding::base64;
+use tendermint::{consensus, net};
+use tiny_http::{Header, Method, Request, Response};
+use zeroize::Zeroizing;
+
+/// Maximum size of a management API request body
+pub const MAX_BODY_LEN: u64 = 1024;
+
+/// Prefix of the `Authorization` header value carrying the token
+const BEARER_PREFIX: &str = "Bearer ";
+
+/// Management API server
+pub struct Server {
+    /// HTTP server
+    http: tiny_http::Server,
+
+    /// Bearer token clients must present
+    token: Zeroizing<String>,
+}
+
+impl Server {
+    /// Bind the management API to the configured listen address
+    pub fn new(config: &ManagementConfig) -> Result<Self, Error> {
+        let token = config.load_token()?;
+
+        let addr = match &config.laddr {
+            net::Address::Tcp { host, port, .. } => format!("{}:{}", host, port),
+            net::Address::Unix { .. } => fail!(
+                ConfigError,
+                "management API does not support Unix sockets: {}",
+                &config.laddr
+            ),
+        };
+
+        let http = tiny_http::Server::http(&addr).map_err(|e| {
+            format_err!(
+                IoError,
+                "couldn't start management API on {}: {}",
+                &config.laddr,
+                e
+            )
+        })?;
+
+        info!("management API listening on {}", &config.laddr);
+
+        Ok(Self { http, token })
+    }
+
+    /// Run the management API in a background thread
+    pub fn spawn(self) -> Result<(), Error> {
+        thread::Builder::new()
+            .name("management".to_owned())
+            .spawn(move || self.run())
+            .map_err(|e| format_err!(IoError, "error spawning management thread: {}", e))?;
+
+        Ok(())
+    }
+
+    /// Handle incoming requests until the server is shut down
+    pub fn run(&self) {
+        for mut request in self.http.incoming_requests() {
+            let result = if self.authorized(&request) {
+                handle_request(&mut request)
+            } else {
+                Err(ApiError::new(401, "missing or invalid bearer token"))
+            };
+
+            let (status, body) = match result {
+                Ok(body) => (200, body),
+                Err(e) => {
+                    warn!(
+                        "management API: {} {} -> {} ({})",
+                        request.method(),
+                        request.url(),
+                        e.status,
+                        e.message
+                    );
+
+                    (e.status, json!({ "error": e.message }))
+                }
+            };
+
+            let response = Response::from_string(body.to_string())
+                .with_status_code(status)
+                .with_header("Content-Type: application/json".parse::<Header>().unwrap());
+
+            if let Err(e) = request.respond(response) {
+                warn!("management API: error sending response: {}", e);
+            }
+        }
+    }
+
+    /// Does the request carry the expected bearer token?
+    fn authorized(&self, request: &Request) -> bool {
+        request
+            .headers()
+            .iter()
+            .find(|header| header.field.equiv("Authorization"))
+            .map(|header| header.value.as_str())
+            .filter(|value| value.starts_with(BEARER_PREFIX))
+            .map(|value| {
+                let token = &value[BEARER_PREFIX.len()..];
+                bool::from(token.as_bytes().ct_eq(self.token.as_bytes()))
+            })
+            .unwrap_or(false)
+    }
+}
+
+/// Errors returned to management API clients
+struct ApiError {
+    /// HTTP status code
+    status: u16,
+
+    /// Description of the error
+    message: String,
+}
+
+impl ApiError {
+    fn new(status: u16, message: impl ToString) -> Self {
+        Self {
+            status,
+            message: message.to_string(),
+        }
+    }
+}
+
+/// Result of handling a management API request
+type ApiResult = Result<serde_json::Value, ApiError>;
+
+/// Status of a particular chain
+#[derive(Serialize)]
+struct ChainStatus {
+    /// Chain ID
+    id: chain::Id,
+
+    /// Is signing paused?
+    paused: bool,
+
+    /// Is the chain enabled?
+    enabled: bool,
+
+    /// Is this KMS a standby for this chain?
+    standby: bool,
+
+    /// Reason signing was halted due to an error (if it was)
+    halted: Option<String>,
+
+    /// Height at or above which signing is refused (if any)
+    halt_height: Option<tendermint::block::Height>,
+
+    /// Consensus state of the last signature
+    consensus_state: consensus::State,
+
+    /// Are state updates failing to be mirrored? (if mirroring is enabled)
+    state_mirror_failing: Option<bool>,
+
+    /// Keys registered for this chain
+    keys: Vec<KeyStatus>,
+}
+
+/// A key registered for a chain
+#[derive(Serialize)]
+struct KeyStatus {
+    /// Provider holding the key
+    provider: String,
+
+    /// Public key, as displayed for the chain's key format
+    public_key: String,
+
+    /// Address derived from the public key (e.g. the validator's consensus
+    /// address), as displayed for the chain's key format
+    address: String,
+
+    /// Operator-defined metadata for the key
+    metadata: Metadata,
+
+    /// Optional capabilities supported by the key's provider
+    capabilities: Capabilities,
+
+    /// Signing operations in progress on the key's provider (if its
+    /// concurrency is limited)
+    in_flight_signs: Option<usize>,
+
+    /// Maximum concurrent signing operations on the key's provider (if
+    /// limited)
+    max_concurrent_signs: Option<usize>,
+
+    /// Number of signatures made by the key (if counted)
+    signature_count: Option<u64>,
+}
+
+/// Route a request to the corresponding handler
+fn handle_request(request: &mut Request) -> ApiResult {
+    let url = request.url().to_owned();
+    let path = url.split('?').next().unwrap();
+    let segments = path
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .collect::<Vec<_>>();
+
+    match (request.method(), segments.as_slice()) {
+        (Method::Get, ["status"]) => status(),
+        (Method::Post, ["chains", chain_id, "pause"]) => set_paused(chain_id, true),
+        (Method::Post, ["chains", chain_id, "resume"]) => set_paused(chain_id, false),
+        (Method::Post, ["chains", chain_id, "enable"]) => enable(chain_id),
+        (Method::Post, ["chains", chain_id, "promote"]) => set_standby(chain_id, false),
+        (Method::Post, ["chains", chain_id, "demote"]) => set_standby(chain_id, true),
+        (Method::Put, ["chains", chain_id, "halt-height"]) => {
+            set_halt_height(chain_id, Some(&read_body(request)?))
+        }
+        (Method::Delete, ["chains", chain_id, "halt-height"]) => set_halt_height(chain_id, None),
+        (Method::Post, ["chains", chain_id, "persist"]) => persist(Some(chain_id)),
+        (Method::Post, ["persist"]) => persist(None),
+        (Method::Get, ["chains", chain_id, "events"]) => events(chain_id),
+        (Method::Get, ["chains", chain_id, "attestation"]) => attestation(chain_id),
+        (Method::Get, ["log-level"]) => log_level(),
+        (Method::Put, ["log-level"]) => set_log_level(&read_body(request)?),
+        _ => Err(ApiError::new(404, format!("not found: {}", path))),
+    }
+}
+
+/// `GET /status`
+fn status() -> ApiResult {
+    let registry = chain::REGISTRY.get();
+
+    let chains = registry
+        .chains()
+        .map(|chain| {
+            let state = chain.state.lock().unwrap();
+
+            ChainStatus {
+                id: chain.id,
+                paused: chain.is_paused(),
+                enabled: chain.is_enabled(),
+                standby: chain.is_standby(),
+                halted: chain.halted_reason(),
+                halt_height: chain.halt_height(),
+                consensus_state: state.consensus_state().clone(),
+                state_mirror_failing: state.is_mirror_failing(),
+                keys: chain
+                    .keyring
+                    .signers()
+                    .map(|signer| {
+                        let public_key = chain.keyring.format().serialize(signer.public_key());
+
+                        KeyStatus {
+                            provider: signer.provider().to_string(),
+                            signature_count: state.signature_count(&public_key),
+                            public_key,
+                            address: chain
+                                .keyring
+                                .format()
+                                .serialize_address(signer.public_key()),
+                            metadata: signer.metadata().clone(),
+                            capabilities: signer.capabilities(),
+                            in_flight_signs: signer
+                                .concurrency_limit()
+                                .map(|limit| limit.in_flight()),
+                            max_concurrent_signs: signer
+                                .concurrency_limit()
+                                .map(|limit| limit.max()),
+                        }
+                    })
+                    .collect(),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    Ok(json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        "chains": chains,
+    }))
+}
+
+/// `POST /chains/<chain_id>/pause` and `POST /chains/<chain_id>/resume`
+fn set_paused(chain_id: &str, paused: bool) -> ApiResult {
+    let chain_id = parse_chain_id(chain_id)?;
+    let registry = chain::REGISTRY.get();
+    let chain = get_chain(&registry, &chain_id)?;
+
+    if paused {
+        chain.pause();
+        warn!("[{}] signing paused via management API", chain_id);
+    } else {
+        chain.resume();
+        info!("[{}] signing resumed via management API", chain_id);
+    }
+
+    Ok(json!({ "id": chain_id, "paused": paused }))
+}
+
+/// `POST /chains/<chain_id>/enable`
+fn enable(chain_id: &str) -> ApiResult {
+    let chain_id = parse_chain_id(chain_id)?;
+    let registry = chain::REGISTRY.get();
+    let chain = get_chain(&registry, &chain_id)?;
+
+    chain.enable();
+    Ok(json!({ "id": chain_id, "enabled": true }))
+}
+
+/// `POST /chains/<chain_id>/promote` and `POST /chains/<chain_id>/demote`
+fn set_standby(chain_id: &str, standby: bool) -> ApiResult {
+    let chain_id = parse_chain_id(chain_id)?;
+    let registry = chain::REGISTRY.get();
+    let chain = get_chain(&registry, &chain_id)?;
+
+    if standby {
+        chain.demote();
+    } else {
+        chain
+            .promote()
+            .map_err(|e| ApiError::new(500, format!("couldn't promote: {}", e)))?;
+    }
+
+    Ok(json!({ "id": chain_id, "standby": standby }))
+}
+
+/// `PUT /chains/<chain_id>/halt-height` and `DELETE /chains/<chain_id>/halt-height`
+fn set_halt_height(chain_id: &str, halt_height: Option<&str>) -> ApiResult {
+    let chain_id = parse_chain_id(chain_id)?;
+    let halt_height = halt_height
+        .map(|height| {
+            height
+                .trim()
+                .parse::<tendermint::block::Height>()
+                .map_err(|e| ApiError::new(400, format!("invalid halt height: {}", e)))
+        })
+        .transpose()?;
+
+    let registry = chain::REGISTRY.get();
+    let chain = get_chain(&registry, &chain_id)?;
+    chain.set_halt_height(halt_height);
+
+    Ok(json!({ "id": chain_id, "halt_height": halt_height }))
+}
+
+/// `POST /chains/<chain_id>/persist` and `POST /persist`
+fn persist(chain_id: Option<&str>) -> ApiResult {
+    let chain_id = chain_id.map(parse_chain_id).transpose()?;
+    let registry = chain::REGISTRY.get();
+
+    // A standby's state may be stale, and writing it could roll back a state
+    // file it shares with the active KMS
+    let chains = match &chain_id {
+        Some(chain_id) => {
+            let chain = get_chain(&registry, chain_id)?;
+
+            if chain.is_standby() {
+                return Err(ApiError::new(
+                    409,
+                    format!("[{}] not persisting state: KMS is a standby", chain_id),
+                ));
+            }
+
+            vec![chain]
+        }
+        None => registry
+            .chains()
+            .filter(|chain| !chain.is_standby())
+            .collect(),
+    };
+
+    let mut persisted = vec![];
+
+    for chain in chains {
+        let mut state = chain.state.lock().unwrap();
+
+        state.persist().map_err(|e| {
+            ApiError::new(500, format!("[{}] couldn't persist state: {}", chain.id, e))
+        })?;
+
+        let consensus_state = state.consensus_state();
+
+        info!(
+            "[{}] state persisted via management API at h/r/s {}",
+            chain.id, consensus_state
+        );
+
+        persisted.push(json!({
+            "id": chain.id,
+            "height": consensus_state.height,
+            "round": consensus_state.round,
+            "step": consensus_state.step,
+        }));
+    }
+
+    Ok(json!({ "chains": persisted }))
+}
+
+/// `GET /chains/<chain_id>/events`
+fn events(chain_id: &str) -> ApiResult {
+    let chain_id = parse_chain_id(chain_id)?;
+    let registry = chain::REGISTRY.get();
+    let chain = get_chain(&registry, &chain_id)?;
+
+    Ok(json!({ "id": chain_id, "events": chain.events.events() }))
+}
+
+/// `GET /chains/<chain_id>/attestation`
+fn attestation(chain_id: &str) -> ApiResult {
+    let chain_id = parse_chain_id(chain_id)?;
+    let registry = chain::REGISTRY.get();
+    let chain = get_chain(&registry, &chain_id)?;
+    let mut attestations = vec![];
+
+    for signer in chain.keyring.signers() {
+        let attestation = match signer.attestation() {
+            Some(result) => result
+                .map_err(|e| ApiError::new(500, format!("couldn't fetch attestation: {}", e)))?,
+            None => continue,
+        };
+
+        attestations.push(json!({
+            "provider": signer.provider().to_string(),
+            "public_key": chain.keyring.format().serialize(signer.public_key()),
+            "format": attestation.format,
+            "sha256": attestation.fingerprint(),
+            "document": String::from_utf8(base64::encode(&attestation.document)).unwrap(),
+        }));
+    }
+
+    Ok(json!({ "id": chain_id, "attestations": attestations }))
+}
+
+/// Parse a chain ID from a request path
+fn parse_chain_id(chain_id: &str) -> Result<chain::Id, ApiError> {
+    chain_id
+        .parse()
+        .map_err(|e| ApiError::new(400, format!("invalid chain ID: {}", e)))
+}
+
+/// Look up a chain in the registry
+fn get_chain<'a>(
+    registry: &'a chain::Guard<'_>,
+    chain_id: &chain::Id,
+) -> Result<&'a chain::Chain, ApiError> {
+    registry
+        .get_chain(chain_id)
+        .ok_or_else(|| ApiError::new(404, format!("unknown chain: {}", chain_id)))
+}
+
+/// `GET /log-level`
+fn log_level() -> ApiResult {
+    let mut app = app_writer();
+    let tracing = tracing_component(&mut app)?;
+    Ok(json!({ "filter": tracing.filter() }))
+}
+
+/// `PUT /log-level`
+fn set_log_level(filter: &str) -> ApiResult {
+    let filter = filter.trim();
+
+    if filter.is_empty() {
+        return Err(ApiError::new(400, "empty log filter"));
+    }
+
+    let mut app = app_writer();
+    let tracing = tracing_component(&mut app)?;
+    tracing.reload_filter(filter);
+    info!("log filter set to `{}` via management API", filter);
+
+    Ok(json!({ "filter": tracing.filter() }))
+}
+
+/// Get the application's tracing component
+fn tracing_component(
+    app: &mut application::lock::Writer<KmsApplication>,
+) -> Result<&mut Tracing, ApiError> {
+    app.state_mut()
+        .components
+        .get_downcast_mut::<Tracing>()
+        .ok_or_else(|| ApiError::new(500, "tracing component not registered"))
+}
+
+/// Read the (bounded) body of a request as a string
+fn read_body(request: &mut Request) -> Result<String, ApiError> {
+    let mut body = String::new();
+
+    request
+        .as_reader()
+        .take(MAX_BODY_LEN)
+        .read_to_string(&mut body)
+        .map_err(|e| ApiError::new(400, format!("error reading request body: {}", e)))?;
+
+    Ok(body)
+}
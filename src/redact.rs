@@ -0,0 +1,182 @@
+//! Redaction of secret material from panic messages.
+//!
+//! Secret-bearing types in tmkms (seeds, keypairs, passwords, and tokens)
+//! don't implement a `Debug` which prints their contents, so they should
+//! never be formatted into a panic message. As defense in depth, panic
+//! messages are also scrubbed of anything which looks like key material
+//! (long hex or Base64 strings, or byte arrays) before being printed or
+//! logged, and backtraces (which may include source snippets) are omitted.
+
+use std::{any::Any, panic};
+
+/// Placeholder for redacted values
+pub const REDACTED: &str = "[REDACTED]";
+
+/// Minimum length of a hex string considered to be potential key material
+/// (i.e. 128 bits)
+const MIN_HEX_LEN: usize = 32;
+
+/// Minimum length of a Base64 string considered to be potential key material
+const MIN_BASE64_LEN: usize = 24;
+
+/// Minimum number of elements in a byte array considered to be potential
+/// key material
+const MIN_BYTE_ARRAY_LEN: usize = 16;
+
+/// Install a panic hook which prints redacted panic messages
+pub fn install_panic_hook() {
+    panic::set_hook(Box::new(|info| {
+        let thread = std::thread::current();
+        let location = info
+            .location()
+            .map(|location| format!("{}:{}", location.file(), location.line()))
+            .unwrap_or_else(|| "unknown location".to_owned());
+
+        eprintln!(
+            "thread '{}' panicked at '{}', {}",
+            thread.name().unwrap_or("<unnamed>"),
+            panic_message(info.payload()),
+            location
+        );
+    }));
+}
+
+/// Get the redacted message from a panic payload
+pub fn panic_message(payload: &dyn Any) -> String {
+    let message = if let Some(msg) = payload.downcast_ref::<String>() {
+        msg.as_ref()
+    } else if let Some(msg) = payload.downcast_ref::<&str>() {
+        msg
+    } else {
+        "unknown cause"
+    };
+
+    redact(message)
+}
+
+/// Replace anything in the given message which looks like key material
+pub fn redact(message: &str) -> String {
+    redact_byte_arrays(&redact_words(message))
+}
+
+/// Redact long hex and Base64 "words"
+fn redact_words(message: &str) -> String {
+    let mut result = String::with_capacity(message.len());
+    let mut word = String::new();
+
+    for c in message.chars() {
+        if c.is_ascii_alphanumeric() || c == '+' || c == '/' || c == '=' {
+            word.push(c);
+        } else {
+            push_word(&mut result, &word);
+            word.clear();
+            result.push(c);
+        }
+    }
+
+    push_word(&mut result, &word);
+    result
+}
+
+/// Append a word to the result, redacting it if it looks like key material
+fn push_word(result: &mut String, word: &str) {
+    let is_hex = word.len() >= MIN_HEX_LEN && word.chars().all(|c| c.is_ascii_hexdigit());
+
+    // Require a mix of character classes so long identifiers aren't redacted
+    let is_base64 = word.len() >= MIN_BASE64_LEN
+        && word.chars().any(|c| c.is_ascii_digit())
+        && word.chars().any(|c| c.is_ascii_uppercase())
+        && word.chars().any(|c| c.is_ascii_lowercase());
+
+    if is_hex || is_base64 {
+        result.push_str(REDACTED);
+    } else {
+        result.push_str(word);
+    }
+}
+
+/// Redact `Debug`-formatted byte arrays, e.g. `[1, 2, 3, ...]`
+fn redact_byte_arrays(message: &str) -> String {
+    let mut result = String::with_capacity(message.len());
+    let mut rest = message;
+
+    while let Some(start) = rest.find('[') {
+        let (before, from_bracket) = rest.split_at(start);
+        result.push_str(before);
+
+        match from_bracket.find(']') {
+            Some(end) if is_byte_array(&from_bracket[1..end]) => {
+                result.push_str(REDACTED);
+                rest = &from_bracket[(end + 1)..];
+            }
+            _ => {
+                result.push('[');
+                rest = &from_bracket[1..];
+            }
+        }
+    }
+
+    result.push_str(rest);
+    result
+}
+
+/// Is this the contents of a (long) `Debug`-formatted byte array?
+fn is_byte_array(contents: &str) -> bool {
+    let elements = contents.split(',').map(str::trim).collect::<Vec<_>>();
+
+    elements.len() >= MIN_BYTE_ARRAY_LEN && elements.iter().all(|elem| elem.parse::<u8>().is_ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use abscissa_core::secret::Secret;
+
+    /// Example secret key (hex)
+    const SECRET_HEX: &str = "833fe62409237b9d62ec77587520911e9a759cec1d19755b7da901b96dca3d42";
+
+    /// Example secret key (Base64)
+    const SECRET_BASE64: &str = "gz/mJAkje51i7HdYdSCRHpp1nOwdGXVbfakBuW3KPUI=";
+
+    fn panic_payload(f: impl FnOnce() + panic::UnwindSafe) -> String {
+        let hook = panic::take_hook();
+        panic::set_hook(Box::new(|_| {}));
+        let payload = panic::catch_unwind(f).unwrap_err();
+        panic::set_hook(hook);
+        panic_message(&*payload)
+    }
+
+    #[test]
+    fn redacts_secret_wrapped_values() {
+        let secret = Secret::new(SECRET_HEX.to_owned());
+        let message = panic_payload(move || panic!("bad key: {:?}", secret));
+        assert!(!message.contains(SECRET_HEX));
+    }
+
+    #[test]
+    fn redacts_key_material() {
+        let bytes = [
+            0x83u8, 0x3f, 0xe6, 0x24, 0x09, 0x23, 0x7b, 0x9d, 0x62, 0xec, 0x77, 0x58, 0x75, 0x20,
+            0x91, 0x1e,
+        ];
+
+        for secret in &[
+            SECRET_HEX.to_owned(),
+            SECRET_BASE64.to_owned(),
+            format!("{:?}", bytes),
+        ] {
+            let secret = secret.clone();
+            let expected = secret.clone();
+            let message = panic_payload(move || panic!("couldn't decode key {}!", secret));
+
+            assert!(!message.contains(&expected), "not redacted: {}", message);
+            assert_eq!(message, format!("couldn't decode key {}!", REDACTED));
+        }
+    }
+
+    #[test]
+    fn leaves_other_messages_intact() {
+        let message = "error writing state to cosmoshub-3_priv_validator_state.json: [1, 2]";
+        assert_eq!(redact(message), message);
+    }
+}
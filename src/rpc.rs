@@ -3,6 +3,12 @@
 // TODO: docs for everything
 #![allow(missing_docs)]
 
+pub mod proto;
+pub mod refusal;
+mod sign_bytes;
+
+pub use self::sign_bytes::{SignBytesRequest, SignedBytesResponse, SIGN_BYTES_AMINO_NAME};
+
 use bytes::Bytes;
 use once_cell::sync::Lazy;
 use prost_amino::{
@@ -10,7 +16,10 @@ use prost_amino::{
     Message,
 };
 use sha2::{Digest, Sha256};
-use std::io::{self, Error, ErrorKind, Read};
+use std::{
+    io::{self, Error, ErrorKind, Read},
+    time::SystemTime,
+};
 use tendermint::amino_types::*;
 
 /// Maximum size of an RPC message
@@ -24,8 +33,15 @@ pub enum Request {
     SignVote(SignVoteRequest),
     ShowPublicKey(PubKeyRequest),
 
+    /// Sign raw (domain-separated) bytes, if enabled for this chain
+    SignBytes(SignBytesRequest),
+
     // PingRequest is a PrivValidatorSocket message to keep the connection alive.
     ReplyPing(PingRequest),
+
+    /// Message of a type the KMS doesn't recognize, identified by its amino
+    /// prefix
+    Unknown(Vec<u8>),
 }
 
 /// Responses from the KMS
@@ -36,10 +52,40 @@ pub enum Response {
     SignedProposal(SignedProposalResponse),
     Ping(PingResponse),
     PublicKey(PubKeyResponse),
+
+    /// Protobuf-encoded public key response, for chains configured with
+    /// `pubkey_encoding = "proto"`
+    ProtoPublicKey(proto::Message),
+    SignedBytes(SignedBytesResponse),
+}
+
+impl Response {
+    /// Get the error reported to the validator in this response (if any)
+    pub fn error(&self) -> Option<&RemoteError> {
+        match self {
+            Response::SignedVote(sv) => sv.err.as_ref(),
+            Response::SignedProposal(sp) => sp.err.as_ref(),
+            Response::SignedBytes(sb) => sb.err.as_ref(),
+            Response::Ping(_) | Response::PublicKey(_) => None,
+            Response::ProtoPublicKey(pk) => pk
+                .pub_key_response
+                .as_ref()
+                .and_then(|response| response.error.as_ref()),
+        }
+    }
 }
 
 pub trait TendermintRequest: SignableMsg {
     fn build_response(self, error: Option<RemoteError>) -> Response;
+
+    /// Timestamp of the underlying consensus message (if present)
+    fn timestamp(&self) -> Option<SystemTime>;
+
+    /// Timestamp of the underlying consensus message as encoded on the wire
+    fn timestamp_msg(&self) -> Option<TimeMsg>;
+
+    /// Replace the timestamp of the underlying consensus message
+    fn set_timestamp_msg(&mut self, timestamp: Option<TimeMsg>);
 }
 
 fn compute_prefix(name: &str) -> Vec<u8> {
@@ -66,44 +112,91 @@ static VOTE_PREFIX: Lazy<Vec<u8>> = Lazy::new(|| compute_prefix(VOTE_AMINO_NAME)
 static PROPOSAL_PREFIX: Lazy<Vec<u8>> = Lazy::new(|| compute_prefix(PROPOSAL_AMINO_NAME));
 static PUBKEY_PREFIX: Lazy<Vec<u8>> = Lazy::new(|| compute_prefix(PUBKEY_AMINO_NAME));
 static PING_PREFIX: Lazy<Vec<u8>> = Lazy::new(|| compute_prefix(PING_AMINO_NAME));
+static SIGN_BYTES_PREFIX: Lazy<Vec<u8>> = Lazy::new(|| compute_prefix(SIGN_BYTES_AMINO_NAME));
+
+static SIGNED_VOTE_PREFIX: Lazy<Vec<u8>> =
+    Lazy::new(|| compute_prefix("tendermint/remotesigner/SignedVoteResponse"));
+static SIGNED_PROPOSAL_PREFIX: Lazy<Vec<u8>> =
+    Lazy::new(|| compute_prefix("tendermint/remotesigner/SignedProposalResponse"));
+static PUBKEY_RESPONSE_PREFIX: Lazy<Vec<u8>> =
+    Lazy::new(|| compute_prefix("tendermint/remotesigner/PubKeyResponse"));
+static PING_RESPONSE_PREFIX: Lazy<Vec<u8>> =
+    Lazy::new(|| compute_prefix("tendermint/remotesigner/PingResponse"));
+static SIGNED_BYTES_PREFIX: Lazy<Vec<u8>> =
+    Lazy::new(|| compute_prefix("tendermint/remotesigner/SignedBytesResponse"));
 
 impl Request {
     /// Read a request from the given readable
     pub fn read<R: Read>(r: &mut R) -> io::Result<Self> {
-        // this buffer contains the overall length and the amino prefix (for the registered types)
-        let mut buf = vec![0; MAX_MSG_LEN];
-        let bytes_read = r.read(&mut buf)?;
-        if bytes_read < 4 {
-            return Err(Error::new(
-                ErrorKind::InvalidData,
-                "Did not read enough bytes to continue.",
-            ));
-        }
+        Self::read_with_bytes(r).map(|(request, _)| request)
+    }
 
-        let mut buf_amino: Bytes = Bytes::from(buf.clone());
-        let len = decode_varint(&mut buf_amino).unwrap();
-        if len > MAX_MSG_LEN as u64 {
-            return Err(Error::new(ErrorKind::InvalidData, "RPC message too large."));
-        }
-        let amino_pre = buf_amino.slice(0..4);
+    /// Read a request from the given readable, along with the bytes it was
+    /// decoded from (including the length prefix)
+    pub fn read_with_bytes<R: Read>(r: &mut R) -> io::Result<(Self, Vec<u8>)> {
+        let (amino_pre, rem) = read_message(r)?;
+        let request = Self::decode(amino_pre, &rem)?;
+        Ok((request, rem))
+    }
 
-        let buf: Bytes = Bytes::from(buf);
+    /// Does re-encoding this request reproduce exactly the bytes it was
+    /// decoded from? Decoding tolerates e.g. unknown or repeated fields and
+    /// non-minimal varints, which a malicious node could use to get the KMS
+    /// to sign a message it interprets differently. Only proposals and votes
+    /// are checked.
+    pub fn is_canonical(&self, bytes: &[u8]) -> bool {
+        let mut buf = vec![];
+
+        let result = match self {
+            Request::SignProposal(req) => req.encode(&mut buf),
+            Request::SignVote(req) => req.encode(&mut buf),
+            _ => return true,
+        };
 
-        let total_len = encoded_len_varint(len).checked_add(len as usize).unwrap();
-        let rem = buf.as_ref()[..total_len].to_vec();
+        result.is_ok() && buf == bytes
+    }
+
+    /// Decode a request with the given amino prefix
+    fn decode(amino_pre: Bytes, rem: &[u8]) -> io::Result<Self> {
         match amino_pre {
-            ref vt if *vt == *VOTE_PREFIX => {
-                Ok(Request::SignVote(SignVoteRequest::decode(rem.as_ref())?))
+            ref vt if *vt == *VOTE_PREFIX => Ok(Request::SignVote(SignVoteRequest::decode(rem)?)),
+            ref pr if *pr == *PROPOSAL_PREFIX => {
+                Ok(Request::SignProposal(SignProposalRequest::decode(rem)?))
             }
-            ref pr if *pr == *PROPOSAL_PREFIX => Ok(Request::SignProposal(
-                SignProposalRequest::decode(rem.as_ref())?,
-            )),
             ref pubk if *pubk == *PUBKEY_PREFIX => {
-                Ok(Request::ShowPublicKey(PubKeyRequest::decode(rem.as_ref())?))
+                Ok(Request::ShowPublicKey(PubKeyRequest::decode(rem)?))
+            }
+            ref ping if *ping == *PING_PREFIX => Ok(Request::ReplyPing(PingRequest::decode(rem)?)),
+            ref sb if *sb == *SIGN_BYTES_PREFIX => {
+                Ok(Request::SignBytes(SignBytesRequest::decode(rem)?))
+            }
+            _ => Ok(Request::Unknown(amino_pre.to_vec())),
+        }
+    }
+}
+
+impl Response {
+    /// Read a response from the given readable. The KMS only sends
+    /// responses, so this is only used when inspecting captured traffic.
+    pub fn read<R: Read>(r: &mut R) -> io::Result<Self> {
+        let (amino_pre, rem) = read_message(r)?;
+
+        match amino_pre {
+            ref sv if *sv == *SIGNED_VOTE_PREFIX => Ok(Response::SignedVote(
+                SignedVoteResponse::decode(rem.as_ref())?,
+            )),
+            ref sp if *sp == *SIGNED_PROPOSAL_PREFIX => Ok(Response::SignedProposal(
+                SignedProposalResponse::decode(rem.as_ref())?,
+            )),
+            ref pubk if *pubk == *PUBKEY_RESPONSE_PREFIX => {
+                Ok(Response::PublicKey(PubKeyResponse::decode(rem.as_ref())?))
             }
-            ref ping if *ping == *PING_PREFIX => {
-                Ok(Request::ReplyPing(PingRequest::decode(rem.as_ref())?))
+            ref ping if *ping == *PING_RESPONSE_PREFIX => {
+                Ok(Response::Ping(PingResponse::decode(rem.as_ref())?))
             }
+            ref sb if *sb == *SIGNED_BYTES_PREFIX => Ok(Response::SignedBytes(
+                SignedBytesResponse::decode(rem.as_ref())?,
+            )),
             _ => Err(Error::new(
                 ErrorKind::InvalidData,
                 "Received unknown RPC message.",
@@ -112,6 +205,33 @@ impl Request {
     }
 }
 
+/// Read a length-prefixed amino message from the given readable, returning
+/// its amino prefix and the message (including the length prefix)
+fn read_message<R: Read>(r: &mut R) -> io::Result<(Bytes, Vec<u8>)> {
+    // this buffer contains the overall length and the amino prefix (for the registered types)
+    let mut buf = vec![0; MAX_MSG_LEN];
+    let bytes_read = r.read(&mut buf)?;
+    if bytes_read < 4 {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            "Did not read enough bytes to continue.",
+        ));
+    }
+
+    let mut buf_amino: Bytes = Bytes::from(buf.clone());
+    let len = decode_varint(&mut buf_amino).unwrap();
+    if len > MAX_MSG_LEN as u64 {
+        return Err(Error::new(ErrorKind::InvalidData, "RPC message too large."));
+    }
+    let amino_pre = buf_amino.slice(0..4);
+
+    let buf: Bytes = Bytes::from(buf);
+
+    let total_len = encoded_len_varint(len).checked_add(len as usize).unwrap();
+    let rem = buf.as_ref()[..total_len].to_vec();
+    Ok((amino_pre, rem))
+}
+
 impl TendermintRequest for SignVoteRequest {
     fn build_response(self, error: Option<RemoteError>) -> Response {
         let response = if let Some(e) = error {
@@ -128,6 +248,23 @@ impl TendermintRequest for SignVoteRequest {
 
         Response::SignedVote(response)
     }
+
+    fn timestamp(&self) -> Option<SystemTime> {
+        self.vote
+            .as_ref()
+            .and_then(|vote| vote.timestamp.clone())
+            .map(SystemTime::from)
+    }
+
+    fn timestamp_msg(&self) -> Option<TimeMsg> {
+        self.vote.as_ref().and_then(|vote| vote.timestamp.clone())
+    }
+
+    fn set_timestamp_msg(&mut self, timestamp: Option<TimeMsg>) {
+        if let Some(vote) = self.vote.as_mut() {
+            vote.timestamp = timestamp;
+        }
+    }
 }
 
 impl TendermintRequest for SignProposalRequest {
@@ -146,4 +283,106 @@ impl TendermintRequest for SignProposalRequest {
 
         Response::SignedProposal(response)
     }
+
+    fn timestamp(&self) -> Option<SystemTime> {
+        self.proposal
+            .as_ref()
+            .and_then(|proposal| proposal.timestamp.clone())
+            .map(SystemTime::from)
+    }
+
+    fn timestamp_msg(&self) -> Option<TimeMsg> {
+        self.proposal
+            .as_ref()
+            .and_then(|proposal| proposal.timestamp.clone())
+    }
+
+    fn set_timestamp_msg(&mut self, timestamp: Option<TimeMsg>) {
+        if let Some(proposal) = self.proposal.as_mut() {
+            proposal.timestamp = timestamp;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use prost_amino::encoding::encode_varint;
+
+    fn example_vote() -> vote::Vote {
+        vote::Vote {
+            vote_type: SignedMsgType::PreCommit.to_u32(),
+            height: 12345,
+            round: 2,
+            timestamp: Some(TimeMsg {
+                seconds: 1_500_000_000,
+                nanos: 0,
+            }),
+            validator_address: vec![0xa3; 20],
+            validator_index: 56789,
+            ..Default::default()
+        }
+    }
+
+    /// Encode a `SignVoteRequest` by hand around the given (encoded) vote
+    /// fields, so they can be non-canonical
+    fn vote_request(vote_fields: &[u8]) -> Vec<u8> {
+        let mut body = VOTE_PREFIX.clone();
+        body.push(0x0a); // field 1 (vote), length-delimited
+        encode_varint(vote_fields.len() as u64, &mut body);
+        body.extend_from_slice(vote_fields);
+
+        let mut message = vec![];
+        encode_varint(body.len() as u64, &mut message);
+        message.extend_from_slice(&body);
+        message
+    }
+
+    fn read_request(bytes: &[u8]) -> (Request, Vec<u8>) {
+        Request::read_with_bytes(&mut &bytes[..]).unwrap()
+    }
+
+    #[test]
+    fn canonical_requests_roundtrip() {
+        let mut vote_fields = vec![];
+        example_vote().encode(&mut vote_fields).unwrap();
+
+        let mut bytes = vec![];
+        SignVoteRequest {
+            vote: Some(example_vote()),
+        }
+        .encode(&mut bytes)
+        .unwrap();
+
+        assert_eq!(vote_request(&vote_fields), bytes);
+
+        let (request, request_bytes) = read_request(&bytes);
+        assert_eq!(request_bytes, bytes);
+        assert!(request.is_canonical(&request_bytes));
+    }
+
+    #[test]
+    fn non_canonical_requests_are_detected() {
+        let mut vote_fields = vec![];
+        example_vote().encode(&mut vote_fields).unwrap();
+
+        // explicitly encoded default value (empty signature)
+        let mut default_value = vote_fields.clone();
+        default_value.extend_from_slice(&[0x42, 0x00]);
+
+        // repeated height (the last value wins) with a non-minimal varint
+        let mut repeated_field = vote_fields.clone();
+        repeated_field.extend_from_slice(&[0x10, 0xb9, 0xe0, 0x00]);
+
+        for malformed in &[default_value, repeated_field] {
+            let (request, request_bytes) = read_request(&vote_request(malformed));
+
+            match request {
+                Request::SignVote(ref req) => assert_eq!(req.vote, Some(example_vote())),
+                ref other => panic!("unexpected request: {:?}", other),
+            }
+
+            assert!(!request.is_canonical(&request_bytes));
+        }
+    }
 }
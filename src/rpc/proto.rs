@@ -0,0 +1,94 @@
+//! Protobuf encoding of the public key response, for validators which
+//! expect the privval protocol's protobuf `Message` envelope rather than
+//! amino (see the `pubkey_encoding` chain option).
+
+use prost_amino_derive::Message;
+use tendermint::{amino_types::RemoteError, PublicKey};
+
+/// privval `Message` envelope, holding a public key response
+#[derive(Clone, PartialEq, Message)]
+pub struct Message {
+    /// Public key response
+    #[prost_amino(message, tag = "2")]
+    pub pub_key_response: Option<PubKeyResponse>,
+}
+
+/// privval `PubKeyResponse`
+#[derive(Clone, PartialEq, Message)]
+pub struct PubKeyResponse {
+    /// Public key of the validator
+    #[prost_amino(message, tag = "1")]
+    pub pub_key: Option<ProtoPublicKey>,
+
+    /// Error fetching the public key
+    #[prost_amino(message, tag = "2")]
+    pub error: Option<RemoteError>,
+}
+
+/// `tendermint.crypto.PublicKey`
+#[derive(Clone, PartialEq, Message)]
+pub struct ProtoPublicKey {
+    /// Ed25519 public key bytes
+    #[prost_amino(bytes, tag = "1")]
+    pub ed25519: Vec<u8>,
+}
+
+impl From<PublicKey> for Message {
+    fn from(public_key: PublicKey) -> Message {
+        let ed25519 = match public_key {
+            PublicKey::Ed25519(ref pk) => pk.as_bytes().to_vec(),
+            PublicKey::Secp256k1(_) => panic!("secp256k1 PubKeyResponse unimplemented"),
+        };
+
+        Message {
+            pub_key_response: Some(PubKeyResponse {
+                pub_key: Some(ProtoPublicKey { ed25519 }),
+                error: None,
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use prost_amino::Message as _;
+    use tendermint::amino_types;
+
+    const PUBLIC_KEY: [u8; 32] = [
+        0x79, 0xce, 0xd, 0xe0, 0x43, 0x33, 0x4a, 0xec, 0xe0, 0x8b, 0x7b, 0xb5, 0x61, 0xbc, 0xe7,
+        0xc1, 0xd4, 0x69, 0xc3, 0x44, 0x26, 0xec, 0xef, 0xc0, 0x72, 0xa, 0x52, 0x4d, 0x37, 0x32,
+        0xef, 0xed,
+    ];
+
+    fn public_key() -> PublicKey {
+        PublicKey::Ed25519(signatory::ed25519::PublicKey::new(PUBLIC_KEY))
+    }
+
+    #[test]
+    fn amino_pubkey_response() {
+        let mut encoded = vec![];
+        amino_types::PubKeyResponse::from(public_key())
+            .encode(&mut encoded)
+            .unwrap();
+
+        let mut expected = vec![
+            0x2b, 0x17, 0xe, 0xd5, 0x7c, 0xa, 0x25, 0x16, 0x24, 0xde, 0x64, 0x20,
+        ];
+        expected.extend_from_slice(&PUBLIC_KEY);
+        assert_eq!(encoded, expected);
+    }
+
+    #[test]
+    fn proto_pubkey_response() {
+        let mut encoded = vec![];
+        Message::from(public_key())
+            .encode_length_delimited(&mut encoded)
+            .unwrap();
+
+        // length, Message.pub_key_response, PubKeyResponse.pub_key, PublicKey.ed25519
+        let mut expected = vec![0x26, 0x12, 0x24, 0xa, 0x22, 0xa, 0x20];
+        expected.extend_from_slice(&PUBLIC_KEY);
+        assert_eq!(encoded, expected);
+    }
+}
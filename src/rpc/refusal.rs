@@ -0,0 +1,141 @@
+//! Reason codes for refusing to sign a request.
+//!
+//! Tendermint's `RemoteError` only distinguishes double signing from other
+//! errors, so the reason code is also prefixed to the error description
+//! (e.g. `maintenance: signing paused for this chain`), where automation on
+//! the validator side can match on it.
+
+use std::fmt::{self, Display};
+use tendermint::amino_types::{remote_error::RemoteErrorCode, RemoteError};
+
+/// Reasons the KMS refuses to sign a request
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum RefusalReason {
+    /// Signing the request would be a double sign
+    DoubleSign,
+
+    /// Signing is paused, halted, or awaiting operator action for this chain
+    Maintenance,
+
+    /// The KMS isn't configured to sign for the request's chain
+    UnauthorizedChain,
+
+    /// The type of message isn't allowed to be signed for this chain
+    MessageTypeNotAllowed,
+
+    /// The request isn't canonically encoded (and this chain requires it)
+    NonCanonical,
+
+    /// The request is for a height, round, or step below what was already
+    /// signed (or below the chain's configured initial height)
+    HeightRegression,
+
+    /// The request is at or above the chain's configured halt height
+    ConfiguredHalt,
+
+    /// The signer is busy and couldn't handle the request in time
+    Overload,
+
+    /// The message's timestamp is outside the chain's configured bounds
+    /// (`block_time_bounds`), or too far from the local clock
+    /// (`max_clock_skew_ms`)
+    TimestampOutOfBounds,
+}
+
+impl RefusalReason {
+    /// Reason code as it appears in logs and error descriptions
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RefusalReason::DoubleSign => "double-sign",
+            RefusalReason::Maintenance => "maintenance",
+            RefusalReason::UnauthorizedChain => "unauthorized-chain",
+            RefusalReason::MessageTypeNotAllowed => "message-type-not-allowed",
+            RefusalReason::NonCanonical => "non-canonical-encoding",
+            RefusalReason::HeightRegression => "height-regression",
+            RefusalReason::ConfiguredHalt => "configured-halt",
+            RefusalReason::Overload => "overload",
+            RefusalReason::TimestampOutOfBounds => "timestamp-out-of-bounds",
+        }
+    }
+
+    /// Is this a transient refusal, i.e. might the same request succeed if
+    /// retried later?
+    pub fn is_transient(self) -> bool {
+        match self {
+            RefusalReason::Maintenance | RefusalReason::Overload => true,
+            _ => false,
+        }
+    }
+
+    /// Build the error reported to the validator for this refusal
+    pub fn to_remote_error(self, description: impl Display) -> RemoteError {
+        let code = match self {
+            RefusalReason::DoubleSign => RemoteErrorCode::DoubleSignError,
+            _ => RemoteErrorCode::RemoteSignerError,
+        };
+
+        RemoteError {
+            code: code as i32,
+            description: format!("{}: {}", self, description),
+        }
+    }
+
+    /// Parse the reason code from an error reported to the validator
+    pub fn from_remote_error(remote_err: &RemoteError) -> Option<Self> {
+        let code = remote_err.description.split(": ").next()?;
+
+        [
+            RefusalReason::DoubleSign,
+            RefusalReason::Maintenance,
+            RefusalReason::UnauthorizedChain,
+            RefusalReason::MessageTypeNotAllowed,
+            RefusalReason::NonCanonical,
+            RefusalReason::HeightRegression,
+            RefusalReason::ConfiguredHalt,
+            RefusalReason::Overload,
+            RefusalReason::TimestampOutOfBounds,
+        ]
+        .iter()
+        .find(|reason| reason.as_str() == code)
+        .cloned()
+    }
+}
+
+impl Display for RefusalReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reason_code_roundtrip() {
+        let remote_err =
+            RefusalReason::Maintenance.to_remote_error("signing paused for this chain");
+
+        assert_eq!(remote_err.code, RemoteErrorCode::RemoteSignerError as i32);
+        assert_eq!(
+            remote_err.description,
+            "maintenance: signing paused for this chain"
+        );
+        assert_eq!(
+            RefusalReason::from_remote_error(&remote_err),
+            Some(RefusalReason::Maintenance)
+        );
+    }
+
+    #[test]
+    fn double_sign_uses_double_sign_code() {
+        let remote_err = RefusalReason::DoubleSign.to_remote_error("at height 1");
+        assert_eq!(remote_err.code, RemoteErrorCode::DoubleSignError as i32);
+    }
+
+    #[test]
+    fn unprefixed_errors_have_no_reason() {
+        let remote_err = RemoteError::double_sign(1);
+        assert_eq!(RefusalReason::from_remote_error(&remote_err), None);
+    }
+}
@@ -0,0 +1,32 @@
+//! `SignBytesRequest`: raw signing extension used by some Tendermint forks.
+//!
+//! This is only handled for chains which explicitly enable it, and the bytes
+//! to be signed must begin with the chain's configured domain prefix.
+
+use prost_amino_derive::Message;
+use tendermint::amino_types::RemoteError;
+
+/// Amino name of `SignBytesRequest`
+pub const SIGN_BYTES_AMINO_NAME: &str = "tendermint/remotesigner/SignBytesRequest";
+
+/// Request to sign arbitrary (domain-separated) bytes
+#[derive(Clone, PartialEq, Message)]
+#[amino_name = "tendermint/remotesigner/SignBytesRequest"]
+pub struct SignBytesRequest {
+    /// Bytes to be signed
+    #[prost_amino(bytes, tag = "1")]
+    pub bytes: Vec<u8>,
+}
+
+/// Response to a `SignBytesRequest`
+#[derive(Clone, PartialEq, Message)]
+#[amino_name = "tendermint/remotesigner/SignedBytesResponse"]
+pub struct SignedBytesResponse {
+    /// Ed25519 signature over the requested bytes
+    #[prost_amino(bytes, tag = "1")]
+    pub signature: Vec<u8>,
+
+    /// Error signing the requested bytes
+    #[prost_amino(message, tag = "2")]
+    pub err: Option<RemoteError>,
+}
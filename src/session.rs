@@ -1,21 +1,39 @@
 //! A session with a validator node
 
 use crate::{
-    chain::{self, state::StateErrorKind},
-    config::ValidatorConfig,
-    connection::{tcp, unix::UnixConnection, Connection},
+    chain::{
+        self,
+        events::SignEvent,
+        state::{LastSigned, State, StateErrorKind},
+        Chain,
+    },
+    config::{
+        chain::{PubKeyEncoding, StepConfig, UnknownMessagePolicy},
+        HandshakeRng, ValidatorConfig,
+    },
+    confirm,
+    connection::{tcp, timed_phase, unix::UnixConnection, Connection},
     error::{Error, ErrorKind::*},
     prelude::*,
-    rpc::{Request, Response, TendermintRequest},
+    rpc::{
+        self, refusal::RefusalReason, Request, Response, SignBytesRequest, SignedBytesResponse,
+        TendermintRequest,
+    },
 };
 use prost_amino::Message;
-use std::{fmt::Debug, os::unix::net::UnixStream, time::Instant};
+use sha2::{Digest, Sha256};
+use std::{
+    fmt::{Debug, Display},
+    os::unix::net::UnixStream,
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+use subtle_encoding::hex;
 use tendermint::{
-    amino_types::{
-        PingRequest, PingResponse, PubKeyRequest, PubKeyResponse, RemoteError, SignedMsgType,
-    },
+    amino_types::{PingRequest, PingResponse, PubKeyRequest, PubKeyResponse, SignedMsgType},
     consensus, net,
 };
+use zeroize::Zeroizing;
 
 /// Encrypted session with a validator node
 pub struct Session {
@@ -30,6 +48,9 @@ impl Session {
     /// Open a session using the given validator configuration
     #[allow(clippy::cognitive_complexity)] // TODO(tarcieri): needs refactoring
     pub fn open(config: ValidatorConfig) -> Result<Self, Error> {
+        let span = span!(Level::INFO, "session", chain_id = %config.chain_id, addr = %config.addr);
+        let _enter = span.enter();
+
         let connection: Box<dyn Connection> = match &config.addr {
             net::Address::Tcp {
                 peer_id,
@@ -39,8 +60,21 @@ impl Session {
                 debug!("{}: Connecting to {}...", &config.chain_id, &config.addr);
 
                 let seed = config.load_secret_key()?;
-                let conn =
-                    tcp::open_secret_connection(host, *port, &seed, peer_id, config.timeout)?;
+                let ephemeral_seed = match config.handshake_rng {
+                    HandshakeRng::Os => None,
+                    HandshakeRng::Provider => Some(provider_ephemeral_seed(&config.chain_id)?),
+                };
+
+                let conn = tcp::open_secret_connection(
+                    host,
+                    *port,
+                    &seed,
+                    peer_id,
+                    config.timeout,
+                    config.handshake_timeout,
+                    config.bind_address,
+                    ephemeral_seed,
+                )?;
 
                 info!(
                     "[{}@{}] connected to validator successfully",
@@ -64,12 +98,23 @@ impl Session {
                     warn!("timeouts not supported with Unix sockets: {}", timeout);
                 }
 
+                if let Some(bind_address) = config.bind_address {
+                    warn!(
+                        "bind_address not supported with Unix sockets: {}",
+                        bind_address
+                    );
+                }
+
+                if config.handshake_rng != HandshakeRng::Os {
+                    warn!("handshake_rng not supported with Unix sockets (no handshake)");
+                }
+
                 debug!(
                     "{}: Connecting to socket at {}...",
                     &config.chain_id, &config.addr
                 );
 
-                let socket = UnixStream::connect(path)?;
+                let socket = timed_phase("unix_connect", || UnixStream::connect(path))?;
                 let conn = UnixConnection::new(socket);
 
                 info!(
@@ -92,20 +137,27 @@ impl Session {
 
     /// Handle an incoming request from the validator
     fn handle_request(&mut self) -> Result<bool, Error> {
-        let request = Request::read(&mut self.connection)?;
+        let (request, request_bytes) = Request::read_with_bytes(&mut self.connection)?;
+        let canonical = request.is_canonical(&request_bytes);
         debug!(
             "[{}:{}] received request: {:?}",
             &self.config.chain_id, &self.config.addr, &request
         );
 
-        let response = match request {
-            Request::SignProposal(req) => self.sign(req)?,
-            Request::SignVote(req) => self.sign(req)?,
+        let (response, is_signing) = match request {
+            Request::SignProposal(req) => (self.sign(req, canonical)?, true),
+            Request::SignVote(req) => (self.sign(req, canonical)?, true),
+            Request::SignBytes(ref req) => (self.sign_bytes(req)?, true),
             // non-signable requests:
-            Request::ReplyPing(ref req) => self.reply_ping(req),
-            Request::ShowPublicKey(ref req) => self.get_public_key(req)?,
+            Request::ReplyPing(ref req) => (self.reply_ping(req), false),
+            Request::ShowPublicKey(ref req) => (self.get_public_key(req)?, false),
+            Request::Unknown(ref prefix) => return self.handle_unknown_message(prefix),
         };
 
+        if is_signing {
+            self.delay_response();
+        }
+
         debug!(
             "[{}:{}] sending response: {:?}",
             &self.config.chain_id, &self.config.addr, &response
@@ -118,6 +170,8 @@ impl Session {
             Response::SignedVote(sv) => sv.encode(&mut buf)?,
             Response::Ping(ping) => ping.encode(&mut buf)?,
             Response::PublicKey(pk) => pk.encode(&mut buf)?,
+            Response::ProtoPublicKey(pk) => pk.encode_length_delimited(&mut buf)?,
+            Response::SignedBytes(sb) => sb.encode(&mut buf)?,
         }
 
         self.connection.write_all(&buf)?;
@@ -125,8 +179,52 @@ impl Session {
         Ok(true)
     }
 
-    /// Perform a digital signature operation
-    fn sign<R>(&mut self, mut request: R) -> Result<Response, Error>
+    /// Wait for a random amount of time before sending a signing response,
+    /// if the chain has `response_jitter_ms` configured. This happens after
+    /// signing (and persisting the state) so it only affects when the
+    /// validator sees the response.
+    fn delay_response(&self) {
+        let delay = chain::REGISTRY
+            .get()
+            .get_chain(&self.config.chain_id)
+            .and_then(|chain| chain.response_delay());
+
+        if let Some(delay) = delay {
+            thread::sleep(delay);
+        }
+    }
+
+    /// Apply the chain's policy to a message of an unrecognized type,
+    /// returning whether to keep handling requests on this connection
+    fn handle_unknown_message(&self, prefix: &[u8]) -> Result<bool, Error> {
+        let policy = chain::REGISTRY
+            .get()
+            .get_chain(&self.config.chain_id)
+            .map(|chain| chain.unknown_messages)
+            .unwrap_or_default();
+
+        let prefix = String::from_utf8(hex::encode(prefix)).unwrap();
+
+        match policy {
+            UnknownMessagePolicy::Close => fail!(
+                ProtocolError,
+                "received unknown RPC message (amino prefix {})",
+                prefix
+            ),
+            UnknownMessagePolicy::Ignore => {
+                warn!(
+                    "[{}@{}] ignoring unknown RPC message (amino prefix {})",
+                    &self.config.chain_id, &self.config.addr, prefix
+                );
+
+                Ok(true)
+            }
+        }
+    }
+
+    /// Perform a digital signature operation. `canonical` is whether the
+    /// request was canonically encoded.
+    fn sign<R>(&mut self, request: R, canonical: bool) -> Result<Response, Error>
     where
         R: TendermintRequest + Debug,
     {
@@ -134,22 +232,225 @@ impl Session {
 
         let registry = chain::REGISTRY.get();
 
-        // unwrap is acceptable here as chain presence is validated in client.rs's
-        // `register_chain` function.
-        let chain = registry.get_chain(&self.config.chain_id).unwrap();
+        let chain = match registry.get_chain(&self.config.chain_id) {
+            Some(chain) => chain,
+            None => {
+                let description = format!("no keys configured for {}", &self.config.chain_id);
+                return Ok(self.refuse(request, RefusalReason::UnauthorizedChain, description));
+            }
+        };
 
-        let (_, request_state) = parse_request(&request)?;
-        let mut chain_state = chain.state.lock().unwrap();
+        if !canonical {
+            let description = "request doesn't re-encode to the bytes received";
+
+            if chain.require_canonical_encoding {
+                return Ok(self.refuse(request, RefusalReason::NonCanonical, description));
+            }
+
+            debug!(
+                "[{}@{}] {}",
+                &self.config.chain_id, &self.config.addr, description
+            );
+        }
+
+        let (msg_type, request_state) = parse_request(&request, &chain.steps)?;
+        let provider = match chain.signing_key(msg_type) {
+            Some(key) => chain.keyring.get_provider(key),
+            None => chain.keyring.default_provider(),
+        }
+        .map(|p| p.to_string());
+        let result = self.sign_for_chain(chain, request, msg_type, request_state.clone());
+
+        let (error, reason) = match &result {
+            Ok(response) => (
+                response.error().map(|e| e.description.clone()),
+                response.error().and_then(RefusalReason::from_remote_error),
+            ),
+            Err(e) => (Some(e.to_string()), None),
+        };
+
+        chain.events.record(SignEvent::new(
+            msg_type,
+            &request_state,
+            provider,
+            error,
+            reason,
+        ));
+
+        result
+    }
+
+    /// Refuse to sign a request, logging the reason and reporting it to the
+    /// validator
+    fn refuse<R>(&self, request: R, reason: RefusalReason, description: impl Display) -> Response
+    where
+        R: TendermintRequest + Debug,
+    {
+        let msg_type = request
+            .msg_type()
+            .map(|msg_type| format!("{:?}", msg_type))
+            .unwrap_or_else(|| "request".to_owned());
+
+        let request_state = request
+            .consensus_state()
+            .map(|state| state.to_string())
+            .unwrap_or_default();
+
+        warn!(
+            reason = reason.as_str(),
+            transient = reason.is_transient(),
+            "[{}@{}] refusing {} at h/r/s {} ({}): {}",
+            &self.config.chain_id,
+            &self.config.addr,
+            msg_type,
+            request_state,
+            reason,
+            description
+        );
+
+        request.build_response(Some(reason.to_remote_error(description)))
+    }
 
-        if let Err(e) = chain_state.update_consensus_state(request_state) {
-            // Report double signing error back to the validator
-            if e.kind() == StateErrorKind::DoubleSign {
-                return self.handle_double_signing(
-                    request,
-                    &chain_state.consensus_state().block_id_prefix(),
+    /// Check the request against the chain's policies and state, and then
+    /// sign it using the chain's keyring
+    fn sign_for_chain<R>(
+        &self,
+        chain: &Chain,
+        mut request: R,
+        msg_type: SignedMsgType,
+        request_state: consensus::State,
+    ) -> Result<Response, Error>
+    where
+        R: TendermintRequest + Debug,
+    {
+        if let Some(description) = chain.signing_disabled_reason() {
+            return Ok(self.refuse(request, RefusalReason::Maintenance, description));
+        }
+
+        if chain.refuse_nil_votes && request_state.block_id.is_none() {
+            match msg_type {
+                SignedMsgType::PreVote | SignedMsgType::PreCommit => {
+                    let description = format!(
+                        "refusing to sign <nil> {:?} at h/r/s {} (refuse_nil_votes is set)",
+                        msg_type, request_state
+                    );
+
+                    return Ok(self.refuse(
+                        request,
+                        RefusalReason::MessageTypeNotAllowed,
+                        description,
+                    ));
+                }
+                SignedMsgType::Proposal => (),
+            }
+        }
+
+        if let Some(initial_height) = chain.initial_height {
+            if request_state.height < initial_height {
+                let description = format!(
+                    "attempted to sign at height {} which is below initial_height {}",
+                    request_state.height, initial_height
                 );
-            } else {
-                return Err(e.into());
+
+                return Ok(self.refuse(request, RefusalReason::HeightRegression, description));
+            }
+        }
+
+        if let Some(halt_height) = chain.halt_height() {
+            if request_state.height >= halt_height {
+                let description = format!(
+                    "attempted to sign at height {} which is at or above halt_height {}",
+                    request_state.height, halt_height
+                );
+
+                return Ok(self.refuse(request, RefusalReason::ConfiguredHalt, description));
+            }
+        }
+
+        if let Some(max_skew) = chain.max_clock_skew {
+            match msg_type {
+                SignedMsgType::PreVote | SignedMsgType::PreCommit => {
+                    if let Some(description) = check_clock_skew(&request, max_skew) {
+                        return Ok(self.refuse(
+                            request,
+                            RefusalReason::TimestampOutOfBounds,
+                            description,
+                        ));
+                    }
+                }
+                SignedMsgType::Proposal => (),
+            }
+        }
+
+        if let Some(ref bounds) = chain.block_time_bounds {
+            match msg_type {
+                SignedMsgType::PreVote | SignedMsgType::PreCommit => {
+                    let description = match request.timestamp() {
+                        Some(timestamp) => bounds.check(timestamp, SystemTime::now()),
+                        None => Some("no timestamp in vote".to_owned()),
+                    };
+
+                    if let Some(description) = description {
+                        return Ok(self.refuse(
+                            request,
+                            RefusalReason::TimestampOutOfBounds,
+                            description,
+                        ));
+                    }
+                }
+                SignedMsgType::Proposal => (),
+            }
+        }
+
+        let mut chain_state = chain.state.lock().unwrap();
+
+        if let Some(description) =
+            chain.check_height_gap(chain_state.consensus_state().height, request_state.height)
+        {
+            return Ok(self.refuse(request, RefusalReason::Maintenance, description));
+        }
+
+        if self.reuse_signature(&mut request, &chain_state, &request_state)? {
+            return Ok(request.build_response(None));
+        }
+
+        // Identical h/r/s and block ID, e.g. re-requested after a restart,
+        // when the previous signature is no longer in memory
+        if request_state == *chain_state.consensus_state() {
+            info!(
+                "[{}@{}] signing h/r/s {} (block {}) again: already signed, but the previous signature can't be reused",
+                &self.config.chain_id,
+                &self.config.addr,
+                request_state,
+                request_state.block_id_prefix()
+            );
+        }
+
+        if let Err(e) = chain_state.update_consensus_state(request_state.clone()) {
+            match e.kind() {
+                // Report double signing error back to the validator
+                StateErrorKind::DoubleSign => {
+                    chain.record_double_sign_attempt();
+
+                    return self.handle_double_signing(
+                        request,
+                        msg_type,
+                        &request_state,
+                        &chain_state.consensus_state().block_id_prefix(),
+                    );
+                }
+                StateErrorKind::HeightRegression
+                | StateErrorKind::RoundRegression
+                | StateErrorKind::StepRegression => {
+                    return Ok(self.refuse(request, RefusalReason::HeightRegression, e));
+                }
+                StateErrorKind::SyncError => {
+                    if chain.halt_on_state_error {
+                        chain.halt(e.to_string());
+                    }
+
+                    return Err(e.into());
+                }
             }
         }
 
@@ -166,20 +467,183 @@ impl Session {
             }
         }
 
+        if let Some(timeout) = chain.confirm_timeout {
+            let description = format!("{:?} at h/r/s {}", msg_type, &request_state);
+
+            if let Err(e) = confirm::confirm(&description, timeout) {
+                return Ok(self.refuse(request, RefusalReason::Maintenance, e));
+            }
+        }
+
         let mut to_sign = vec![];
         request.sign_bytes(self.config.chain_id, &mut to_sign)?;
 
-        // TODO(ismail): figure out which key to use here instead of taking the only key
         let started_at = Instant::now();
-        let signature = chain.keyring.sign_ed25519(None, &to_sign)?;
+        let signature = match chain
+            .keyring
+            .sign_ed25519(chain.signing_key(msg_type), &to_sign)
+        {
+            Ok(signature) => signature,
+            Err(e) if *e.kind() == Overloaded => {
+                return Ok(self.refuse(request, RefusalReason::Overload, e));
+            }
+            Err(e) => return Err(e),
+        };
+
+        let sign_bytes_hash = if chain.log_sign_bytes_hash {
+            Some(Sha256::digest(&to_sign))
+        } else {
+            None
+        };
 
-        self.log_signing_request(&request, started_at).unwrap();
+        self.log_signing_request(msg_type, &request_state, started_at, sign_bytes_hash);
 
         request.set_signature(&signature);
+        chain.count_signature(&mut chain_state, chain.signing_key(msg_type));
+
+        chain_state.record_signed(LastSigned {
+            consensus_state: request_state,
+            timestamp: request.timestamp_msg(),
+            sign_bytes: to_sign,
+            signature,
+        });
 
         Ok(request.build_response(None))
     }
 
+    /// If the request is for the same height, round, step, and block ID as
+    /// the last message signed, and differs from it at most in its timestamp,
+    /// answer it idempotently with the previous timestamp and signature
+    /// rather than signing a second message.
+    ///
+    /// Returns `true` if the previous signature was set on the request.
+    fn reuse_signature<R>(
+        &self,
+        request: &mut R,
+        chain_state: &State,
+        request_state: &consensus::State,
+    ) -> Result<bool, Error>
+    where
+        R: TendermintRequest + Debug,
+    {
+        let last_signed = match chain_state.last_signed(request_state) {
+            Some(last_signed) => last_signed,
+            None => return Ok(false),
+        };
+
+        let timestamp = request.timestamp_msg();
+        request.set_timestamp_msg(last_signed.timestamp.clone());
+
+        let mut to_sign = vec![];
+        request.sign_bytes(self.config.chain_id, &mut to_sign)?;
+
+        if to_sign != last_signed.sign_bytes {
+            request.set_timestamp_msg(timestamp);
+            return Ok(false);
+        }
+
+        if timestamp != last_signed.timestamp {
+            warn!(
+                "[{}@{}] re-request for h/r/s {} differs only in timestamp; returning previous signature",
+                &self.config.chain_id, &self.config.addr, request_state
+            );
+        }
+
+        request.set_signature(&last_signed.signature);
+        Ok(true)
+    }
+
+    /// Sign raw bytes (`SignBytesRequest` extension), if enabled for this chain
+    fn sign_bytes(&mut self, request: &SignBytesRequest) -> Result<Response, Error> {
+        let registry = chain::REGISTRY.get();
+
+        // unwrap is acceptable here as chain presence is validated in client.rs's
+        // `register_chain` function.
+        let chain = registry.get_chain(&self.config.chain_id).unwrap();
+
+        // Raw bytes are signed with the key reported to the validator, i.e.
+        // the vote key (if `signing_keys` are configured)
+        let public_key = chain.public_key();
+
+        let refusal = match chain.sign_bytes_domain {
+            None => Some((
+                RefusalReason::MessageTypeNotAllowed,
+                "SignBytesRequest is not enabled for this chain".to_owned(),
+            )),
+            Some(ref domain) if !request.bytes.starts_with(domain) => Some((
+                RefusalReason::MessageTypeNotAllowed,
+                "bytes to sign are missing the required domain prefix".to_owned(),
+            )),
+            Some(_) => match public_key {
+                Ok(_) => chain
+                    .signing_disabled_reason()
+                    .map(|description| (RefusalReason::Maintenance, description)),
+                Err(ref e) => Some((
+                    RefusalReason::MessageTypeNotAllowed,
+                    format!(
+                        "no key to sign raw bytes with ({}): configure a vote key",
+                        e
+                    ),
+                )),
+            },
+        };
+
+        let started_at = Instant::now();
+
+        let result = match refusal {
+            Some(refusal) => Err(refusal),
+            None => match chain
+                .keyring
+                .sign_ed25519(public_key.as_ref().ok(), &request.bytes)
+            {
+                Ok(signature) => Ok(signature),
+                Err(e) if *e.kind() == Overloaded => Err((RefusalReason::Overload, e.to_string())),
+                Err(e) => return Err(e),
+            },
+        };
+
+        let signature = match result {
+            Ok(signature) => signature,
+            Err((reason, description)) => {
+                warn!(
+                    reason = reason.as_str(),
+                    transient = reason.is_transient(),
+                    "[{}@{}] refusing SignBytesRequest ({}): {}",
+                    &self.config.chain_id,
+                    &self.config.addr,
+                    reason,
+                    description
+                );
+
+                return Ok(Response::SignedBytes(SignedBytesResponse {
+                    signature: vec![],
+                    err: Some(reason.to_remote_error(description)),
+                }));
+            }
+        };
+
+        info!(
+            "[{}@{}] signed {} raw bytes ({} ms)",
+            &self.config.chain_id,
+            &self.config.addr,
+            request.bytes.len(),
+            started_at.elapsed().as_millis(),
+        );
+
+        // A signature was made, so the key was found
+        let public_key = public_key?;
+        chain.count_signature(&mut chain.state.lock().unwrap(), Some(&public_key));
+
+        let public_key = public_key
+            .ed25519()
+            .ok_or_else(|| Error::from(InvalidKey))?;
+
+        Ok(Response::SignedBytes(SignedBytesResponse {
+            signature: chain.sign_bytes_output.encode(&public_key, &signature),
+            err: None,
+        }))
+    }
+
     /// Reply to a ping request
     fn reply_ping(&mut self, _request: &PingRequest) -> Response {
         debug!("replying with PingResponse");
@@ -193,43 +657,62 @@ impl Session {
         let registry = chain::REGISTRY.get();
         let chain = registry.get_chain(&self.config.chain_id).unwrap();
 
-        Ok(Response::PublicKey(PubKeyResponse::from(
-            *chain.keyring.default_pubkey()?,
-        )))
+        let public_key = *chain.public_key()?;
+
+        Ok(match chain.pubkey_encoding {
+            PubKeyEncoding::Amino => Response::PublicKey(PubKeyResponse::from(public_key)),
+            PubKeyEncoding::Proto => {
+                Response::ProtoPublicKey(rpc::proto::Message::from(public_key))
+            }
+        })
     }
 
     /// Write an INFO logline about a signing request
-    fn log_signing_request<R>(&self, request: &R, started_at: Instant) -> Result<(), Error>
-    where
-        R: TendermintRequest + Debug,
-    {
-        let (msg_type, request_state) = parse_request(request)?;
-
-        info!(
-            "[{}@{}] signed {:?}:{} at h/r/s {} ({} ms)",
-            &self.config.chain_id,
-            &self.config.addr,
-            msg_type,
-            request_state.block_id_prefix(),
-            request_state,
-            started_at.elapsed().as_millis(),
-        );
-
-        Ok(())
+    fn log_signing_request(
+        &self,
+        msg_type: SignedMsgType,
+        request_state: &consensus::State,
+        started_at: Instant,
+        sign_bytes_hash: Option<impl AsRef<[u8]>>,
+    ) {
+        let elapsed_ms = started_at.elapsed().as_millis();
+
+        match sign_bytes_hash {
+            Some(hash) => info!(
+                "[{}@{}] signed {:?}:{} at h/r/s {} ({} ms, sign bytes sha256:{})",
+                &self.config.chain_id,
+                &self.config.addr,
+                msg_type,
+                request_state.block_id_prefix(),
+                request_state,
+                elapsed_ms,
+                String::from_utf8(hex::encode(hash)).unwrap(),
+            ),
+            None => info!(
+                "[{}@{}] signed {:?}:{} at h/r/s {} ({} ms)",
+                &self.config.chain_id,
+                &self.config.addr,
+                msg_type,
+                request_state.block_id_prefix(),
+                request_state,
+                elapsed_ms,
+            ),
+        }
     }
 
     /// Handle attempted double signing
     fn handle_double_signing<R>(
         &self,
         request: R,
+        msg_type: SignedMsgType,
+        request_state: &consensus::State,
         original_block_id: &str,
     ) -> Result<Response, Error>
     where
         R: TendermintRequest + Debug,
     {
-        let (msg_type, request_state) = parse_request(&request)?;
-
         error!(
+            reason = RefusalReason::DoubleSign.as_str(),
             "[{}:{}] attempted double sign {:?} at h/r/s: {} ({} != {})",
             &self.config.chain_id,
             &self.config.addr,
@@ -239,14 +722,49 @@ impl Session {
             request_state.block_id_prefix()
         );
 
-        let remote_err = RemoteError::double_sign(request.height().unwrap());
+        let remote_err = RefusalReason::DoubleSign.to_remote_error(format!(
+            "double signing requested at height: {}",
+            request.height().unwrap()
+        ));
+
         Ok(request.build_response(Some(remote_err)))
     }
 }
 
-/// Parse the consensus state from an incoming request
+/// Get a seed for the Secret Connection's ephemeral keys from the RNG of a
+/// provider holding the given chain's keys
+fn provider_ephemeral_seed(chain_id: &chain::Id) -> Result<Zeroizing<[u8; 32]>, Error> {
+    let registry = chain::REGISTRY.get();
+
+    let chain = registry
+        .get_chain(chain_id)
+        .ok_or_else(|| format_err!(InvalidKey, "no keys configured for chain: {}", chain_id))?;
+
+    let mut seed = Zeroizing::new([0u8; 32]);
+
+    let provider = chain.keyring.random_bytes(&mut *seed).map_err(|e| {
+        format_err!(
+            ConfigError,
+            "[{}] handshake_rng = \"provider\": {}",
+            chain_id,
+            e
+        )
+    })?;
+
+    debug!(
+        "[{}] ephemeral key seed generated by {}",
+        chain_id, provider
+    );
+    Ok(seed)
+}
+
+/// Parse the consensus state from an incoming request, assigning its step
+/// according to the chain's step configuration
 // TODO(tarcieri): fix the upstream Amino parser to do this correctly for us
-fn parse_request<R>(request: &R) -> Result<(SignedMsgType, consensus::State), Error>
+fn parse_request<R>(
+    request: &R,
+    steps: &StepConfig,
+) -> Result<(SignedMsgType, consensus::State), Error>
 where
     R: TendermintRequest + Debug,
 {
@@ -258,11 +776,34 @@ where
         .consensus_state()
         .ok_or_else(|| format_err!(ProtocolError, "no consensus state in request"))?;
 
-    consensus_state.step = match msg_type {
-        SignedMsgType::Proposal => 0,
-        SignedMsgType::PreVote => 1,
-        SignedMsgType::PreCommit => 2,
-    };
+    consensus_state.step = steps.step(msg_type);
 
     Ok((msg_type, consensus_state))
 }
+
+/// Check the timestamp of a vote is within `max_skew` of the local clock,
+/// returning a description of the problem if not
+fn check_clock_skew<R>(request: &R, max_skew: Duration) -> Option<String>
+where
+    R: TendermintRequest + Debug,
+{
+    let timestamp = match request.timestamp() {
+        Some(timestamp) => timestamp,
+        None => return Some("no timestamp in vote".to_owned()),
+    };
+
+    let skew = match timestamp.duration_since(SystemTime::now()) {
+        Ok(ahead) => ahead,
+        Err(e) => e.duration(),
+    };
+
+    if skew > max_skew {
+        Some(format!(
+            "vote timestamp skewed by {} ms from local clock (max {} ms)",
+            skew.as_millis(),
+            max_skew.as_millis()
+        ))
+    } else {
+        None
+    }
+}
@@ -0,0 +1,312 @@
+//! Signing server: serves this KMS's keys to front-end KMS instances.
+//!
+//! This allows keys to be kept on a single backend (e.g. in a secure
+//! enclave) while front-end KMS instances (using the `remote` provider)
+//! handle the connections to validators. Front-ends connect over an
+//! authenticated Secret Connection, and must be listed in the server's
+//! `allowed_peer_ids`.
+//!
+//! The server signs whatever front-ends send it: double-signing protection
+//! (and the consensus state it relies on) is entirely the front-end's
+//! responsibility. To remain safe, each chain must be signed for by exactly
+//! one front-end. Pausing or halting a chain on the server stops it signing
+//! for that chain.
+
+use crate::{
+    chain,
+    config::{sign_server::SignServerConfig, validator::load_or_generate_secret_key},
+    connection::secret_connection::{PublicKey, SecretConnection},
+    error::{Error, ErrorKind::*},
+    prelude::*,
+    rpc::MAX_MSG_LEN,
+};
+use prost_amino::Message;
+use prost_amino_derive::Message;
+use signatory::{ed25519, public_key::PublicKeyed};
+use signatory_dalek::Ed25519Signer;
+use std::{
+    io::{self, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    thread,
+    time::Duration,
+};
+use subtle::ConstantTimeEq;
+use tendermint::{net, node, TendermintKey};
+
+/// Default connection timeout in seconds
+const DEFAULT_TIMEOUT: u16 = 10;
+
+/// Request to sign a message with one of a chain's consensus keys
+#[derive(Clone, PartialEq, Message)]
+pub struct SignRequest {
+    /// Chain the key is registered for
+    #[prost_amino(string, tag = "1")]
+    pub chain_id: String,
+
+    /// Ed25519 public key to sign with
+    #[prost_amino(bytes, tag = "2")]
+    pub public_key: Vec<u8>,
+
+    /// Message to sign
+    #[prost_amino(bytes, tag = "3")]
+    pub msg: Vec<u8>,
+}
+
+/// Response to a `SignRequest`: either a signature or an error
+#[derive(Clone, PartialEq, Message)]
+pub struct SignResponse {
+    /// Ed25519 signature (empty on error)
+    #[prost_amino(bytes, tag = "1")]
+    pub signature: Vec<u8>,
+
+    /// Description of the error (empty on success)
+    #[prost_amino(string, tag = "2")]
+    pub error: String,
+}
+
+/// Write a length-delimited message to a Secret Connection. Messages must
+/// fit in a single frame.
+pub fn write_message<M: Message, W: Write>(conn: &mut W, msg: &M) -> Result<(), Error> {
+    let mut buf = vec![];
+    msg.encode_length_delimited(&mut buf)?;
+
+    if buf.len() > MAX_MSG_LEN {
+        fail!(
+            ProtocolError,
+            "sign server message too large: {} bytes (max {})",
+            buf.len(),
+            MAX_MSG_LEN
+        );
+    }
+
+    conn.write_all(&buf)?;
+    Ok(())
+}
+
+/// Read a length-delimited message (sent with `write_message`) from a
+/// Secret Connection, returning `None` if the connection was closed
+pub fn read_message<M: Message + Default, R: Read>(conn: &mut R) -> Result<Option<M>, Error> {
+    let mut buf = vec![0; MAX_MSG_LEN];
+
+    let n = match conn.read(&mut buf) {
+        Ok(n) => n,
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+
+    M::decode_length_delimited(&buf[..n])
+        .map(Some)
+        .map_err(|e| format_err!(ProtocolError, "malformed sign server message: {}", e).into())
+}
+
+/// Signing server
+pub struct Server {
+    /// Listener for front-end connections
+    listener: TcpListener,
+
+    /// Secret Connection identity key
+    secret_key: ed25519::Seed,
+
+    /// Secret Connection identity public key
+    public_key: PublicKey,
+
+    /// Front-ends allowed to connect
+    allowed_peer_ids: Vec<node::Id>,
+
+    /// Read/write timeout of connections
+    timeout: Duration,
+}
+
+impl Server {
+    /// Bind the signing server to the configured listen address
+    pub fn new(config: &SignServerConfig) -> Result<Self, Error> {
+        let addr = match &config.laddr {
+            net::Address::Tcp { host, port, .. } => format!("{}:{}", host, port),
+            net::Address::Unix { .. } => fail!(
+                ConfigError,
+                "sign server does not support Unix sockets: {}",
+                &config.laddr
+            ),
+        };
+
+        if config.allowed_peer_ids.is_empty() {
+            fail!(
+                ConfigError,
+                "sign server requires at least one entry in `allowed_peer_ids`"
+            );
+        }
+
+        let secret_key = load_or_generate_secret_key(&config.secret_key)?;
+        let public_key = Ed25519Signer::from(&secret_key)
+            .public_key()
+            .map(PublicKey::from)
+            .map_err(|_| Error::from(InvalidKey))?;
+
+        let listener = TcpListener::bind(&addr).map_err(|e| {
+            format_err!(
+                IoError,
+                "couldn't start sign server on {}: {}",
+                &config.laddr,
+                e
+            )
+        })?;
+
+        info!(
+            "sign server listening on {} (node ID: {})",
+            &config.laddr, &public_key
+        );
+
+        Ok(Self {
+            listener,
+            secret_key,
+            public_key,
+            allowed_peer_ids: config.allowed_peer_ids.clone(),
+            timeout: Duration::from_secs(config.timeout.unwrap_or(DEFAULT_TIMEOUT).into()),
+        })
+    }
+
+    /// Address the server is listening on
+    pub fn local_addr(&self) -> Result<SocketAddr, Error> {
+        Ok(self.listener.local_addr()?)
+    }
+
+    /// Run the signing server in a background thread
+    pub fn spawn(self) -> Result<thread::JoinHandle<()>, Error> {
+        thread::Builder::new()
+            .name("sign-server".to_owned())
+            .spawn(move || self.run())
+            .map_err(|e| format_err!(IoError, "error spawning sign server thread: {}", e).into())
+    }
+
+    /// Accept connections from front-ends, handling each in its own thread
+    pub fn run(self) {
+        for stream in self.listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(e) => {
+                    error!("sign server: error accepting connection: {}", e);
+                    continue;
+                }
+            };
+
+            let peer_addr = stream
+                .peer_addr()
+                .map(|addr| addr.to_string())
+                .unwrap_or_else(|_| "unknown".to_owned());
+
+            let signer = Ed25519Signer::from(&self.secret_key);
+            let public_key = self.public_key;
+            let allowed_peer_ids = self.allowed_peer_ids.clone();
+            let timeout = self.timeout;
+
+            let result = thread::Builder::new()
+                .name(format!("sign-server {}", peer_addr))
+                .spawn(move || {
+                    let result =
+                        handle_connection(stream, &signer, &public_key, &allowed_peer_ids, timeout);
+
+                    if let Err(e) = result {
+                        error!("sign server: [{}] {}", peer_addr, e);
+                    }
+                });
+
+            if let Err(e) = result {
+                error!("sign server: error spawning connection thread: {}", e);
+            }
+        }
+    }
+}
+
+/// Authenticate a front-end connection, then answer its requests until it
+/// disconnects
+fn handle_connection(
+    stream: TcpStream,
+    signer: &Ed25519Signer,
+    public_key: &PublicKey,
+    allowed_peer_ids: &[node::Id],
+    timeout: Duration,
+) -> Result<(), Error> {
+    let socket = stream.try_clone()?;
+    socket.set_read_timeout(Some(timeout))?;
+    socket.set_write_timeout(Some(timeout))?;
+
+    let mut conn = SecretConnection::new(stream, public_key, signer)?;
+    let peer_id = conn.remote_pubkey().peer_id();
+
+    if !allowed_peer_ids
+        .iter()
+        .any(|allowed| allowed.ct_eq(&peer_id).unwrap_u8() == 1)
+    {
+        fail!(
+            VerificationError,
+            "refusing connection from unauthorized peer ID {}",
+            peer_id
+        );
+    }
+
+    info!("sign server: accepted connection from {}", peer_id);
+
+    loop {
+        // Requests may be arbitrarily far apart, so only time out writes
+        // once the connection is established
+        socket.set_read_timeout(None)?;
+
+        let request = match read_message::<SignRequest, _>(&mut conn)? {
+            Some(request) => request,
+            None => {
+                info!("sign server: {} disconnected", peer_id);
+                return Ok(());
+            }
+        };
+
+        let response = match sign(&request) {
+            Ok(signature) => SignResponse {
+                signature: signature.as_ref().to_vec(),
+                error: String::new(),
+            },
+            Err(e) => {
+                warn!(
+                    "sign server: refusing request from {} for chain {}: {}",
+                    peer_id, request.chain_id, e
+                );
+
+                SignResponse {
+                    signature: vec![],
+                    error: e.to_string(),
+                }
+            }
+        };
+
+        write_message(&mut conn, &response)?;
+    }
+}
+
+/// Sign the requested message using the chain's keyring
+fn sign(request: &SignRequest) -> Result<ed25519::Signature, Error> {
+    let chain_id = request
+        .chain_id
+        .parse::<chain::Id>()
+        .map_err(|e| format_err!(ProtocolError, "invalid chain ID: {}", e))?;
+
+    let public_key = ed25519::PublicKey::from_bytes(&request.public_key)
+        .ok_or_else(|| format_err!(InvalidKey, "invalid Ed25519 public key"))?;
+
+    let registry = chain::REGISTRY.get();
+
+    let chain = registry.get_chain(&chain_id).ok_or_else(|| {
+        format_err!(
+            InvalidKey,
+            "chain `{}` isn't registered with the sign server",
+            chain_id
+        )
+    })?;
+
+    if let Some(reason) = chain.signing_disabled_reason() {
+        fail!(SigningError, "{}", reason);
+    }
+
+    chain.keyring.sign_ed25519(
+        Some(&TendermintKey::ConsensusKey(public_key.into())),
+        &request.msg,
+    )
+}
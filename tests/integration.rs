@@ -17,6 +17,8 @@ use std::{
     net::{TcpListener, TcpStream},
     os::unix::net::{UnixListener, UnixStream},
     process::{Child, Command},
+    thread,
+    time::Duration,
 };
 use tempfile::NamedTempFile;
 use tendermint::amino_types::{self, *};
@@ -26,6 +28,7 @@ use tmkms::{
         unix::UnixConnection,
     },
     keyring::SecretKeyEncoding,
+    rpc::{SignBytesRequest, SignedBytesResponse},
 };
 
 /// Integration tests for the KMS command-line interface
@@ -109,12 +112,29 @@ impl KmsProcess {
 
     /// Spawn the KMS process and connect to the Unix listener
     pub fn create_unix() -> Self {
+        Self::create_unix_with_chain_config("")
+    }
+
+    /// Spawn the KMS process with additional `[[chain]]` settings, and
+    /// connect to the Unix listener
+    pub fn create_unix_with_chain_config(chain_config: &str) -> Self {
+        Self::create_unix_with(|socket_path| {
+            KmsProcess::create_unix_config(socket_path, chain_config)
+        })
+    }
+
+    /// Spawn the KMS process with the config file created by the given
+    /// function (from the socket path), and connect to the Unix listener
+    pub fn create_unix_with<F>(create_config: F) -> Self
+    where
+        F: FnOnce(&str) -> NamedTempFile,
+    {
         // Create a random socket path and a config file
         let mut rng = rand::thread_rng();
         let letter: char = rng.gen_range(b'a', b'z') as char;
         let number: u32 = rng.gen_range(0, 999999);
         let socket_path = format!("/tmp/tmkms-{}{:06}.sock", letter, number);
-        let config = KmsProcess::create_unix_config(&socket_path);
+        let config = create_config(&socket_path);
 
         // Start listening for connections via the Unix socket
         let listener = UnixListener::bind(socket_path).unwrap();
@@ -142,6 +162,7 @@ impl KmsProcess {
             [[chain]]
             id = "test_chain_id"
             key_format = {{ type = "bech32", account_key_prefix = "cosmospub", consensus_key_prefix = "cosmosvalconspub" }}
+            enabled = true
 
             [[validator]]
             addr = "tcp://{}@127.0.0.1:{}"
@@ -163,7 +184,7 @@ impl KmsProcess {
     }
 
     /// Create a config file for a UNIX KMS and return its path
-    fn create_unix_config(socket_path: &str) -> NamedTempFile {
+    fn create_unix_config(socket_path: &str, chain_config: &str) -> NamedTempFile {
         let mut config_file = NamedTempFile::new().unwrap();
         writeln!(
             config_file,
@@ -171,6 +192,10 @@ impl KmsProcess {
             [[chain]]
             id = "test_chain_id"
             key_format = {{ type = "bech32", account_key_prefix = "cosmospub", consensus_key_prefix = "cosmosvalconspub" }}
+            enabled = true
+            prewarm = true
+            unknown_messages = "ignore"
+            {}
 
             [[validator]]
             addr = "unix://{}"
@@ -183,7 +208,7 @@ impl KmsProcess {
             key_format = "base64"
             path = "{}"
         "#,
-            socket_path, SIGNING_KEY_PATH
+            chain_config, socket_path, SIGNING_KEY_PATH
         )
         .unwrap();
 
@@ -213,6 +238,13 @@ impl KmsProcess {
     }
 }
 
+impl Drop for KmsProcess {
+    fn drop(&mut self) {
+        // Already killed if it belongs to a `ProtocolTester`
+        let _ = self.process.kill();
+    }
+}
+
 /// A struct to hold protocol integration tests contexts
 struct ProtocolTester {
     tcp_device: KmsProcess,
@@ -425,6 +457,86 @@ fn test_handle_and_sign_vote() {
     });
 }
 
+#[test]
+fn test_resign_vote_differing_only_in_timestamp() {
+    let (pub_key, _) = test_key();
+
+    let dt = "2018-02-11T07:09:22.765Z".parse::<DateTime<Utc>>().unwrap();
+    let t = TimeMsg {
+        seconds: dt.timestamp(),
+        nanos: dt.timestamp_subsec_nanos() as i32,
+    };
+
+    ProtocolTester::apply(|mut pt| {
+        let mut responses = vec![];
+
+        // The same precommit re-requested with a later timestamp is answered
+        // with the original timestamp and signature
+        for delay in 0..2 {
+            let vote_msg = amino_types::vote::Vote {
+                vote_type: 0x02,
+                height: 12345,
+                round: 2,
+                timestamp: Some(TimeMsg {
+                    seconds: t.seconds + delay,
+                    nanos: t.nanos,
+                }),
+                block_id: Some(BlockId {
+                    hash: b"some hash00000000000000000000000".to_vec(),
+                    parts_header: Some(PartsSetHeader {
+                        total: 1000000,
+                        hash: b"parts_hash0000000000000000000000".to_vec(),
+                    }),
+                }),
+                validator_address: vec![
+                    0xa3, 0xb2, 0xcc, 0xdd, 0x71, 0x86, 0xf1, 0x68, 0x5f, 0x21, 0xf2, 0x48, 0x2a,
+                    0xf4, 0xfb, 0x34, 0x46, 0xa8, 0x4b, 0x35,
+                ],
+                validator_index: 56789,
+                signature: vec![],
+            };
+
+            let svr = amino_types::vote::SignVoteRequest {
+                vote: Some(vote_msg),
+            };
+            let mut buf = vec![];
+            svr.encode(&mut buf).unwrap();
+            pt.write_all(&buf).unwrap();
+
+            // receive response:
+            let mut resp_buf = vec![0u8; 1024];
+            pt.read(&mut resp_buf).unwrap();
+
+            let actual_len = extract_actual_len(&resp_buf).unwrap();
+            let mut resp = vec![0u8; actual_len as usize];
+            resp.copy_from_slice(&resp_buf[..actual_len as usize]);
+
+            let v_resp =
+                vote::SignedVoteResponse::decode(resp.as_ref()).expect("decoding vote failed");
+
+            responses.push(
+                v_resp
+                    .vote
+                    .expect("vote should be embedded int the response but none was found"),
+            );
+        }
+
+        assert_eq!(responses[1].timestamp, Some(t));
+        assert_eq!(responses[0].signature, responses[1].signature);
+
+        let svr = amino_types::vote::SignVoteRequest {
+            vote: Some(responses[1].clone()),
+        };
+        let mut sign_bytes: Vec<u8> = vec![];
+        svr.sign_bytes("test_chain_id".into(), &mut sign_bytes)
+            .unwrap();
+
+        let verifier = Ed25519Verifier::from(&pub_key);
+        let signature = ed25519::Signature::from_bytes(&responses[1].signature).unwrap();
+        verifier.verify(&sign_bytes, &signature).unwrap();
+    });
+}
+
 #[test]
 #[should_panic]
 fn test_exceed_max_height() {
@@ -531,3 +643,332 @@ fn test_handle_and_sign_ping_pong() {
         PingResponse::decode(resp.as_ref()).expect("decoding ping response failed");
     });
 }
+
+#[test]
+fn test_handle_repeated_pings() {
+    ProtocolTester::apply(|mut pt| {
+        // Keepalive pings on an otherwise idle connection are each answered,
+        // including when interleaved with other requests
+        for request in 0..4 {
+            let mut buf = vec![];
+
+            if request == 2 {
+                PubKeyRequest {}.encode(&mut buf).unwrap();
+            } else {
+                PingRequest {}.encode(&mut buf).unwrap();
+            }
+
+            pt.write_all(&buf).unwrap();
+
+            // receive response:
+            let mut resp_buf = vec![0u8; 1024];
+            pt.read(&mut resp_buf).unwrap();
+
+            let actual_len = extract_actual_len(&resp_buf).unwrap();
+            let mut resp = vec![0u8; actual_len as usize];
+            resp.copy_from_slice(&resp_buf[..actual_len as usize]);
+
+            if request == 2 {
+                PubKeyResponse::decode(resp.as_ref()).expect("decoding public key failed");
+            } else {
+                PingResponse::decode(resp.as_ref()).expect("decoding ping response failed");
+            }
+        }
+    });
+}
+
+#[test]
+fn test_sign_bytes_rejected_unless_enabled() {
+    ProtocolTester::apply(|mut pt| {
+        let mut buf = vec![];
+        SignBytesRequest {
+            bytes: b"example-domain:hello".to_vec(),
+        }
+        .encode(&mut buf)
+        .unwrap();
+        pt.write_all(&buf).unwrap();
+
+        // receive response:
+        let mut resp_buf = vec![0u8; 1024];
+        pt.read(&mut resp_buf).unwrap();
+
+        let actual_len = extract_actual_len(&resp_buf).unwrap();
+        let mut resp = vec![0u8; actual_len as usize];
+        resp.copy_from_slice(&resp_buf[..actual_len as usize]);
+
+        let sb_resp = SignedBytesResponse::decode(resp.as_ref())
+            .expect("decoding signed bytes response failed");
+        assert!(sb_resp.signature.is_empty());
+
+        let err = sb_resp.err.expect("expected an error");
+        assert!(
+            err.description.starts_with("message-type-not-allowed: "),
+            "{}",
+            err.description
+        );
+    });
+}
+
+#[test]
+fn test_unknown_message_policies() {
+    ProtocolTester::apply(|mut pt| {
+        // length-prefixed message with an unregistered amino prefix
+        let unknown_msg = [0x04, 0xde, 0xad, 0xbe, 0xef];
+
+        // Unix socket KMS is configured with `unknown_messages = "ignore"`,
+        // so the connection keeps working afterwards
+        pt.unix_connection.write_all(&unknown_msg).unwrap();
+        thread::sleep(Duration::from_millis(200));
+
+        let mut buf = vec![];
+        PingRequest {}.encode(&mut buf).unwrap();
+        pt.unix_connection.write_all(&buf).unwrap();
+
+        let mut resp_buf = vec![0u8; 1024];
+        let len = pt.unix_connection.read(&mut resp_buf).unwrap();
+        assert!(len > 0, "connection closed after unknown message");
+
+        let actual_len = extract_actual_len(&resp_buf).unwrap();
+        PingResponse::decode(&resp_buf[..actual_len as usize])
+            .expect("decoding ping response failed");
+
+        // TCP KMS uses the default policy, which closes the connection
+        pt.tcp_connection.write_all(&unknown_msg).unwrap();
+
+        match pt.tcp_connection.read(&mut resp_buf) {
+            Ok(0) | Err(_) => (),
+            Ok(len) => panic!("expected the connection to close, read {} bytes", len),
+        }
+    });
+}
+
+#[test]
+fn test_skewed_vote_refused() {
+    let state_dir = tempfile::tempdir().unwrap();
+    let kms = KmsProcess::create_unix_with_chain_config(&format!(
+        "max_clock_skew_ms = 5000\nstate_file = \"{}\"",
+        state_dir.path().join("state.json").display()
+    ));
+    let mut conn = kms.create_connection();
+
+    let dt = "2018-02-11T07:09:22.765Z".parse::<DateTime<Utc>>().unwrap();
+
+    let svr = amino_types::vote::SignVoteRequest {
+        vote: Some(amino_types::vote::Vote {
+            vote_type: 0x01,
+            height: 12345,
+            round: 2,
+            timestamp: Some(TimeMsg {
+                seconds: dt.timestamp(),
+                nanos: dt.timestamp_subsec_nanos() as i32,
+            }),
+            block_id: None,
+            validator_address: vec![
+                0xa3, 0xb2, 0xcc, 0xdd, 0x71, 0x86, 0xf1, 0x68, 0x5f, 0x21, 0xf2, 0x48, 0x2a, 0xf4,
+                0xfb, 0x34, 0x46, 0xa8, 0x4b, 0x35,
+            ],
+            validator_index: 56789,
+            signature: vec![],
+        }),
+    };
+
+    let mut buf = vec![];
+    svr.encode(&mut buf).unwrap();
+    conn.write_all(&buf).unwrap();
+
+    // The vote is refused, rather than the connection being closed
+    let mut resp_buf = vec![0u8; 1024];
+    let len = conn.read(&mut resp_buf).unwrap();
+    assert!(len > 0, "connection closed instead of refusing the vote");
+
+    let actual_len = extract_actual_len(&resp_buf).unwrap();
+    let v_resp = vote::SignedVoteResponse::decode(&resp_buf[..actual_len as usize])
+        .expect("decoding vote failed");
+
+    let err = v_resp.err.expect("expected an error");
+    assert!(
+        err.description.starts_with("timestamp-out-of-bounds: "),
+        "{}",
+        err.description
+    );
+
+    let mut buf = vec![];
+    PingRequest {}.encode(&mut buf).unwrap();
+    conn.write_all(&buf).unwrap();
+
+    let len = conn.read(&mut resp_buf).unwrap();
+    assert!(len > 0, "connection closed after refusal");
+
+    let actual_len = extract_actual_len(&resp_buf).unwrap();
+    PingResponse::decode(&resp_buf[..actual_len as usize]).expect("decoding ping response failed");
+}
+
+#[test]
+fn test_block_time_bounds_only_apply_to_votes() {
+    let state_dir = tempfile::tempdir().unwrap();
+    let kms = KmsProcess::create_unix_with_chain_config(&format!(
+        "block_time_bounds = {{ max_past_ms = 60000 }}\nstate_file = \"{}\"",
+        state_dir.path().join("state.json").display()
+    ));
+    let mut conn = kms.create_connection();
+
+    // Proposals are signed regardless of their timestamps
+    let dt = "2018-02-11T07:09:22.765Z".parse::<DateTime<Utc>>().unwrap();
+
+    let spr = amino_types::proposal::SignProposalRequest {
+        proposal: Some(amino_types::proposal::Proposal {
+            msg_type: amino_types::SignedMsgType::Proposal.to_u32(),
+            height: 12345,
+            round: 1,
+            timestamp: Some(TimeMsg {
+                seconds: dt.timestamp(),
+                nanos: dt.timestamp_subsec_nanos() as i32,
+            }),
+            pol_round: -1,
+            block_id: None,
+            signature: vec![],
+        }),
+    };
+
+    let mut buf = vec![];
+    spr.encode(&mut buf).unwrap();
+    conn.write_all(&buf).unwrap();
+
+    let mut resp_buf = vec![0u8; 1024];
+    let len = conn.read(&mut resp_buf).unwrap();
+    assert!(len > 0, "connection closed instead of signing the proposal");
+
+    let actual_len = extract_actual_len(&resp_buf).unwrap();
+    let p_resp = proposal::SignedProposalResponse::decode(&resp_buf[..actual_len as usize])
+        .expect("decoding proposal failed");
+    assert!(p_resp.err.is_none(), "{:?}", p_resp.err);
+
+    // Votes without a timestamp are refused, rather than the connection
+    // being closed
+    let svr = amino_types::vote::SignVoteRequest {
+        vote: Some(amino_types::vote::Vote {
+            vote_type: 0x01,
+            height: 12345,
+            round: 2,
+            timestamp: None,
+            block_id: None,
+            validator_address: vec![
+                0xa3, 0xb2, 0xcc, 0xdd, 0x71, 0x86, 0xf1, 0x68, 0x5f, 0x21, 0xf2, 0x48, 0x2a, 0xf4,
+                0xfb, 0x34, 0x46, 0xa8, 0x4b, 0x35,
+            ],
+            validator_index: 56789,
+            signature: vec![],
+        }),
+    };
+
+    let mut buf = vec![];
+    svr.encode(&mut buf).unwrap();
+    conn.write_all(&buf).unwrap();
+
+    let len = conn.read(&mut resp_buf).unwrap();
+    assert!(len > 0, "connection closed instead of refusing the vote");
+
+    let actual_len = extract_actual_len(&resp_buf).unwrap();
+    let v_resp = vote::SignedVoteResponse::decode(&resp_buf[..actual_len as usize])
+        .expect("decoding vote failed");
+
+    let err = v_resp.err.expect("expected an error");
+    assert!(
+        err.description.starts_with("timestamp-out-of-bounds: "),
+        "{}",
+        err.description
+    );
+}
+
+#[test]
+fn test_sign_bytes_with_signing_keys() {
+    let dir = tempfile::tempdir().unwrap();
+
+    // Two consensus keys: one for proposals and one for votes
+    let keys = [3u8, 4]
+        .iter()
+        .map(|&seed_byte| {
+            let seed = ed25519::Seed::from_bytes([seed_byte; 32]).unwrap();
+            let path = dir.path().join(format!("signing-{}.key", seed_byte));
+            fs::write(
+                &path,
+                subtle_encoding::base64::encode(seed.as_secret_slice()),
+            )
+            .unwrap();
+
+            let public_key = Ed25519Signer::from(&seed).public_key().unwrap();
+            (path, public_key)
+        })
+        .collect::<Vec<_>>();
+
+    let kms = KmsProcess::create_unix_with(|socket_path| {
+        let mut config_file = NamedTempFile::new().unwrap();
+        let key_hex = |public_key: &ed25519::PublicKey| {
+            tendermint::PublicKey::from_raw_ed25519(public_key.as_bytes())
+                .unwrap()
+                .to_hex()
+        };
+
+        writeln!(
+            config_file,
+            r#"
+            [[chain]]
+            id = "test_chain_id"
+            key_format = {{ type = "hex" }}
+            enabled = true
+            state_file = "{}"
+            sign_bytes_domain = "example-domain:"
+            signing_keys = {{ proposal = "{}", vote = "{}" }}
+
+            [[validator]]
+            addr = "unix://{}"
+            chain_id = "test_chain_id"
+
+            [[providers.softsign]]
+            chain_ids = ["test_chain_id"]
+            key_format = "base64"
+            path = "{}"
+
+            [[providers.softsign]]
+            chain_ids = ["test_chain_id"]
+            key_format = "base64"
+            path = "{}"
+        "#,
+            dir.path().join("state.json").display(),
+            key_hex(&keys[0].1),
+            key_hex(&keys[1].1),
+            socket_path,
+            keys[0].0.display(),
+            keys[1].0.display()
+        )
+        .unwrap();
+
+        config_file
+    });
+
+    let mut conn = kms.create_connection();
+    let msg = b"example-domain:hello";
+
+    let mut buf = vec![];
+    SignBytesRequest {
+        bytes: msg.to_vec(),
+    }
+    .encode(&mut buf)
+    .unwrap();
+    conn.write_all(&buf).unwrap();
+
+    let mut resp_buf = vec![0u8; 1024];
+    let len = conn.read(&mut resp_buf).unwrap();
+    assert!(len > 0, "connection closed instead of signing");
+
+    let actual_len = extract_actual_len(&resp_buf).unwrap();
+    let sb_resp = SignedBytesResponse::decode(&resp_buf[..actual_len as usize])
+        .expect("decoding signed bytes response failed");
+    assert!(sb_resp.err.is_none(), "{:?}", sb_resp.err);
+
+    // Signed by the vote key
+    let signature = ed25519::Signature::from_bytes(&sb_resp.signature).unwrap();
+    Ed25519Verifier::from(&keys[1].1)
+        .verify(msg, &signature)
+        .unwrap();
+}
@@ -16,6 +16,7 @@ CFG_TEMPLATE=$(cat <<-EOF
 [[chain]]
 id = "CHAIN_ID"
 key_format = { type = "bech32", account_key_prefix = "cosmospub", consensus_key_prefix = "cosmosvalconspub" }
+enabled = true
 
 [[validator]]
 addr = "VALIDATOR_ADDR"